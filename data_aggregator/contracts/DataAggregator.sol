@@ -8,6 +8,22 @@ contract DataAggregator {
     address owner;
     address utils;
 
+    // The metadata returned by getMetadata, for one pair in a batch.
+    // If it couldn't be fetched (e.g. the address isn't a pair), success
+    // is false and every other field is left empty.
+    struct PairMetadata {
+        bool success;
+        address factoryAddress;
+        address token0Address;
+        string token0Name;
+        string token0Symbol;
+        uint8 token0Decimals;
+        address token1Address;
+        string token1Name;
+        string token1Symbol;
+        uint8 token1Decimals;
+    }
+
     constructor (address _utils) {
         owner = msg.sender;
         utils = _utils;
@@ -36,6 +52,31 @@ contract DataAggregator {
         (token1Name, token1Symbol, token1Decimals) = getTokenMetadata(token1Address);
     }
 
+    // Given many pair addresses, return the metadata for each pair,
+    // without failing the whole batch when one pair's metadata can't be fetched.
+    function getMetadataBatch (address[] calldata pairAddresses) external view returns (
+        PairMetadata[] memory metadata)
+    {
+        metadata = new PairMetadata[](pairAddresses.length);
+
+        for (uint i = 0; i < pairAddresses.length; i++) {
+            try this.getMetadataStruct(pairAddresses[i]) returns (PairMetadata memory pairMetadata) {
+                metadata[i] = pairMetadata;
+            } catch (bytes memory) {}
+        }
+    }
+
+    // The same as getMetadata, but returned as a struct, so getMetadataBatch
+    // can call it externally and catch its failures.
+    function getMetadataStruct (address pairAddress) external view returns (
+        PairMetadata memory metadata)
+    {
+        metadata.success = true;
+        (metadata.factoryAddress, metadata.token0Address, metadata.token1Address) = getPairMetadata(pairAddress);
+        (metadata.token0Name, metadata.token0Symbol, metadata.token0Decimals) = getTokenMetadata(metadata.token0Address);
+        (metadata.token1Name, metadata.token1Symbol, metadata.token1Decimals) = getTokenMetadata(metadata.token1Address);
+    }
+
     function getPairMetadata (address pairAddress) private view returns (
         address factoryAddress,
         address token0Address,
@@ -0,0 +1,16 @@
+//SPDX-License-Identifier: Unlicense
+pragma solidity ^0.8.4;
+
+// A token that returns its name and symbol as bytes32 instead of string,
+// like MKR, for testing that the DataAggregator still decodes them.
+contract Bytes32Token {
+    bytes32 public name;
+    bytes32 public symbol;
+    uint8 public decimals;
+
+    constructor (bytes32 _name, bytes32 _symbol, uint8 _decimals) {
+        name = _name;
+        symbol = _symbol;
+        decimals = _decimals;
+    }
+}
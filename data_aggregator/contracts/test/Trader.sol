@@ -0,0 +1,56 @@
+//SPDX-License-Identifier: Unlicense
+pragma solidity ^0.8.4;
+
+interface ITestToken {
+    function balanceOf (address owner) external view returns (uint256);
+    function transfer (address to, uint256 value) external returns (bool);
+}
+
+interface IUniswapV2Pair {
+    function token0 () external view returns (address);
+    function token1 () external view returns (address);
+    function getReserves () external view returns (uint112, uint112, uint32);
+    function swap (uint256 amount0Out, uint256 amount1Out, address to, bytes calldata data) external;
+}
+
+// Swaps on a Uniswap V2 pair directly, the way a sandwich bot's contract does,
+// so the pair's Swap events name this contract as both sender and recipient.
+contract Trader {
+    address owner;
+    address lastTokenOut;
+    uint256 lastAmountOut;
+
+    constructor () {
+        owner = msg.sender;
+    }
+
+    // Sell exactly `amountIn` of `tokenIn` for as much of the other token as the pair gives.
+    function swapExactIn (address pair, address tokenIn, uint256 amountIn) public {
+        require (msg.sender == owner);
+
+        (uint112 reserve0, uint112 reserve1, ) = IUniswapV2Pair(pair).getReserves();
+        bool zeroForOne = IUniswapV2Pair(pair).token0() == tokenIn;
+        (uint256 reserveIn, uint256 reserveOut) = zeroForOne
+            ? (uint256(reserve0), uint256(reserve1))
+            : (uint256(reserve1), uint256(reserve0));
+
+        // The pair's own pricing, with its 0.3% fee.
+        uint256 amountInWithFee = amountIn * 997;
+        uint256 amountOut = amountInWithFee * reserveOut / (reserveIn * 1000 + amountInWithFee);
+
+        ITestToken(tokenIn).transfer(pair, amountIn);
+        lastTokenOut = zeroForOne ? IUniswapV2Pair(pair).token1() : IUniswapV2Pair(pair).token0();
+        lastAmountOut = amountOut;
+
+        if (zeroForOne) {
+            IUniswapV2Pair(pair).swap(0, amountOut, address(this), "");
+        } else {
+            IUniswapV2Pair(pair).swap(amountOut, 0, address(this), "");
+        }
+    }
+
+    // Sell everything bought by the previous swap, e.g. to close a frontrun.
+    function reverseLast (address pair) external {
+        swapExactIn(pair, lastTokenOut, lastAmountOut);
+    }
+}
@@ -10,6 +10,11 @@ module.exports = {
     ]
   },
   networks: {
+    // The local node used by Sandwich Lab's end-to-end tests reports
+    // Ethereum's chain id, so the app will serve and scan it as Ethereum.
+    hardhat: {
+      chainId: 1
+    },
     arbitrum: {
       url: process.env.ARBITRUM_URL,
       accounts: [process.env.PRIVATE_KEY]
@@ -0,0 +1,180 @@
+const hre = require("hardhat");
+const fs = require("fs");
+const UniswapV2Factory = require("@uniswap/v2-core/build/UniswapV2Factory.json");
+const UniswapV2Pair = require("@uniswap/v2-core/build/UniswapV2Pair.json");
+
+// This script sets up the chain for Sandwich Lab's end-to-end tests
+// (see `tests/e2e.rs`), and should be run against a local node, e.g.
+// `E2E_OUTPUT=e2e.json npx hardhat run scripts/e2e.js --network localhost`.
+// It writes the deployed addresses and every scripted sandwich to E2E_OUTPUT.
+
+// Sandwich Lab only scans pairs from factories it knows, so the local
+// factory's code is copied to the Uniswap V2 factory's Ethereum address.
+const FACTORY_ADDRESS = "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f";
+
+const { parseUnits } = hre.ethers.utils;
+const gwei = (n) => parseUnits(n.toString(), "gwei");
+
+async function main() {
+  const [deployer, attacker, victim, bystander] = await hre.ethers.getSigners();
+
+  const DataUtils = await hre.ethers.getContractFactory("DataUtils");
+  const dataUtils = await DataUtils.deploy();
+  await dataUtils.deployed();
+
+  const DataAggregator = await hre.ethers.getContractFactory("DataAggregator");
+  const dataAggregator = await DataAggregator.deploy(dataUtils.address);
+  await dataAggregator.deployed();
+
+  const Factory = new hre.ethers.ContractFactory(
+    UniswapV2Factory.abi, UniswapV2Factory.bytecode, deployer);
+  const localFactory = await Factory.deploy(deployer.address);
+  await localFactory.deployed();
+
+  await hre.network.provider.send("hardhat_setCode", [
+    FACTORY_ADDRESS, await hre.ethers.provider.getCode(localFactory.address)]);
+  const factory = new hre.ethers.Contract(FACTORY_ADDRESS, UniswapV2Factory.abi, deployer);
+
+  // Create a WETH/USDC pair with 1,000 WETH and 2,000,000 USDC of liquidity.
+  const TestToken = await hre.ethers.getContractFactory("TestToken");
+  const weth = await TestToken.deploy("Wrapped Ether", "WETH", 18);
+  const usdc = await TestToken.deploy("USD Coin", "USDC", 6);
+  await weth.deployed();
+  await usdc.deployed();
+
+  await (await factory.createPair(weth.address, usdc.address)).wait();
+  const pairAddress = await factory.getPair(weth.address, usdc.address);
+  const pair = new hre.ethers.Contract(pairAddress, UniswapV2Pair.abi, deployer);
+
+  await (await weth.mint(pairAddress, parseUnits("1000", 18))).wait();
+  await (await usdc.mint(pairAddress, parseUnits("2000000", 6))).wait();
+  await (await pair.mint(deployer.address)).wait();
+
+  // A batch tolerates entries that aren't pairs.
+  const batch = await dataAggregator.getMetadataBatch([pairAddress, weth.address]);
+
+  if (!batch[0].success || batch[0].factoryAddress != FACTORY_ADDRESS || batch[1].success) {
+    throw new Error("getMetadataBatch did not return the expected metadata");
+  }
+
+  // Each account trades through its own Trader contract, funded with both tokens.
+  const Trader = await hre.ethers.getContractFactory("Trader");
+  const traders = {};
+
+  for (const [name, signer] of [["attacker", attacker], ["victim", victim], ["bystander", bystander]]) {
+    const trader = await Trader.connect(signer).deploy();
+    await trader.deployed();
+    await (await weth.mint(trader.address, parseUnits("500", 18))).wait();
+    await (await usdc.mint(trader.address, parseUnits("1000000", 6))).wait();
+    traders[name] = trader;
+  }
+
+  const usdcIn = (trader, amount, tip) =>
+    ({ trader, method: "swapExactIn", args: [pairAddress, usdc.address, parseUnits(amount, 6)], tip });
+  const wethIn = (trader, amount, tip) =>
+    ({ trader, method: "swapExactIn", args: [pairAddress, weth.address, parseUnits(amount, 18)], tip });
+  const reverseLast = (trader, tip) =>
+    ({ trader, method: "reverseLast", args: [pairAddress], tip });
+
+  const blocks = [];
+
+  blocks.push(await mineBlock(
+    "The attacker buys WETH ahead of the victim's buy, and sells it right after.",
+    [
+      usdcIn(traders.attacker, "40000", 3),
+      usdcIn(traders.victim, "9000", 2),
+      reverseLast(traders.attacker, 1)
+    ],
+    [[0, [1], 2]]));
+
+  blocks.push(await mineBlock(
+    "Ordinary traffic, with no sandwich.",
+    [
+      usdcIn(traders.victim, "12000", 3),
+      wethIn(traders.bystander, "2", 2),
+      usdcIn(traders.victim, "700", 1)
+    ],
+    []));
+
+  blocks.push(await mineBlock(
+    "The attacker sells WETH ahead of two sells, around an opposite-direction bystander.",
+    [
+      wethIn(traders.attacker, "25", 5),
+      wethIn(traders.victim, "3", 4),
+      usdcIn(traders.bystander, "5000", 3),
+      wethIn(traders.victim, "3", 2),
+      reverseLast(traders.attacker, 1)
+    ],
+    [[0, [1, 2, 3], 4]]));
+
+  blocks.push(await mineBlock(
+    "The attacker round-trips around an opposite-direction swap, so there is no victim.",
+    [
+      usdcIn(traders.attacker, "25000", 3),
+      wethIn(traders.bystander, "10", 2),
+      reverseLast(traders.attacker, 1)
+    ],
+    []));
+
+  // Bury the scripted blocks under enough confirmations to be scanned.
+  await hre.network.provider.send("hardhat_mine", ["0x20"]);
+
+  const output = {
+    data_aggregator: dataAggregator.address.toLowerCase(),
+    factory: FACTORY_ADDRESS.toLowerCase(),
+    pair: pairAddress.toLowerCase(),
+    base: (await pair.token0()) == weth.address ? "WETH" : "USDC",
+    quote: (await pair.token0()) == weth.address ? "USDC" : "WETH",
+    blocks
+  };
+
+  fs.writeFileSync(process.env.E2E_OUTPUT || "e2e.json", JSON.stringify(output, null, 2));
+  console.log(`Scripted ${blocks.length} blocks on pair ${pairAddress}`);
+}
+
+// Send the given swaps into a single block, ordered by their priority fees,
+// and return the block with its sandwiches, given as [frontrun, [lunchmeat], backrun]
+// indexes into `swaps`. Throws if the block isn't mined in the expected order.
+async function mineBlock(note, swaps, sandwiches) {
+  await hre.network.provider.send("evm_setAutomine", [false]);
+
+  const hashes = [];
+
+  for (const swap of swaps) {
+    const tx = await swap.trader[swap.method](...swap.args, {
+      gasLimit: 300000,
+      maxFeePerGas: gwei(100),
+      maxPriorityFeePerGas: gwei(swap.tip)
+    });
+    hashes.push(tx.hash);
+  }
+
+  await hre.network.provider.send("evm_mine", []);
+  await hre.network.provider.send("evm_setAutomine", [true]);
+
+  const receipts = await Promise.all(hashes.map((hash) => hre.ethers.provider.getTransactionReceipt(hash)));
+  const blockNumber = receipts[0].blockNumber;
+
+  receipts.forEach((receipt, i) => {
+    if (receipt.blockNumber != blockNumber || receipt.transactionIndex != i || receipt.status != 1) {
+      throw new Error(`swap ${i} of block ${blockNumber} was not mined as scripted`);
+    }
+  });
+
+  return {
+    block_number: blockNumber,
+    note,
+    sandwiches: sandwiches.map(([frontrun, lunchmeat, backrun]) => ({
+      frontrun: hashes[frontrun],
+      lunchmeat: lunchmeat.map((i) => hashes[i]),
+      backrun: hashes[backrun]
+    }))
+  };
+}
+
+main()
+  .then(() => process.exit(0))
+  .catch((error) => {
+    console.error(error);
+    process.exit(1);
+});
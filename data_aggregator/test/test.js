@@ -1,5 +1,6 @@
 const { expect } = require("chai");
 const { ethers } = require("hardhat");
+const UniswapV2Factory = require("@uniswap/v2-core/build/UniswapV2Factory.json");
 
 // These tests should be run via the following command:
 // `npx hardhat test --network mainnet`
@@ -96,4 +97,57 @@ describe("DataAggregator", function () {
     expect(t1sym).to.equal("WETH");
     expect(t1d).to.equal(18);
   });
-}); 
+});
+
+// These tests deploy everything they need on the local Hardhat network,
+// so they should be run via `npx hardhat test` (without `--network`).
+describe("DataAggregator batches", function () {
+  it("should return every pair's metadata in one batch, even when a token \
+  returns bytes32 instead of string, and flag entries that aren't pairs", async function () {
+    const [deployer] = await hre.ethers.getSigners();
+    const { formatBytes32String } = hre.ethers.utils;
+
+    const DataUtils = await hre.ethers.getContractFactory("DataUtils");
+    const dataUtils = await DataUtils.deploy();
+    await dataUtils.deployed();
+
+    const DataAggregator = await hre.ethers.getContractFactory("DataAggregator");
+    const dataAggregator = await DataAggregator.deploy(dataUtils.address);
+    await dataAggregator.deployed();
+
+    const Factory = new hre.ethers.ContractFactory(
+      UniswapV2Factory.abi, UniswapV2Factory.bytecode, deployer);
+    const factory = await Factory.deploy(deployer.address);
+    await factory.deployed();
+
+    // A MKR-like token, whose name and symbol are bytes32, paired with WETH.
+    const TestToken = await hre.ethers.getContractFactory("TestToken");
+    const weth = await TestToken.deploy("Wrapped Ether", "WETH", 18);
+    await weth.deployed();
+
+    const Bytes32Token = await hre.ethers.getContractFactory("Bytes32Token");
+    const mkr = await Bytes32Token.deploy(formatBytes32String("Maker"), formatBytes32String("MKR"), 18);
+    await mkr.deployed();
+
+    await (await factory.createPair(mkr.address, weth.address)).wait();
+    const pairAddress = await factory.getPair(mkr.address, weth.address);
+
+    const [pair, notPair] = await dataAggregator.getMetadataBatch([pairAddress, weth.address]);
+
+    expect(pair.success).to.equal(true);
+    expect(pair.factoryAddress).to.equal(factory.address);
+
+    // The factory orders the tokens by address.
+    const tokens = {
+      [pair.token0Address]: [pair.token0Name, pair.token0Symbol, pair.token0Decimals],
+      [pair.token1Address]: [pair.token1Name, pair.token1Symbol, pair.token1Decimals]
+    };
+
+    expect(tokens[mkr.address]).to.deep.equal(["Maker", "MKR", 18]);
+    expect(tokens[weth.address]).to.deep.equal(["Wrapped Ether", "WETH", 18]);
+
+    // WETH isn't a pair, so its entry fails without failing the batch.
+    expect(notPair.success).to.equal(false);
+    expect(notPair.factoryAddress).to.equal(hre.ethers.constants.AddressZero);
+  });
+});
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE lunchmeat_transactions DROP COLUMN is_victim;
@@ -0,0 +1,3 @@
+-- Mark whether each lunchmeat transaction traded in the frontrun's direction
+-- (a true victim) or in the opposite direction (a bystander).
+ALTER TABLE lunchmeat_transactions ADD COLUMN is_victim BOOLEAN NOT NULL DEFAULT 't';
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE multipool_victim_transactions;
+DROP TABLE multipool_sandwich_pairs;
+DROP TABLE multipool_sandwiches;
+DROP TABLE block_scans;
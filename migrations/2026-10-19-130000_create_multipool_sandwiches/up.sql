@@ -0,0 +1,39 @@
+-- Your SQL goes here
+CREATE TABLE block_scans (
+    block_scan_id BIGSERIAL PRIMARY KEY,
+    blockchain_str_id VARCHAR (32) NOT NULL,
+    block_number BIGINT NOT NULL CHECK (block_number >= 0),
+    scan_complete BOOLEAN NOT NULL DEFAULT 'f',
+    scan_failed BOOLEAN NOT NULL DEFAULT 'f',
+    UNIQUE (blockchain_str_id, block_number)
+);
+
+CREATE TABLE multipool_sandwiches (
+    multipool_sandwich_id BIGSERIAL PRIMARY KEY,
+    blockchain_str_id VARCHAR (32) NOT NULL,
+    block_number BIGINT NOT NULL CHECK (block_number >= 0),
+    attacker_address CHAR (42) NOT NULL,
+    frontrun_tx_hash CHAR (66) NOT NULL,
+    frontrun_tx_index INT NOT NULL CHECK (frontrun_tx_index >= 0),
+    backrun_tx_hash CHAR (66) NOT NULL,
+    backrun_tx_index INT NOT NULL CHECK (backrun_tx_index >= 0)
+);
+
+CREATE INDEX multipool_sandwich_block_number ON multipool_sandwiches (blockchain_str_id, block_number);
+
+CREATE TABLE multipool_sandwich_pairs (
+    multipool_sandwich_id BIGINT NOT NULL REFERENCES multipool_sandwiches (multipool_sandwich_id) ON DELETE CASCADE,
+    pair_id INT NOT NULL REFERENCES pairs (pair_id) ON DELETE CASCADE,
+    PRIMARY KEY (multipool_sandwich_id, pair_id)
+);
+
+CREATE INDEX multipool_sandwich_pair_pair_id ON multipool_sandwich_pairs (pair_id);
+
+CREATE TABLE multipool_victim_transactions (
+    multipool_victim_id BIGSERIAL PRIMARY KEY,
+    tx_hash CHAR (66) NOT NULL,
+    tx_index INT NOT NULL CHECK (tx_index >= 0),
+    multipool_sandwich_id BIGINT NOT NULL REFERENCES multipool_sandwiches (multipool_sandwich_id) ON DELETE CASCADE
+);
+
+CREATE INDEX multipool_victim_sandwich_id ON multipool_victim_transactions (multipool_sandwich_id);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE jit_swap_transactions;
+DROP TABLE jit_attacks;
@@ -0,0 +1,35 @@
+-- Your SQL goes here
+CREATE TABLE jit_attacks (
+    jit_attack_id BIGSERIAL PRIMARY KEY,
+    pair_id INT NOT NULL REFERENCES pairs (pair_id) ON DELETE CASCADE,
+    block_number BIGINT NOT NULL CHECK (block_number >= 0),
+    owner_address CHAR (42) NOT NULL,
+    tick_lower INT NOT NULL,
+    tick_upper INT NOT NULL,
+    liquidity DOUBLE PRECISION NOT NULL,
+    mint_tx_hash CHAR (66) NOT NULL,
+    mint_tx_index INT NOT NULL CHECK (mint_tx_index >= 0),
+    burn_tx_hash CHAR (66) NOT NULL,
+    burn_tx_index INT NOT NULL CHECK (burn_tx_index >= 0),
+    base_minted DOUBLE PRECISION NOT NULL,
+    quote_minted DOUBLE PRECISION NOT NULL,
+    base_burned DOUBLE PRECISION NOT NULL,
+    quote_burned DOUBLE PRECISION NOT NULL,
+    base_fees DOUBLE PRECISION NOT NULL,
+    quote_fees DOUBLE PRECISION NOT NULL
+);
+
+CREATE INDEX jit_attack_block_number ON jit_attacks (pair_id, block_number);
+
+CREATE TABLE jit_swap_transactions (
+    jit_swap_id BIGSERIAL PRIMARY KEY,
+    tx_hash CHAR (66) NOT NULL,
+    tx_index INT NOT NULL CHECK (tx_index >= 0),
+    base_in DOUBLE PRECISION NOT NULL,
+    quote_in DOUBLE PRECISION NOT NULL,
+    base_out DOUBLE PRECISION NOT NULL,
+    quote_out DOUBLE PRECISION NOT NULL,
+    jit_attack_id BIGINT NOT NULL REFERENCES jit_attacks (jit_attack_id) ON DELETE CASCADE
+);
+
+CREATE INDEX jit_swap_attack_id ON jit_swap_transactions (jit_attack_id);
@@ -0,0 +1,23 @@
+-- Your SQL goes here
+CREATE TABLE arbitrages (
+    arbitrage_id BIGSERIAL PRIMARY KEY,
+    blockchain_str_id VARCHAR (32) NOT NULL,
+    block_number BIGINT NOT NULL CHECK (block_number >= 0),
+    tx_hash CHAR (66) NOT NULL,
+    tx_index INT NOT NULL CHECK (tx_index >= 0),
+    arbitrage_kind VARCHAR (16) NOT NULL,
+    searcher_address CHAR (42) NOT NULL,
+    profit_token_id INT NOT NULL REFERENCES tokens (token_id) ON DELETE CASCADE,
+    profit DOUBLE PRECISION NOT NULL,
+    target_tx_hash CHAR (66),
+    target_tx_index INT CHECK (target_tx_index >= 0)
+);
+
+CREATE INDEX arbitrage_block_number ON arbitrages (blockchain_str_id, block_number);
+
+CREATE TABLE arbitrage_pairs (
+    arbitrage_id BIGINT NOT NULL REFERENCES arbitrages (arbitrage_id) ON DELETE CASCADE,
+    hop INT NOT NULL CHECK (hop >= 0),
+    pair_id INT NOT NULL REFERENCES pairs (pair_id) ON DELETE CASCADE,
+    PRIMARY KEY (arbitrage_id, hop)
+);
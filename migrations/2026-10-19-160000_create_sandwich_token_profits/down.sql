@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE sandwich_token_profits;
+ALTER TABLE sandwiches DROP COLUMN attacker_address;
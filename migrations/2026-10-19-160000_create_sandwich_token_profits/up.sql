@@ -0,0 +1,13 @@
+-- Sandwiches scanned before this migration have no attacker or token profits.
+ALTER TABLE sandwiches ADD COLUMN attacker_address CHAR (42);
+
+-- The attacker's net balance change per token across the frontrun and backrun.
+-- `amount` is scaled by the token's decimals when the token is known
+-- (i.e. `token_id` is set), and is in raw token units otherwise.
+CREATE TABLE sandwich_token_profits (
+    sandwich_id BIGINT NOT NULL REFERENCES sandwiches (sandwich_id) ON DELETE CASCADE,
+    token_address CHAR (42) NOT NULL,
+    token_id INT REFERENCES tokens (token_id) ON DELETE SET NULL,
+    amount DOUBLE PRECISION NOT NULL,
+    PRIMARY KEY (sandwich_id, token_address)
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE sandwiches DROP COLUMN bribe_tx_hash;
+ALTER TABLE sandwiches DROP COLUMN bribe;
@@ -0,0 +1,5 @@
+-- The native token paid directly to the block's fee recipient, and the
+-- separate transaction that paid it (if it wasn't paid by a leg itself).
+-- Sandwiches scanned before this migration have no bribe recorded.
+ALTER TABLE sandwiches ADD COLUMN bribe DOUBLE PRECISION;
+ALTER TABLE sandwiches ADD COLUMN bribe_tx_hash CHAR (66);
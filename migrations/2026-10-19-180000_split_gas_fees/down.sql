@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE backrun_transactions DROP COLUMN gas_tip;
+ALTER TABLE backrun_transactions DROP COLUMN gas_burned;
+ALTER TABLE backrun_transactions DROP COLUMN gas_used;
+ALTER TABLE lunchmeat_transactions DROP COLUMN gas_tip;
+ALTER TABLE lunchmeat_transactions DROP COLUMN gas_burned;
+ALTER TABLE lunchmeat_transactions DROP COLUMN gas_used;
+ALTER TABLE frontrun_transactions DROP COLUMN gas_tip;
+ALTER TABLE frontrun_transactions DROP COLUMN gas_burned;
+ALTER TABLE frontrun_transactions DROP COLUMN gas_used;
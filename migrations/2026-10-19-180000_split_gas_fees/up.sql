@@ -0,0 +1,13 @@
+-- Split each transaction's gas fee into the part burned and the priority tip.
+-- Transactions scanned before this migration have neither recorded.
+ALTER TABLE frontrun_transactions ADD COLUMN gas_used BIGINT CHECK (gas_used >= 0);
+ALTER TABLE frontrun_transactions ADD COLUMN gas_burned DOUBLE PRECISION;
+ALTER TABLE frontrun_transactions ADD COLUMN gas_tip DOUBLE PRECISION;
+
+ALTER TABLE lunchmeat_transactions ADD COLUMN gas_used BIGINT CHECK (gas_used >= 0);
+ALTER TABLE lunchmeat_transactions ADD COLUMN gas_burned DOUBLE PRECISION;
+ALTER TABLE lunchmeat_transactions ADD COLUMN gas_tip DOUBLE PRECISION;
+
+ALTER TABLE backrun_transactions ADD COLUMN gas_used BIGINT CHECK (gas_used >= 0);
+ALTER TABLE backrun_transactions ADD COLUMN gas_burned DOUBLE PRECISION;
+ALTER TABLE backrun_transactions ADD COLUMN gas_tip DOUBLE PRECISION;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE sandwiches DROP COLUMN block_id;
+DROP TABLE blocks;
@@ -0,0 +1,19 @@
+-- The header data of every block that holds a sandwich.
+-- `miner` is the block's fee recipient (`coinbase`), which since the merge
+-- is usually the address of the builder that built the block.
+-- `base_fee` is in wei, and is unset for blocks before EIP-1559.
+CREATE TABLE blocks (
+    block_id BIGSERIAL PRIMARY KEY,
+    blockchain_str_id VARCHAR (32) NOT NULL,
+    block_number BIGINT NOT NULL CHECK (block_number >= 0),
+    block_hash CHAR (66) NOT NULL,
+    miner CHAR (42) NOT NULL,
+    block_timestamp BIGINT NOT NULL CHECK (block_timestamp >= 0),
+    base_fee BIGINT CHECK (base_fee >= 0),
+    UNIQUE (blockchain_str_id, block_number)
+);
+
+CREATE INDEX block_miner ON blocks (blockchain_str_id, miner);
+
+-- Sandwiches scanned before this migration aren't linked to a block.
+ALTER TABLE sandwiches ADD COLUMN block_id BIGINT REFERENCES blocks (block_id) ON DELETE SET NULL;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE ranges DROP COLUMN swaps_stored;
+DROP TABLE swaps;
@@ -0,0 +1,25 @@
+-- Every swap decoded while scanning a pair's ranges, so that detection can be
+-- re-run without fetching logs again. Raw amounts are decimal strings, since
+-- they don't fit in a BIGINT. The price fields are only set for V3 swaps.
+CREATE TABLE swaps (
+    swap_id BIGSERIAL PRIMARY KEY,
+    pair_id INT NOT NULL REFERENCES pairs (pair_id) ON DELETE CASCADE,
+    block_number BIGINT NOT NULL CHECK (block_number >= 0),
+    tx_hash CHAR (66) NOT NULL,
+    tx_index INT NOT NULL CHECK (tx_index >= 0),
+    log_index INT NOT NULL CHECK (log_index >= 0),
+    sender CHAR (42) NOT NULL,
+    recipient CHAR (42) NOT NULL,
+    amount0_in VARCHAR (78) NOT NULL,
+    amount1_in VARCHAR (78) NOT NULL,
+    amount0_out VARCHAR (78) NOT NULL,
+    amount1_out VARCHAR (78) NOT NULL,
+    sqrt_price_x96 VARCHAR (78),
+    liquidity VARCHAR (39),
+    tick INT,
+    UNIQUE (pair_id, block_number, log_index)
+);
+
+-- Ranges created before this migration were scanned without storing their swaps.
+ALTER TABLE ranges ADD COLUMN swaps_stored BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE ranges ALTER COLUMN swaps_stored SET DEFAULT TRUE;
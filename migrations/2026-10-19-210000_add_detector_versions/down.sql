@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX sandwich_detector_version;
+ALTER TABLE ranges DROP COLUMN detector_version;
+ALTER TABLE sandwiches DROP COLUMN superseded;
+ALTER TABLE sandwiches DROP COLUMN detector_version;
@@ -0,0 +1,12 @@
+-- The detector (and its revision and settings) that found each sandwich,
+-- and that was last run over each range. Results found before detectors
+-- were versioned are marked 'unversioned'. Sandwiches are superseded,
+-- rather than deleted, when their range is re-detected.
+ALTER TABLE sandwiches ADD COLUMN detector_version VARCHAR (64) NOT NULL DEFAULT 'unversioned';
+ALTER TABLE sandwiches ALTER COLUMN detector_version DROP DEFAULT;
+ALTER TABLE sandwiches ADD COLUMN superseded BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE ranges ADD COLUMN detector_version VARCHAR (64) NOT NULL DEFAULT 'unversioned';
+ALTER TABLE ranges ALTER COLUMN detector_version DROP DEFAULT;
+
+CREATE INDEX sandwich_detector_version ON sandwiches (pair_id, detector_version, block_number);
@@ -0,0 +1,13 @@
+-- Factories that aren't configured as exchanges, but whose pairs were probed
+-- and found to have a V2 (`getReserves`) or V3 (`slot0`, `fee`) interface.
+-- They're only served while a blockchain allows unverified exchanges,
+-- unless an operator has marked them as verified.
+CREATE TABLE exchanges (
+    exchange_id SERIAL PRIMARY KEY,
+    blockchain_str_id VARCHAR (32) NOT NULL,
+    factory_address CHAR (42) NOT NULL,
+    exchange_name VARCHAR (64) NOT NULL,
+    exchange_kind VARCHAR (2) NOT NULL CHECK (exchange_kind IN ('v2', 'v3')),
+    verified BOOLEAN NOT NULL DEFAULT FALSE,
+    UNIQUE (blockchain_str_id, factory_address)
+);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE factory_scans;
+DROP INDEX pair_quote_token_id;
+DROP INDEX pair_base_token_id;
+ALTER TABLE pairs DROP COLUMN fee_tier;
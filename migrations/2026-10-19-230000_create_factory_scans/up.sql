@@ -0,0 +1,17 @@
+-- The fee tier (in hundredths of a basis point) of V3 pools found from their
+-- factory's PoolCreated events. It's unset for V2 pairs, and for V3 pools
+-- that were added by address.
+ALTER TABLE pairs ADD COLUMN fee_tier INT CHECK (fee_tier >= 0);
+
+-- Pairs are looked up by their tokens once they're discovered from factories.
+CREATE INDEX pair_base_token_id ON pairs (base_token_id);
+CREATE INDEX pair_quote_token_id ON pairs (quote_token_id);
+
+-- How far each factory's PairCreated or PoolCreated events have been indexed.
+CREATE TABLE factory_scans (
+    factory_scan_id SERIAL PRIMARY KEY,
+    blockchain_str_id VARCHAR (32) NOT NULL,
+    factory_address CHAR (42) NOT NULL,
+    last_block BIGINT NOT NULL CHECK (last_block >= 0),
+    UNIQUE (blockchain_str_id, factory_address)
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX token_address_prefix;
+DROP INDEX token_name_prefix;
+DROP INDEX token_symbol_prefix;
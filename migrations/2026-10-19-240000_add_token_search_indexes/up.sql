@@ -0,0 +1,5 @@
+-- Tokens are searched by case-insensitive prefixes of their symbol, name
+-- or address (e.g. `lower(token_symbol) LIKE 'wet%'`), which these serve.
+CREATE INDEX token_symbol_prefix ON tokens (lower(token_symbol) varchar_pattern_ops);
+CREATE INDEX token_name_prefix ON tokens (lower(token_name) varchar_pattern_ops);
+CREATE INDEX token_address_prefix ON tokens (token_address bpchar_pattern_ops);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE backrun_transactions DROP COLUMN gas_l1_fee;
+ALTER TABLE lunchmeat_transactions DROP COLUMN gas_l1_fee;
+ALTER TABLE frontrun_transactions DROP COLUMN gas_l1_fee;
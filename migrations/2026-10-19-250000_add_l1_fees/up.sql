@@ -0,0 +1,5 @@
+-- Record the part of each transaction's gas fee that paid for posting it to L1,
+-- on rollups. Transactions scanned before this migration have none recorded.
+ALTER TABLE frontrun_transactions ADD COLUMN gas_l1_fee DOUBLE PRECISION;
+ALTER TABLE lunchmeat_transactions ADD COLUMN gas_l1_fee DOUBLE PRECISION;
+ALTER TABLE backrun_transactions ADD COLUMN gas_l1_fee DOUBLE PRECISION;
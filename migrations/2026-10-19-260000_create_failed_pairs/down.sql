@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX failed_pair_factory;
+DROP TABLE failed_pairs;
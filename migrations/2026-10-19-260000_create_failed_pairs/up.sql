@@ -0,0 +1,16 @@
+-- Pairs found in a factory's PairCreated or PoolCreated events whose metadata
+-- couldn't be fetched (e.g. because a token has no `decimals()`). The factory's
+-- scan moves past them, so they're retried from here on later runs instead.
+CREATE TABLE failed_pairs (
+    failed_pair_id SERIAL PRIMARY KEY,
+    blockchain_str_id VARCHAR (32) NOT NULL,
+    factory_address CHAR (42) NOT NULL,
+    pair_address CHAR (42) NOT NULL,
+    token0_address CHAR (42) NOT NULL,
+    token1_address CHAR (42) NOT NULL,
+    fee_tier INT CHECK (fee_tier >= 0),
+    attempts INT NOT NULL DEFAULT 1 CHECK (attempts >= 1),
+    UNIQUE (blockchain_str_id, pair_address)
+);
+
+CREATE INDEX failed_pair_factory ON failed_pairs (blockchain_str_id, factory_address);
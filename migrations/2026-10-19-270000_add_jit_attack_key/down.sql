@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE jit_attacks DROP CONSTRAINT jit_attack_transactions;
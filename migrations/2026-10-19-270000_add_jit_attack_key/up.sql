@@ -0,0 +1,11 @@
+-- A JIT liquidity attack is identified by its pair and its Mint and Burn
+-- transactions, so that scanning a range again can't save it twice.
+-- Keep only the first copy of any attack that was already saved twice.
+DELETE FROM jit_attacks a
+    USING jit_attacks b
+    WHERE a.pair_id = b.pair_id
+        AND a.mint_tx_hash = b.mint_tx_hash
+        AND a.burn_tx_hash = b.burn_tx_hash
+        AND a.jit_attack_id > b.jit_attack_id;
+
+ALTER TABLE jit_attacks ADD CONSTRAINT jit_attack_transactions UNIQUE (pair_id, mint_tx_hash, burn_tx_hash);
@@ -2,8 +2,9 @@ use diesel::prelude::*;
 use diesel::r2d2::ConnectionManager;
 use diesel::result::Error as DbError;
 use diesel::dsl::max;
-use diesel::{ insert_into, update/*, delete*/ };
+use diesel::{ insert_into, update, delete };
 use std::env;
+use std::collections::HashMap;
 use r2d2;
 use super::models::{ 
     Token, 
@@ -12,7 +13,20 @@ use super::models::{
     Sandwich, 
     FrontrunTransaction, 
     LunchmeatTransaction, 
-    BackrunTransaction };
+    BackrunTransaction,
+    BlockScan,
+    Block,
+    DetectedExchange,
+    FailedPair,
+    MultipoolSandwich,
+    MultipoolVictimTransaction,
+    JitAttack,
+    JitSwapTransaction,
+    Arbitrage,
+    SandwichTokenProfit,
+    Swap };
+
+sql_function!(fn lower(x: diesel::sql_types::Text) -> diesel::sql_types::Text);
 
 pub type Pool = r2d2::Pool<ConnectionManager<PgConnection>>;
 pub type DbConnection = r2d2::PooledConnection<ConnectionManager<PgConnection>>;
@@ -21,6 +35,12 @@ pub type DbConnection = r2d2::PooledConnection<ConnectionManager<PgConnection>>;
 // throughout the entire application.
 pub fn init_db_pool() -> Pool {
     let db_url = env::var("DATABASE_URL").expect("DATABASE_URL environment variable not found");
+    connect_db_pool(&db_url)
+}
+
+// Create a database pool for the database at the given url,
+// and run any pending migrations on it.
+pub fn connect_db_pool(db_url: &str) -> Pool {
     let manager = ConnectionManager::<PgConnection>::new(db_url);
     let pool = Pool::new(manager).expect("error creating db pool");
 
@@ -88,6 +108,36 @@ pub fn insert_token(
         .get_result(db_connection)
 }
 
+// Fetch the tokens with the given token ids.
+pub fn fetch_tokens_by_ids(
+    db_connection: &DbConnection,
+    tids: &[i32]
+) -> Result<Vec<Token>, DbError> {
+    use crate::api::schema::tokens::dsl::*;
+
+    tokens
+        .filter(token_id.eq_any(tids))
+        .load::<Token>(db_connection)
+}
+
+// Fetch the id of the token with the given parameters,
+// inserting the token first if it doesn't exist yet.
+pub fn fetch_or_insert_token(
+    db_connection: &DbConnection,
+    token_nm: &str,
+    token_sym: &str,
+    token_dec: i16,
+    blockchain_id: &str,
+    token_addr: &str
+) -> Result<i32, DbError> {
+    match fetch_token_by_params(db_connection, blockchain_id, token_addr) {
+        Ok(token) => Ok(token.token_id),
+        Err(DbError::NotFound) => insert_token(
+            db_connection, token_nm, token_sym, token_dec, blockchain_id, token_addr),
+        Err(e) => Err(e)
+    }
+}
+
 // Fetch the pair with the given parameters,
 // or return `Err(NotFound)`.
 pub fn fetch_pair_by_params(
@@ -105,7 +155,7 @@ pub fn fetch_pair_by_params(
 
 // Fetch the pair with the given `pair_id`,
 // or return `Err(NotFound)`.
-/*pub fn fetch_pair_by_id(
+pub fn fetch_pair_by_id(
     db_connection: &DbConnection,
     pid: i32
 ) -> Result<Pair, DbError> {
@@ -114,7 +164,72 @@ pub fn fetch_pair_by_params(
     pairs
         .find(pid)
         .first(db_connection)
-}*/
+}
+
+// Fetch every pair on the given blockchain.
+pub fn fetch_pairs_by_blockchain(
+    db_connection: &DbConnection,
+    blockchain_id: &str
+) -> Result<Vec<Pair>, DbError> {
+    use crate::api::schema::pairs::dsl::*;
+
+    pairs
+        .filter(blockchain_str_id.eq(blockchain_id.to_lowercase()))
+        .order(pair_id)
+        .load::<Pair>(db_connection)
+}
+
+// Fetch up to `limit` pairs on the given factories (by blockchain id) with a
+// token whose symbol, name or address starts with the given prefix (ignoring
+// case). Pairs with a token whose symbol is exactly the prefix come first,
+// then pairs in the order they were saved.
+pub fn search_pairs(
+    db_connection: &DbConnection,
+    factories: &HashMap<String, Vec<String>>,
+    prefix: &str,
+    limit: i64
+) -> Result<Vec<Pair>, DbError> {
+    use crate::api::schema::pairs::dsl::*;
+    use crate::api::schema::tokens;
+
+    if factories.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Match the prefix literally, rather than as a pattern.
+    let prefix = prefix.to_lowercase();
+    let pattern = format!("{}%", prefix
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_"));
+
+    let matching_tokens = tokens::table
+        .select(tokens::token_id)
+        .filter(lower(tokens::token_symbol).like(pattern.clone())
+            .or(lower(tokens::token_name).like(pattern.clone()))
+            .or(tokens::token_address.like(pattern)));
+
+    let exact_tokens = tokens::table
+        .select(tokens::token_id)
+        .filter(lower(tokens::token_symbol).eq(prefix));
+
+    // Only the given factories' pairs on each blockchain.
+    let mut query = pairs.into_boxed();
+
+    for (blockchain_id, factory_addrs) in factories.iter() {
+        query = query.or_filter(blockchain_str_id.eq(blockchain_id.to_lowercase())
+            .and(factory_address.eq_any(factory_addrs.iter().map(|a| a.to_lowercase()).collect::<Vec<String>>())));
+    }
+
+    query
+        .filter(base_token_id.eq_any(matching_tokens.clone())
+            .or(quote_token_id.eq_any(matching_tokens)))
+        .order((base_token_id.eq_any(exact_tokens.clone())
+            .or(quote_token_id.eq_any(exact_tokens))).desc())
+        .then_order_by(pair_id)
+        .limit(limit)
+        .load::<Pair>(db_connection)
+}
 
 // Take the parameters for a new pair;
 // then insert it and return the new `pair_id`.
@@ -124,7 +239,8 @@ pub fn insert_pair(
     factory_addr: &str,
     pair_addr: &str,
     base_id: i32,
-    quote_id: i32
+    quote_id: i32,
+    fee: Option<i32>
 ) -> Result<i32, DbError> {
     use crate::api::schema::pairs::dsl::*;
 
@@ -133,7 +249,8 @@ pub fn insert_pair(
         factory_address.eq(factory_addr.to_lowercase()),
         pair_address.eq(pair_addr.to_lowercase()),
         base_token_id.eq(base_id),
-        quote_token_id.eq(quote_id)
+        quote_token_id.eq(quote_id),
+        fee_tier.eq(fee)
     );
 
     insert_into(pairs)
@@ -142,6 +259,168 @@ pub fn insert_pair(
         .get_result(db_connection)
 }
 
+// Set the fee tier of the pair with the given `pair_id`,
+// unless it already has one.
+pub fn update_pair_fee_tier(
+    db_connection: &DbConnection,
+    pid: i32,
+    fee: i32
+) -> Result<usize, DbError> {
+    use crate::api::schema::pairs::dsl::*;
+
+    update(pairs.filter(pair_id.eq(pid)).filter(fee_tier.is_null()))
+        .set(fee_tier.eq(fee))
+        .execute(db_connection)
+}
+
+// Fetch every detected exchange on the given blockchain.
+pub fn fetch_detected_exchanges(
+    db_connection: &DbConnection,
+    blockchain_id: &str
+) -> Result<Vec<DetectedExchange>, DbError> {
+    use crate::api::schema::exchanges::dsl::*;
+
+    exchanges
+        .filter(blockchain_str_id.eq(blockchain_id.to_lowercase()))
+        .order(exchange_id)
+        .load::<DetectedExchange>(db_connection)
+}
+
+// Fetch the detected exchange with the given factory address,
+// or insert it (as unverified) if it doesn't exist yet.
+pub fn fetch_or_insert_detected_exchange(
+    db_connection: &DbConnection,
+    blockchain_id: &str,
+    factory_addr: &str,
+    name: &str,
+    kind: &str
+) -> Result<DetectedExchange, DbError> {
+    use crate::api::schema::exchanges::dsl::*;
+
+    let existing = exchanges
+        .filter(blockchain_str_id.eq(blockchain_id.to_lowercase()))
+        .filter(factory_address.eq(factory_addr.to_lowercase()))
+        .first(db_connection);
+
+    match existing {
+        Ok(exchange) => Ok(exchange),
+        Err(DbError::NotFound) => {
+            let values = (
+                blockchain_str_id.eq(blockchain_id.to_lowercase()),
+                factory_address.eq(factory_addr.to_lowercase()),
+                exchange_name.eq(name),
+                exchange_kind.eq(kind)
+            );
+
+            insert_into(exchanges)
+                .values(values)
+                .get_result(db_connection)
+        },
+        Err(e) => Err(e)
+    }
+}
+
+// Fetch the last block whose PairCreated or PoolCreated events have been
+// indexed from the given factory, or `None` if it hasn't been indexed yet.
+pub fn fetch_factory_scan_last_block(
+    db_connection: &DbConnection,
+    blockchain_id: &str,
+    factory_addr: &str
+) -> Result<Option<i64>, DbError> {
+    use crate::api::schema::factory_scans::dsl::*;
+
+    factory_scans
+        .filter(blockchain_str_id.eq(blockchain_id.to_lowercase()))
+        .filter(factory_address.eq(factory_addr.to_lowercase()))
+        .select(last_block)
+        .first(db_connection)
+        .optional()
+}
+
+// Record that the given factory's events have been indexed up to `block`.
+pub fn upsert_factory_scan(
+    db_connection: &DbConnection,
+    blockchain_id: &str,
+    factory_addr: &str,
+    block: i64
+) -> Result<usize, DbError> {
+    use crate::api::schema::factory_scans::dsl::*;
+
+    let values = (
+        blockchain_str_id.eq(blockchain_id.to_lowercase()),
+        factory_address.eq(factory_addr.to_lowercase()),
+        last_block.eq(block)
+    );
+
+    insert_into(factory_scans)
+        .values(values)
+        .on_conflict((blockchain_str_id, factory_address))
+        .do_update()
+        .set(last_block.eq(block))
+        .execute(db_connection)
+}
+
+// Fetch the given factory's pairs whose metadata couldn't be fetched
+// yet, leaving out those that have failed `max_attempts` times.
+pub fn fetch_failed_pairs(
+    db_connection: &DbConnection,
+    blockchain_id: &str,
+    factory_addr: &str,
+    max_attempts: i32
+) -> Result<Vec<FailedPair>, DbError> {
+    use crate::api::schema::failed_pairs::dsl::*;
+
+    failed_pairs
+        .filter(blockchain_str_id.eq(blockchain_id.to_lowercase()))
+        .filter(factory_address.eq(factory_addr.to_lowercase()))
+        .filter(attempts.lt(max_attempts))
+        .order(failed_pair_id)
+        .load::<FailedPair>(db_connection)
+}
+
+// Record that the given pair's metadata couldn't be fetched,
+// counting another attempt if it has failed before.
+pub fn upsert_failed_pair(
+    db_connection: &DbConnection,
+    blockchain_id: &str,
+    factory_addr: &str,
+    pair_addr: &str,
+    (token0, token1): (&str, &str),
+    fee: Option<i32>
+) -> Result<usize, DbError> {
+    use crate::api::schema::failed_pairs::dsl::*;
+
+    let values = (
+        blockchain_str_id.eq(blockchain_id.to_lowercase()),
+        factory_address.eq(factory_addr.to_lowercase()),
+        pair_address.eq(pair_addr.to_lowercase()),
+        token0_address.eq(token0.to_lowercase()),
+        token1_address.eq(token1.to_lowercase()),
+        fee_tier.eq(fee)
+    );
+
+    insert_into(failed_pairs)
+        .values(values)
+        .on_conflict((blockchain_str_id, pair_address))
+        .do_update()
+        .set(attempts.eq(attempts + 1))
+        .execute(db_connection)
+}
+
+// Forget that the given pair's metadata couldn't be fetched.
+pub fn delete_failed_pair(
+    db_connection: &DbConnection,
+    blockchain_id: &str,
+    pair_addr: &str
+) -> Result<usize, DbError> {
+    use crate::api::schema::failed_pairs::dsl::*;
+
+    delete(failed_pairs
+        .filter(blockchain_str_id.eq(blockchain_id.to_lowercase()))
+        .filter(pair_address.eq(pair_addr.to_lowercase())))
+        .execute(db_connection)
+}
+
 // Given a `block_number` and a `pair_id`, find the range,
 // if any, that contains that block.
 pub fn find_encompassing_range(
@@ -158,6 +437,26 @@ pub fn find_encompassing_range(
         .first(db_connection)
 }
 
+// Fetch every pair on the given blockchain with a completed range
+// that contains the given block.
+pub fn fetch_pairs_with_complete_range(
+    db_connection: &DbConnection,
+    blockchain_id: &str,
+    block_number: i64
+) -> Result<Vec<Pair>, DbError> {
+    use crate::api::schema::{ pairs, ranges };
+
+    pairs::table
+        .inner_join(ranges::table)
+        .filter(pairs::blockchain_str_id.eq(blockchain_id.to_lowercase()))
+        .filter(ranges::lower_bound.le(block_number))
+        .filter(ranges::upper_bound.ge(block_number))
+        .filter(ranges::scan_complete.eq(true))
+        .select(pairs::all_columns)
+        .distinct()
+        .load::<Pair>(db_connection)
+}
+
 // Given a `block_number` and a `pair_id`, find the upper bound
 // of the range, if any, that immediately precedes that block.
 pub fn find_preceding_range_upper_bound(
@@ -181,7 +480,8 @@ pub fn insert_range(
     lb: i64,
     ub: i64,
     complete: bool,
-    failed: bool
+    failed: bool,
+    version: &str
 ) -> Result<Range, DbError> {
     use crate::api::schema::ranges::dsl::*;
 
@@ -190,7 +490,8 @@ pub fn insert_range(
         lower_bound.eq(lb),
         upper_bound.eq(ub),
         scan_complete.eq(complete),
-        scan_failed.eq(failed)
+        scan_failed.eq(failed),
+        detector_version.eq(version)
     );
 
     insert_into(ranges)
@@ -198,6 +499,52 @@ pub fn insert_range(
         .get_result(db_connection)
 }
 
+// Fetch every completed range for a given `pair_id`, latest first.
+pub fn fetch_complete_ranges_by_pair_id(
+    db_connection: &DbConnection,
+    pid: i32
+) -> Result<Vec<Range>, DbError> {
+    use crate::api::schema::ranges::dsl::*;
+
+    ranges
+        .filter(pair_id.eq(pid))
+        .filter(scan_complete.eq(true))
+        .order(upper_bound.desc())
+        .load::<Range>(db_connection)
+}
+
+// Fetch every completed range with stored swaps on the given blockchain,
+// along with its pair, for re-detection.
+pub fn fetch_redetectable_ranges(
+    db_connection: &DbConnection,
+    blockchain_id: &str
+) -> Result<Vec<(Pair, Range)>, DbError> {
+    use crate::api::schema::{ pairs, ranges };
+
+    pairs::table
+        .inner_join(ranges::table)
+        .filter(pairs::blockchain_str_id.eq(blockchain_id.to_lowercase()))
+        .filter(ranges::scan_complete.eq(true))
+        .filter(ranges::swaps_stored.eq(true))
+        .select((pairs::all_columns, ranges::all_columns))
+        .order(ranges::range_id)
+        .load::<(Pair, Range)>(db_connection)
+}
+
+// Record the detector version most recently run over a range.
+pub fn update_range_detector_version(
+    db_connection: &DbConnection,
+    rid: i64,
+    version: &str
+) -> Result<i64, DbError> {
+    use crate::api::schema::ranges::dsl::*;
+
+    update(ranges.filter(range_id.eq(rid)))
+        .set(detector_version.eq(version))
+        .returning(range_id)
+        .get_result(db_connection)
+}
+
 // Update a range's scan-related metadata.
 pub fn update_range_metadata(
     db_connection: &DbConnection,
@@ -224,16 +571,22 @@ pub fn update_range_metadata(
 }*/
 
 // Fetch all sandwiches for a given `pair_id` and `block_number` range
-// or return `Err(NotFound)`.
+// or return `Err(NotFound)`. Only the current (i.e. not superseded)
+// sandwiches are fetched, unless a detector `version` is given.
 pub fn fetch_all_sandwiches_by_params(
     db_connection: &DbConnection,
     pid: i32,
     min_ge_block: Option<i64>,
-    max_le_block: Option<i64>
+    max_le_block: Option<i64>,
+    version: Option<&str>
 ) -> Result<Vec<Sandwich>, DbError> {
     use crate::api::schema::sandwiches::dsl::*;
 
-    let base_query = sandwiches.filter(pair_id.eq(pid));
+    let base_query = sandwiches.filter(pair_id.eq(pid)).into_boxed();
+    let base_query = match version {
+        Some(version) => base_query.filter(detector_version.eq(version.to_string())),
+        None => base_query.filter(superseded.eq(false))
+    };
 
     if let Some(min_block) = min_ge_block {
         if let Some(max_block) = max_le_block {
@@ -274,13 +627,22 @@ pub fn fetch_all_sandwiches_by_params(
 pub fn insert_sandwich(
     db_connection: &DbConnection,
     pid: i32,
-    block: i64
+    block: i64,
+    attacker: &str,
+    (brb, brb_hash): (f64, Option<&str>),
+    bid: Option<i64>,
+    version: &str
 ) -> Result<Sandwich, DbError> {
     use crate::api::schema::sandwiches::dsl::*;
 
     let values = (
         pair_id.eq(pid),
-        block_number.eq(block)
+        block_number.eq(block),
+        attacker_address.eq(attacker.to_lowercase()),
+        bribe.eq(brb),
+        bribe_tx_hash.eq(brb_hash),
+        block_id.eq(bid),
+        detector_version.eq(version)
     );
 
     insert_into(sandwiches)
@@ -288,6 +650,83 @@ pub fn insert_sandwich(
         .get_result(db_connection)
 }
 
+// Mark every current sandwich for a given `pair_id` in the given
+// block range as superseded, e.g. before the range is re-detected.
+pub fn supersede_sandwiches(
+    db_connection: &DbConnection,
+    pid: i32,
+    min_block: i64,
+    max_block: i64
+) -> Result<usize, DbError> {
+    use crate::api::schema::sandwiches::dsl::*;
+
+    update(sandwiches
+        .filter(pair_id.eq(pid))
+        .filter(block_number.between(min_block, max_block))
+        .filter(superseded.eq(false)))
+        .set(superseded.eq(true))
+        .execute(db_connection)
+}
+
+// Delete every sandwich (and its transactions) for a given `pair_id` in the given block.
+pub fn delete_sandwiches_by_block(
+    db_connection: &DbConnection,
+    pid: i32,
+    block: i64
+) -> Result<usize, DbError> {
+    use crate::api::schema::sandwiches::dsl::*;
+
+    delete(sandwiches.filter(pair_id.eq(pid)).filter(block_number.eq(block)))
+        .execute(db_connection)
+}
+
+// Fetch the attacker's per-token profits for a given `sandwich_id`.
+pub fn fetch_sandwich_token_profits_by_sandwich_id(
+    db_connection: &DbConnection,
+    sid: i64
+) -> Result<Vec<SandwichTokenProfit>, DbError> {
+    use crate::api::schema::sandwich_token_profits::dsl::*;
+
+    sandwich_token_profits
+        .filter(sandwich_id.eq(sid))
+        .order(token_address)
+        .load::<SandwichTokenProfit>(db_connection)
+}
+
+// Insert the attacker's profit in a single token for a given `sandwich_id`.
+pub fn insert_sandwich_token_profit(
+    db_connection: &DbConnection,
+    sid: i64,
+    token_addr: &str,
+    tid: Option<i32>,
+    amt: f64
+) -> Result<usize, DbError> {
+    use crate::api::schema::sandwich_token_profits::dsl::*;
+
+    let values = (
+        sandwich_id.eq(sid),
+        token_address.eq(token_addr.to_lowercase()),
+        token_id.eq(tid),
+        amount.eq(amt)
+    );
+
+    insert_into(sandwich_token_profits)
+        .values(values)
+        .execute(db_connection)
+}
+
+// The gas fees paid by a sandwich transaction, in the native token:
+// the total fee, the parts burned and tipped to the block's builder,
+// and, on rollups, the part that paid for posting it to L1.
+// All but the total are only known if the transaction's receipt was fetched.
+pub struct GasFees {
+    pub gas: f64,
+    pub gas_used: Option<i64>,
+    pub gas_burned: Option<f64>,
+    pub gas_tip: Option<f64>,
+    pub gas_l1_fee: Option<f64>
+}
+
 // Fetch the frontrun transaction for a given `sandwich_id`
 // or return `Err(NotFound)`.
 pub fn fetch_frontrun_transaction_by_sandwich_id(
@@ -301,16 +740,14 @@ pub fn fetch_frontrun_transaction_by_sandwich_id(
         .first(db_connection)
 }
 
-// Insert a new frontrun transaction.
+// Insert a new frontrun transaction. The amounts are
+// (base in, quote in, base out, quote out).
 pub fn insert_frontrun_transaction(
     db_connection: &DbConnection,
     hash: &str,
     idx: i32,
-    t0_in: f64,
-    t1_in: f64,
-    t0_out: f64,
-    t1_out: f64,
-    gs: f64,
+    amounts: (f64, f64, f64, f64),
+    fees: &GasFees,
     sid: i64
 ) -> Result<FrontrunTransaction, DbError> {
     use crate::api::schema::frontrun_transactions::dsl::*;
@@ -318,11 +755,15 @@ pub fn insert_frontrun_transaction(
     let values = (
         tx_hash.eq(hash),
         tx_index.eq(idx),
-        base_in.eq(t0_in),
-        quote_in.eq(t1_in),
-        base_out.eq(t0_out),
-        quote_out.eq(t1_out),
-        gas.eq(gs),
+        base_in.eq(amounts.0),
+        quote_in.eq(amounts.1),
+        base_out.eq(amounts.2),
+        quote_out.eq(amounts.3),
+        gas.eq(fees.gas),
+        gas_used.eq(fees.gas_used),
+        gas_burned.eq(fees.gas_burned),
+        gas_tip.eq(fees.gas_tip),
+        gas_l1_fee.eq(fees.gas_l1_fee),
         sandwich_id.eq(sid)
     );
 
@@ -344,29 +785,33 @@ pub fn fetch_lunchmeat_transactions_by_sandwich_id(
         .load::<LunchmeatTransaction>(db_connection)
 }
 
-// Insert a new lunchmeat transaction.
+// Insert a new lunchmeat transaction. The amounts are
+// (base in, quote in, base out, quote out).
 pub fn insert_lunchmeat_transaction(
     db_connection: &DbConnection,
     hash: &str,
     idx: i32,
-    t0_in: f64,
-    t1_in: f64,
-    t0_out: f64,
-    t1_out: f64,
-    gs: f64,
-    sid: i64
+    amounts: (f64, f64, f64, f64),
+    fees: &GasFees,
+    sid: i64,
+    victim: bool
 ) -> Result<LunchmeatTransaction, DbError> {
     use crate::api::schema::lunchmeat_transactions::dsl::*;
 
     let values = (
         tx_hash.eq(hash),
         tx_index.eq(idx),
-        base_in.eq(t0_in),
-        quote_in.eq(t1_in),
-        base_out.eq(t0_out),
-        quote_out.eq(t1_out),
-        gas.eq(gs),
-        sandwich_id.eq(sid)
+        base_in.eq(amounts.0),
+        quote_in.eq(amounts.1),
+        base_out.eq(amounts.2),
+        quote_out.eq(amounts.3),
+        gas.eq(fees.gas),
+        gas_used.eq(fees.gas_used),
+        gas_burned.eq(fees.gas_burned),
+        gas_tip.eq(fees.gas_tip),
+        gas_l1_fee.eq(fees.gas_l1_fee),
+        sandwich_id.eq(sid),
+        is_victim.eq(victim)
     );
 
     insert_into(lunchmeat_transactions)
@@ -387,16 +832,14 @@ pub fn fetch_backrun_transaction_by_sandwich_id(
         .first(db_connection)
 }
 
-// Insert a new backrun transaction.
+// Insert a new backrun transaction. The amounts are
+// (base in, quote in, base out, quote out).
 pub fn insert_backrun_transaction(
     db_connection: &DbConnection,
     hash: &str,
     idx: i32,
-    t0_in: f64,
-    t1_in: f64,
-    t0_out: f64,
-    t1_out: f64,
-    gs: f64,
+    amounts: (f64, f64, f64, f64),
+    fees: &GasFees,
     sid: i64
 ) -> Result<BackrunTransaction, DbError> {
     use crate::api::schema::backrun_transactions::dsl::*;
@@ -404,15 +847,614 @@ pub fn insert_backrun_transaction(
     let values = (
         tx_hash.eq(hash),
         tx_index.eq(idx),
-        base_in.eq(t0_in),
-        quote_in.eq(t1_in),
-        base_out.eq(t0_out),
-        quote_out.eq(t1_out),
-        gas.eq(gs),
+        base_in.eq(amounts.0),
+        quote_in.eq(amounts.1),
+        base_out.eq(amounts.2),
+        quote_out.eq(amounts.3),
+        gas.eq(fees.gas),
+        gas_used.eq(fees.gas_used),
+        gas_burned.eq(fees.gas_burned),
+        gas_tip.eq(fees.gas_tip),
+        gas_l1_fee.eq(fees.gas_l1_fee),
         sandwich_id.eq(sid)
     );
 
     insert_into(backrun_transactions)
         .values(values)
         .get_result(db_connection)
-}
\ No newline at end of file
+}
+
+// Fetch the block scan with the given parameters,
+// or return `Err(NotFound)`.
+pub fn fetch_block_scan_by_params(
+    db_connection: &DbConnection,
+    blockchain_id: &str,
+    block: i64
+) -> Result<BlockScan, DbError> {
+    use crate::api::schema::block_scans::dsl::*;
+
+    block_scans
+        .filter(blockchain_str_id.eq(blockchain_id.to_lowercase()))
+        .filter(block_number.eq(block))
+        .first(db_connection)
+}
+
+// Insert a new block scan.
+pub fn insert_block_scan(
+    db_connection: &DbConnection,
+    blockchain_id: &str,
+    block: i64,
+    complete: bool,
+    failed: bool
+) -> Result<BlockScan, DbError> {
+    use crate::api::schema::block_scans::dsl::*;
+
+    let values = (
+        blockchain_str_id.eq(blockchain_id.to_lowercase()),
+        block_number.eq(block),
+        scan_complete.eq(complete),
+        scan_failed.eq(failed)
+    );
+
+    insert_into(block_scans)
+        .values(values)
+        .get_result(db_connection)
+}
+
+// Update a block scan's scan-related metadata.
+pub fn update_block_scan_metadata(
+    db_connection: &DbConnection,
+    bsid: i64,
+    complete: bool,
+    failed: bool
+) -> Result<i64, DbError> {
+    use crate::api::schema::block_scans::dsl::*;
+
+    update(block_scans.filter(block_scan_id.eq(bsid)))
+        .set((scan_complete.eq(complete), scan_failed.eq(failed)))
+        .returning(block_scan_id)
+        .get_result(db_connection)
+}
+
+// Clear the failed flag of the given block scan so it can be retried. Returns
+// `false` if the scan hadn't failed, e.g. because another request reset it first.
+pub fn reset_failed_block_scan(
+    db_connection: &DbConnection,
+    bsid: i64
+) -> Result<bool, DbError> {
+    use crate::api::schema::block_scans::dsl::*;
+
+    update(block_scans.filter(block_scan_id.eq(bsid)).filter(scan_failed.eq(true)))
+        .set((scan_complete.eq(false), scan_failed.eq(false)))
+        .execute(db_connection)
+        .map(|updated| updated > 0)
+}
+
+// Fetch the block with the given parameters,
+// or return `Err(NotFound)`.
+pub fn fetch_block_by_params(
+    db_connection: &DbConnection,
+    blockchain_id: &str,
+    block: i64
+) -> Result<Block, DbError> {
+    use crate::api::schema::blocks::dsl::*;
+
+    blocks
+        .filter(blockchain_str_id.eq(blockchain_id.to_lowercase()))
+        .filter(block_number.eq(block))
+        .first(db_connection)
+}
+
+// Fetch the block with the given `block_id`,
+// or return `Err(NotFound)`.
+pub fn fetch_block_by_id(
+    db_connection: &DbConnection,
+    bid: i64
+) -> Result<Block, DbError> {
+    use crate::api::schema::blocks::dsl::*;
+
+    blocks
+        .find(bid)
+        .first(db_connection)
+}
+
+// Fetch the block with the given parameters,
+// or insert it if it doesn't exist yet.
+pub fn fetch_or_insert_block(
+    db_connection: &DbConnection,
+    blockchain_id: &str,
+    block: i64,
+    hash: &str,
+    fee_recipient: &str,
+    timestamp: i64,
+    fee: Option<i64>
+) -> Result<Block, DbError> {
+    use crate::api::schema::blocks::dsl::*;
+
+    match fetch_block_by_params(db_connection, blockchain_id, block) {
+        Ok(db_block) => Ok(db_block),
+        Err(DbError::NotFound) => {
+            let values = (
+                blockchain_str_id.eq(blockchain_id.to_lowercase()),
+                block_number.eq(block),
+                block_hash.eq(hash.to_lowercase()),
+                miner.eq(fee_recipient.to_lowercase()),
+                block_timestamp.eq(timestamp),
+                base_fee.eq(fee)
+            );
+
+            insert_into(blocks)
+                .values(values)
+                .get_result(db_connection)
+        },
+        Err(e) => Err(e)
+    }
+}
+
+// Fetch every block on the given blockchain from block `since` onwards.
+pub fn fetch_blocks_since(
+    db_connection: &DbConnection,
+    blockchain_id: &str,
+    since: i64
+) -> Result<Vec<Block>, DbError> {
+    use crate::api::schema::blocks::dsl::*;
+
+    blocks
+        .filter(blockchain_str_id.eq(blockchain_id.to_lowercase()))
+        .filter(block_number.ge(since))
+        .order(block_number)
+        .load::<Block>(db_connection)
+}
+
+// Fetch the hash recorded for the block given by `bid`, locking its row
+// until the end of the current transaction.
+pub fn lock_block_hash(
+    db_connection: &DbConnection,
+    bid: i64
+) -> Result<String, DbError> {
+    use crate::api::schema::blocks::dsl::*;
+
+    blocks
+        .filter(block_id.eq(bid))
+        .select(block_hash)
+        .for_update()
+        .first(db_connection)
+}
+
+// Replace the header data of the block given by `bid`, e.g. after a reorg.
+pub fn update_block_header(
+    db_connection: &DbConnection,
+    bid: i64,
+    hash: &str,
+    fee_recipient: &str,
+    timestamp: i64,
+    fee: Option<i64>
+) -> Result<Block, DbError> {
+    use crate::api::schema::blocks::dsl::*;
+
+    let values = (
+        block_hash.eq(hash.to_lowercase()),
+        miner.eq(fee_recipient.to_lowercase()),
+        block_timestamp.eq(timestamp),
+        base_fee.eq(fee)
+    );
+
+    update(blocks.filter(block_id.eq(bid)))
+        .set(values)
+        .get_result(db_connection)
+}
+
+// Fetch the fee recipient, block id and bribe of every current sandwich on the
+// given blockchain that is linked to a block, optionally within a block range.
+pub fn fetch_sandwich_miners(
+    db_connection: &DbConnection,
+    blockchain_id: &str,
+    min_ge_block: Option<i64>,
+    max_le_block: Option<i64>
+) -> Result<Vec<(String, i64, Option<f64>)>, DbError> {
+    use crate::api::schema::{ blocks, sandwiches };
+
+    sandwiches::table
+        .inner_join(blocks::table)
+        .filter(blocks::blockchain_str_id.eq(blockchain_id.to_lowercase()))
+        .filter(blocks::block_number.ge(min_ge_block.unwrap_or(0)))
+        .filter(blocks::block_number.le(max_le_block.unwrap_or(i64::MAX)))
+        .filter(sandwiches::superseded.eq(false))
+        .select((blocks::miner, blocks::block_id, sandwiches::bribe))
+        .load::<(String, i64, Option<f64>)>(db_connection)
+}
+
+// Fetch all multi-pool sandwiches in the given block.
+pub fn fetch_multipool_sandwiches_by_params(
+    db_connection: &DbConnection,
+    blockchain_id: &str,
+    block: i64
+) -> Result<Vec<MultipoolSandwich>, DbError> {
+    use crate::api::schema::multipool_sandwiches::dsl::*;
+
+    multipool_sandwiches
+        .filter(blockchain_str_id.eq(blockchain_id.to_lowercase()))
+        .filter(block_number.eq(block))
+        .order(frontrun_tx_index)
+        .load::<MultipoolSandwich>(db_connection)
+}
+
+// Insert a new multi-pool sandwich. The frontrun and backrun are (hash, index) tuples.
+pub fn insert_multipool_sandwich(
+    db_connection: &DbConnection,
+    blockchain_id: &str,
+    block: i64,
+    attacker: &str,
+    (frontrun_hash, frontrun_idx): (&str, i32),
+    (backrun_hash, backrun_idx): (&str, i32)
+) -> Result<MultipoolSandwich, DbError> {
+    use crate::api::schema::multipool_sandwiches::dsl::*;
+
+    let values = (
+        blockchain_str_id.eq(blockchain_id.to_lowercase()),
+        block_number.eq(block),
+        attacker_address.eq(attacker.to_lowercase()),
+        frontrun_tx_hash.eq(frontrun_hash),
+        frontrun_tx_index.eq(frontrun_idx),
+        backrun_tx_hash.eq(backrun_hash),
+        backrun_tx_index.eq(backrun_idx)
+    );
+
+    insert_into(multipool_sandwiches)
+        .values(values)
+        .get_result(db_connection)
+}
+
+// Fetch the addresses of every pair affected by the given multi-pool sandwich.
+pub fn fetch_multipool_sandwich_pair_addresses(
+    db_connection: &DbConnection,
+    msid: i64
+) -> Result<Vec<String>, DbError> {
+    use crate::api::schema::multipool_sandwich_pairs;
+    use crate::api::schema::pairs;
+
+    multipool_sandwich_pairs::table
+        .inner_join(pairs::table)
+        .filter(multipool_sandwich_pairs::multipool_sandwich_id.eq(msid))
+        .select(pairs::pair_address)
+        .order(pairs::pair_address)
+        .load::<String>(db_connection)
+}
+
+// Link a multi-pool sandwich to a pair it affected.
+pub fn insert_multipool_sandwich_pair(
+    db_connection: &DbConnection,
+    msid: i64,
+    pid: i32
+) -> Result<usize, DbError> {
+    use crate::api::schema::multipool_sandwich_pairs::dsl::*;
+
+    let values = (
+        multipool_sandwich_id.eq(msid),
+        pair_id.eq(pid)
+    );
+
+    insert_into(multipool_sandwich_pairs)
+        .values(values)
+        .execute(db_connection)
+}
+
+// Fetch all victim transactions for a given `multipool_sandwich_id`.
+pub fn fetch_multipool_victim_transactions_by_sandwich_id(
+    db_connection: &DbConnection,
+    msid: i64
+) -> Result<Vec<MultipoolVictimTransaction>, DbError> {
+    use crate::api::schema::multipool_victim_transactions::dsl::*;
+
+    multipool_victim_transactions
+        .filter(multipool_sandwich_id.eq(msid))
+        .order(tx_index)
+        .load::<MultipoolVictimTransaction>(db_connection)
+}
+
+// Insert a new multi-pool victim transaction.
+pub fn insert_multipool_victim_transaction(
+    db_connection: &DbConnection,
+    hash: &str,
+    idx: i32,
+    msid: i64
+) -> Result<MultipoolVictimTransaction, DbError> {
+    use crate::api::schema::multipool_victim_transactions::dsl::*;
+
+    let values = (
+        tx_hash.eq(hash),
+        tx_index.eq(idx),
+        multipool_sandwich_id.eq(msid)
+    );
+
+    insert_into(multipool_victim_transactions)
+        .values(values)
+        .get_result(db_connection)
+}
+
+// Fetch all JIT liquidity attacks on the given pair between the given blocks (inclusive).
+pub fn fetch_jit_attacks_by_params(
+    db_connection: &DbConnection,
+    pid: i32,
+    min_block: i64,
+    max_block: i64
+) -> Result<Vec<JitAttack>, DbError> {
+    use crate::api::schema::jit_attacks::dsl::*;
+
+    jit_attacks
+        .filter(pair_id.eq(pid))
+        .filter(block_number.between(min_block, max_block))
+        .order((block_number.desc(), mint_tx_index))
+        .load::<JitAttack>(db_connection)
+}
+
+// Delete every JIT liquidity attack (and its swaps) for a given `pair_id` in the given block.
+pub fn delete_jit_attacks_by_block(
+    db_connection: &DbConnection,
+    pid: i32,
+    block: i64
+) -> Result<usize, DbError> {
+    use crate::api::schema::jit_attacks::dsl::*;
+
+    delete(jit_attacks.filter(pair_id.eq(pid)).filter(block_number.eq(block)))
+        .execute(db_connection)
+}
+
+// A JIT liquidity attack to insert. The mint and burn are (hash, index)
+// tuples, and each (base, quote) tuple holds the token amounts minted,
+// burned or earned as fees.
+pub struct NewJitAttack<'a> {
+    pub owner: &'a str,
+    pub ticks: (i32, i32), // (lower, upper)
+    pub liquidity: f64,
+    pub mint: (&'a str, i32),
+    pub burn: (&'a str, i32),
+    pub minted: (f64, f64),
+    pub burned: (f64, f64),
+    pub fees: (f64, f64)
+}
+
+// Insert a new JIT liquidity attack on the given pair in the given block,
+// or return `None` if the attack (by its transactions) is already saved.
+pub fn insert_jit_attack(
+    db_connection: &DbConnection,
+    pid: i32,
+    block: i64,
+    attack: &NewJitAttack
+) -> Result<Option<JitAttack>, DbError> {
+    use crate::api::schema::jit_attacks::dsl::*;
+
+    let values = (
+        pair_id.eq(pid),
+        block_number.eq(block),
+        owner_address.eq(attack.owner.to_lowercase()),
+        tick_lower.eq(attack.ticks.0),
+        tick_upper.eq(attack.ticks.1),
+        liquidity.eq(attack.liquidity),
+        mint_tx_hash.eq(attack.mint.0),
+        mint_tx_index.eq(attack.mint.1),
+        burn_tx_hash.eq(attack.burn.0),
+        burn_tx_index.eq(attack.burn.1),
+        base_minted.eq(attack.minted.0),
+        quote_minted.eq(attack.minted.1),
+        base_burned.eq(attack.burned.0),
+        quote_burned.eq(attack.burned.1),
+        base_fees.eq(attack.fees.0),
+        quote_fees.eq(attack.fees.1)
+    );
+
+    insert_into(jit_attacks)
+        .values(values)
+        .on_conflict((pair_id, mint_tx_hash, burn_tx_hash))
+        .do_nothing()
+        .get_result(db_connection)
+        .optional()
+}
+
+// Fetch the swaps targeted by the given JIT liquidity attack.
+pub fn fetch_jit_swap_transactions_by_attack_id(
+    db_connection: &DbConnection,
+    jid: i64
+) -> Result<Vec<JitSwapTransaction>, DbError> {
+    use crate::api::schema::jit_swap_transactions::dsl::*;
+
+    jit_swap_transactions
+        .filter(jit_attack_id.eq(jid))
+        .order(tx_index)
+        .load::<JitSwapTransaction>(db_connection)
+}
+
+// Insert a new swap targeted by a JIT liquidity attack.
+// The amounts are (base in, quote in, base out, quote out).
+pub fn insert_jit_swap_transaction(
+    db_connection: &DbConnection,
+    hash: &str,
+    idx: i32,
+    amounts: (f64, f64, f64, f64),
+    jid: i64
+) -> Result<JitSwapTransaction, DbError> {
+    use crate::api::schema::jit_swap_transactions::dsl::*;
+
+    let values = (
+        tx_hash.eq(hash),
+        tx_index.eq(idx),
+        base_in.eq(amounts.0),
+        quote_in.eq(amounts.1),
+        base_out.eq(amounts.2),
+        quote_out.eq(amounts.3),
+        jit_attack_id.eq(jid)
+    );
+
+    insert_into(jit_swap_transactions)
+        .values(values)
+        .get_result(db_connection)
+}
+
+// Fetch all arbitrages found in the given block.
+pub fn fetch_arbitrages_by_params(
+    db_connection: &DbConnection,
+    blockchain_id: &str,
+    block: i64
+) -> Result<Vec<Arbitrage>, DbError> {
+    use crate::api::schema::arbitrages::dsl::*;
+
+    arbitrages
+        .filter(blockchain_str_id.eq(blockchain_id.to_lowercase()))
+        .filter(block_number.eq(block))
+        .order(tx_index)
+        .load::<Arbitrage>(db_connection)
+}
+
+// An arbitrage to insert. The transaction and its target are (hash, index)
+// tuples; the target is only set for backrun arbitrages.
+pub struct NewArbitrage<'a> {
+    pub tx: (&'a str, i32),
+    pub kind: &'a str,
+    pub searcher: &'a str,
+    pub profit_token_id: i32,
+    pub profit: f64,
+    pub target: Option<(&'a str, i32)>
+}
+
+// Insert a new arbitrage found in the given block.
+pub fn insert_arbitrage(
+    db_connection: &DbConnection,
+    blockchain_id: &str,
+    block: i64,
+    arbitrage: &NewArbitrage
+) -> Result<Arbitrage, DbError> {
+    use crate::api::schema::arbitrages::dsl::*;
+
+    let values = (
+        blockchain_str_id.eq(blockchain_id.to_lowercase()),
+        block_number.eq(block),
+        tx_hash.eq(arbitrage.tx.0),
+        tx_index.eq(arbitrage.tx.1),
+        arbitrage_kind.eq(arbitrage.kind),
+        searcher_address.eq(arbitrage.searcher.to_lowercase()),
+        profit_token_id.eq(arbitrage.profit_token_id),
+        profit.eq(arbitrage.profit),
+        target_tx_hash.eq(arbitrage.target.map(|(target_hash, _)| target_hash)),
+        target_tx_index.eq(arbitrage.target.map(|(_, target_idx)| target_idx))
+    );
+
+    insert_into(arbitrages)
+        .values(values)
+        .get_result(db_connection)
+}
+
+// Fetch the addresses of the pairs traded by the given arbitrage, in hop order.
+pub fn fetch_arbitrage_pair_addresses(
+    db_connection: &DbConnection,
+    aid: i64
+) -> Result<Vec<String>, DbError> {
+    use crate::api::schema::arbitrage_pairs;
+    use crate::api::schema::pairs;
+
+    arbitrage_pairs::table
+        .inner_join(pairs::table)
+        .filter(arbitrage_pairs::arbitrage_id.eq(aid))
+        .select(pairs::pair_address)
+        .order(arbitrage_pairs::hop)
+        .load::<String>(db_connection)
+}
+
+// Record that the given arbitrage traded the given pair at the given hop.
+pub fn insert_arbitrage_pair(
+    db_connection: &DbConnection,
+    aid: i64,
+    hp: i32,
+    pid: i32
+) -> Result<usize, DbError> {
+    use crate::api::schema::arbitrage_pairs::dsl::*;
+
+    let values = (
+        arbitrage_id.eq(aid),
+        hop.eq(hp),
+        pair_id.eq(pid)
+    );
+
+    insert_into(arbitrage_pairs)
+        .values(values)
+        .execute(db_connection)
+}
+
+// Fetch every stored swap for a given `pair_id` in the given block range,
+// in the order they were logged.
+pub fn fetch_swaps_by_params(
+    db_connection: &DbConnection,
+    pid: i32,
+    min_block: i64,
+    max_block: i64
+) -> Result<Vec<Swap>, DbError> {
+    use crate::api::schema::swaps::dsl::*;
+
+    swaps
+        .filter(pair_id.eq(pid))
+        .filter(block_number.between(min_block, max_block))
+        .order((block_number, log_index))
+        .load::<Swap>(db_connection)
+}
+
+// Delete every stored swap for a given `pair_id` in the given block.
+pub fn delete_swaps_by_block(
+    db_connection: &DbConnection,
+    pid: i32,
+    block: i64
+) -> Result<usize, DbError> {
+    use crate::api::schema::swaps::dsl::*;
+
+    delete(swaps.filter(pair_id.eq(pid)).filter(block_number.eq(block)))
+        .execute(db_connection)
+}
+
+// A decoded swap to store. The amounts are (in0, in1, out0, out1), and the
+// price fields are (sqrt price, liquidity, tick), for V3 swaps only.
+pub struct NewSwap {
+    pub block: i64,
+    pub tx: (String, i32), // (hash, index)
+    pub log_index: i32,
+    pub addresses: (String, String), // (sender, recipient)
+    pub amounts: (String, String, String, String),
+    pub price: (Option<String>, Option<String>, Option<i32>)
+}
+
+// Insert the given decoded swaps on the given pair, a batch at a time,
+// skipping any that are already stored (e.g. if a failed scan is retried).
+pub fn insert_swaps(
+    db_connection: &DbConnection,
+    pid: i32,
+    new_swaps: &[NewSwap]
+) -> Result<usize, DbError> {
+    use crate::api::schema::swaps::dsl::*;
+
+    let values = new_swaps.iter()
+        .map(|swap| (
+            pair_id.eq(pid),
+            block_number.eq(swap.block),
+            tx_hash.eq(swap.tx.0.to_lowercase()),
+            tx_index.eq(swap.tx.1),
+            log_index.eq(swap.log_index),
+            sender.eq(swap.addresses.0.to_lowercase()),
+            recipient.eq(swap.addresses.1.to_lowercase()),
+            amount0_in.eq(&swap.amounts.0),
+            amount1_in.eq(&swap.amounts.1),
+            amount0_out.eq(&swap.amounts.2),
+            amount1_out.eq(&swap.amounts.3),
+            sqrt_price_x96.eq(swap.price.0.as_ref()),
+            liquidity.eq(swap.price.1.as_ref()),
+            tick.eq(swap.price.2)))
+        .collect::<Vec<_>>();
+
+    let mut inserted = 0;
+
+    // Keep each statement well under Postgres' limit on bind parameters.
+    for batch in values.chunks(1_000) {
+        inserted += insert_into(swaps)
+            .values(batch)
+            .on_conflict_do_nothing()
+            .execute(db_connection)?;
+    }
+
+    Ok(inserted)
+}
@@ -0,0 +1,245 @@
+use ethers::types::Address;
+use super::swap::{ Swap, Direction };
+use super::multipool::TxSwaps;
+use super::transfers::{ TokenTransfer, net_flow };
+use super::super::models::Token;
+
+// How an arbitrage was positioned in its block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArbitrageKind {
+    Atomic, // a closed cycle of swaps inside a single transaction
+    Backrun // an atomic arbitrage placed right after a swap it profits from
+}
+
+impl ArbitrageKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Atomic => "atomic",
+            Self::Backrun => "backrun"
+        }
+    }
+}
+
+pub struct Arbitrage<'a> {
+    pub kind: ArbitrageKind,
+    pub transaction: TxSwaps<'a>,
+    pub searcher: Address, // the account that received the profit
+    pub token: &'a Token, // the token the cycle starts and ends in
+    pub profit: f64,
+    pub target: Option<TxSwaps<'a>> // the swap that was backrun, if any
+}
+
+// Find the profitable cyclic arbitrages among a block's transactions.
+// A transaction is an arbitrage if its swaps, in log order, chain each
+// output token into the next input token and end in the token they
+// started with. Profit is taken from the transaction's Transfer logs
+// where possible, falling back on the swap amounts otherwise.
+pub fn find_arbitrages<'a>(
+    transactions: &[TxSwaps<'a>],
+    transfers: &[TokenTransfer]
+) -> Vec<Arbitrage<'a>> {
+    let mut arbitrages = vec![];
+
+    for (k, transaction) in transactions.iter().enumerate() {
+        let token = match find_cycle(transaction) {
+            Some(token) => token,
+            None => continue
+        };
+
+        let (searcher, profit) = match measure_profit(transaction, token, transfers) {
+            Some((searcher, profit)) if profit > 0.0 => (searcher, profit),
+            _ => continue
+        };
+
+        let target = find_target(&transactions[..k], transaction);
+
+        arbitrages.push(Arbitrage {
+            kind: if target.is_some() { ArbitrageKind::Backrun } else { ArbitrageKind::Atomic },
+            transaction: transaction.clone(),
+            searcher,
+            token,
+            profit,
+            target
+        });
+    }
+
+    arbitrages
+}
+
+// Return the token a transaction's swaps start and end in,
+// if they form a closed cycle of at least two swaps.
+fn find_cycle<'a>(transaction: &TxSwaps<'a>) -> Option<&'a Token> {
+    if transaction.swaps.len() < 2 {
+        return None;
+    }
+
+    let start = token_in(transaction.swaps[0])?;
+    let mut current = token_out(transaction.swaps[0])?;
+
+    for swap in transaction.swaps.iter().skip(1) {
+        if token_in(swap)?.token_id != current.token_id {
+            return None;
+        }
+
+        current = token_out(swap)?;
+    }
+
+    if current.token_id == start.token_id { Some(start) } else { None }
+}
+
+// Find the account that gained the most of the cycle's token, and how much.
+// Candidates are the transaction sender, the first swap's sender
+// and the last swap's recipient (usually the searcher's contract).
+fn measure_profit(
+    transaction: &TxSwaps,
+    token: &Token,
+    transfers: &[TokenTransfer]
+) -> Option<(Address, f64)> {
+    let first = transaction.swaps.first()?;
+    let last = transaction.swaps.last()?;
+    let token_address = token.token_address.parse::<Address>().ok()?;
+
+    let transfers = transfers.iter()
+        .filter(|t| t.tx_hash == transaction.tx_hash && t.token == token_address)
+        .cloned()
+        .collect::<Vec<TokenTransfer>>();
+
+    if transfers.is_empty() {
+        let searcher = if transaction.from.is_zero() { last.swap.recipient() } else { transaction.from };
+        return Some((searcher, amount_out(last) - amount_in(first)));
+    }
+
+    [transaction.from, first.swap.sender(), last.swap.recipient()].into_iter()
+        .filter(|account| !account.is_zero())
+        .map(|account| (account, net_flow(&transfers, token_address, account, token.decimals as u32)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+// The most recent earlier transaction to trade on one of the arbitrage's
+// pools, if it traded that pool in the opposite direction (i.e. it moved
+// the price that the arbitrage then moved back).
+fn find_target<'a>(earlier: &[TxSwaps<'a>], arbitrage: &TxSwaps<'a>) -> Option<TxSwaps<'a>> {
+    let pools = arbitrage.pools();
+    let previous = earlier.iter().rev().find(|tx| !tx.pools().is_disjoint(&pools))?;
+
+    if previous.from == arbitrage.from {
+        return None;
+    }
+
+    let is_reversed = pools.iter().any(|pool| {
+        match (previous.direction_on(*pool), arbitrage.direction_on(*pool)) {
+            (Some(a), Some(b)) => a != Direction::Unknown && a.reverse() == b,
+            _ => false
+        }
+    });
+
+    if is_reversed { Some(previous.clone()) } else { None }
+}
+
+fn token_in<'a>(swap: &Swap<'a>) -> Option<&'a Token> {
+    match swap.swap.direction() {
+        Direction::BaseToQuote => Some(swap.base),
+        Direction::QuoteToBase => Some(swap.quote),
+        Direction::Unknown => None
+    }
+}
+
+fn token_out<'a>(swap: &Swap<'a>) -> Option<&'a Token> {
+    match swap.swap.direction() {
+        Direction::BaseToQuote => Some(swap.quote),
+        Direction::QuoteToBase => Some(swap.base),
+        Direction::Unknown => None
+    }
+}
+
+fn amount_in(swap: &Swap) -> f64 {
+    match swap.swap.direction() {
+        Direction::BaseToQuote => swap.in0(),
+        Direction::QuoteToBase => swap.in1(),
+        Direction::Unknown => 0f64
+    }
+}
+
+fn amount_out(swap: &Swap) -> f64 {
+    match swap.swap.direction() {
+        Direction::BaseToQuote => swap.out1(),
+        Direction::QuoteToBase => swap.out0(),
+        Direction::Unknown => 0f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::multipool::group_by_transaction;
+    use super::super::swap::{ SwapCore, to_wrapped };
+    use ethers::types::{ TxHash, U256 };
+    use std::collections::HashMap;
+
+    const SEARCHER: Address = Address::repeat_byte(0xaa);
+    const TRADER: Address = Address::repeat_byte(0xbb);
+    const POOL_A: Address = Address::repeat_byte(1);
+    const POOL_B: Address = Address::repeat_byte(2);
+
+    // A swap as (tx index, pool, sender, raw amounts).
+    type MockSwap = (u64, Address, Address, (u64, u64, u64, u64));
+
+    // Run arbitrage detection over the given swaps,
+    // on two pools of the same base and quote tokens.
+    fn find(swaps: &[MockSwap], transfers: &[TokenTransfer]) -> Vec<(ArbitrageKind, f64)> {
+        let (base, quote) = (Token::mock(1, 0), Token::mock(2, 0));
+        let swaps = swaps.iter()
+            .map(|(tx_index, pool, sender, amounts)| to_wrapped(
+                SwapCore::mock(*tx_index, *pool, *sender, *sender, *amounts), 18, &base, &quote))
+            .collect::<Vec<Swap>>();
+        let senders = swaps.iter()
+            .map(|swap| (swap.swap.tx_hash, swap.swap.sender()))
+            .collect::<HashMap<TxHash, Address>>();
+
+        find_arbitrages(&group_by_transaction(&swaps, &senders), transfers).iter()
+            .map(|arbitrage| (arbitrage.kind, arbitrage.profit))
+            .collect()
+    }
+
+    #[test]
+    fn closed_cycles_are_arbitrages() {
+        // Base for quote on one pool, then quote back for more base on the other.
+        let cycle = [(0, POOL_A, SEARCHER, (10, 0, 0, 20)), (0, POOL_B, SEARCHER, (0, 20, 11, 0))];
+        assert_eq!(find(&cycle, &[]), vec![(ArbitrageKind::Atomic, 1.0)]);
+
+        // The same cycle at a loss, and a route that doesn't come back to base.
+        let loss = [(0, POOL_A, SEARCHER, (10, 0, 0, 20)), (0, POOL_B, SEARCHER, (0, 20, 9, 0))];
+        assert!(find(&loss, &[]).is_empty());
+
+        let open = [(0, POOL_A, SEARCHER, (10, 0, 0, 20)), (0, POOL_B, SEARCHER, (10, 0, 0, 20))];
+        assert!(find(&open, &[]).is_empty());
+    }
+
+    #[test]
+    fn arbitrages_after_an_opposite_swap_are_backruns() {
+        let swaps = [
+            (0, POOL_A, TRADER, (0, 50, 30, 0)),
+            (1, POOL_A, SEARCHER, (10, 0, 0, 20)),
+            (1, POOL_B, SEARCHER, (0, 20, 11, 0))
+        ];
+
+        assert_eq!(find(&swaps, &[]), vec![(ArbitrageKind::Backrun, 1.0)]);
+    }
+
+    #[test]
+    fn profit_is_taken_from_transfers() {
+        let swaps = [(0, POOL_A, SEARCHER, (10, 0, 0, 20)), (0, POOL_B, SEARCHER, (0, 20, 11, 0))];
+        let transfer = |from: Address, to: Address, amount: u64| TokenTransfer {
+            tx_hash: TxHash::from_low_u64_be(1),
+            token: Address::repeat_byte(0x11),
+            from,
+            to,
+            amount: U256::from(amount)
+        };
+
+        // The swap amounts say 1, but the searcher really received 3 more than it paid.
+        let transfers = [transfer(SEARCHER, POOL_A, 10), transfer(POOL_B, SEARCHER, 13)];
+
+        assert_eq!(find(&swaps, &transfers), vec![(ArbitrageKind::Atomic, 3.0)]);
+    }
+}
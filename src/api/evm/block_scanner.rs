@@ -0,0 +1,347 @@
+use actix_web::web::Data;
+use ethers::prelude::{ Provider, Http, Middleware, EthEvent, LogMeta };
+use ethers::abi::RawLog;
+use ethers::types::{ Address, Filter, Log, TxHash, ValueOrArray };
+use super::super::{ db, models };
+use super::swap::{ RawSwapV2, RawSwapV3, SwapCore, Swap, to_wrapped };
+use super::multipool::{ MultipoolSandwich, group_by_transaction, find_multipool_sandwiches };
+use super::arbitrage::{ Arbitrage, find_arbitrages };
+use super::transfers::{ TokenTransfer, transfer_signatures, decode_transfer_log };
+use super::metadata::fetch_many_pair_metadata;
+use super::scanner::check_chain_id;
+use crate::state::{ AppState, Blockchain };
+use diesel::prelude::*;
+use diesel::NotFound;
+use diesel::result::Error as DbError;
+use tokio::runtime::Runtime;
+use std::thread;
+use std::error::Error;
+use std::collections::HashMap;
+
+// A supported pool seen in a block, with its pair and token records.
+pub struct Pool {
+    pub pair: models::Pair,
+    pub base: models::Token,
+    pub quote: models::Token
+}
+
+// Fetch the scan of the given block, or create it and start scanning
+// the block in the background. A failed scan is retried from scratch.
+pub fn fetch_or_start_block_scan(
+    app_state: Data<AppState>,
+    db_connection: &db::DbConnection,
+    blockchain_str_id: String,
+    block: i64
+) -> Result<models::BlockScan, DbError> {
+    let block_scan = match db::fetch_block_scan_by_params(db_connection, &blockchain_str_id, block) {
+        Err(NotFound) => db::insert_block_scan(
+            db_connection, &blockchain_str_id, block, false, false)?,
+        Ok(block_scan) if block_scan.scan_failed => {
+            // Another request may have already started the retry.
+            if !db::reset_failed_block_scan(db_connection, block_scan.block_scan_id)? {
+                return db::fetch_block_scan_by_params(db_connection, &blockchain_str_id, block);
+            }
+
+            models::BlockScan { scan_failed: false, ..block_scan }
+        },
+        result => return result
+    };
+
+    if !start_block_scan_job(app_state, blockchain_str_id, block_scan.clone()) {
+        return db::update_block_scan_metadata(
+            db_connection, block_scan.block_scan_id, false, true)
+            .map(|_| models::BlockScan { scan_failed: true, ..block_scan });
+    }
+
+    Ok(block_scan)
+}
+
+// Start scanning every supported pool in the given block
+// for multi-pool MEV, inside a new thread.
+pub fn start_block_scan_job(
+    app_state: Data<AppState>,
+    blockchain_str_id: String,
+    block_scan: models::BlockScan
+) -> bool {
+    thread::Builder::new().spawn(move || {
+        let db_connection = match app_state.db_pool.get() {
+            Ok(db_connection) => db_connection,
+            Err(_) => return // the block scan stays incomplete and can be retried
+        };
+
+        let block_scan_id = block_scan.block_scan_id;
+        let (runtime, blockchain) = match (
+            Runtime::new(), app_state.blockchains.get(&blockchain_str_id)
+        ) {
+            (Ok(runtime), Some(blockchain)) => (runtime, blockchain),
+            _ => {
+                db::update_block_scan_metadata(&db_connection, block_scan_id, false, true).ok();
+                return
+            }
+        };
+
+        let result = runtime.block_on(run_block_scan(
+            &db_connection,
+            blockchain,
+            &blockchain_str_id,
+            block_scan.block_number as u64));
+
+        // Mark this block scan as either complete or failed.
+        match result {
+            Ok(_) => db::update_block_scan_metadata(&db_connection, block_scan_id, true, false).ok(),
+            Err(e) => {
+                println!("Block scan {block_scan_id} failed: {e}");
+                db::update_block_scan_metadata(&db_connection, block_scan_id, false, true).ok()
+            }
+        };
+    }).is_ok() // whether the thread was successfully created
+}
+
+async fn run_block_scan(
+    db_connection: &db::DbConnection,
+    blockchain: &Blockchain,
+    blockchain_str_id: &str,
+    block: u64
+) -> Result<(), Box<dyn Error>> {
+    let provider = Provider::<Http>::try_from(blockchain.provider_url.clone())?;
+    check_chain_id(&provider, blockchain.chain_id).await?;
+
+    // Load every V2 and V3 Swap log and token transfer in the block.
+    let logs = fetch_block_logs(&provider, block).await?;
+    let swap_cores = logs.iter().filter_map(decode_swap_log).collect::<Vec<SwapCore>>();
+    let transfers = logs.iter().filter_map(decode_transfer_log).collect::<Vec<TokenTransfer>>();
+
+    println!("\nBlock {block}: fetched {} swaps across all pools.", swap_cores.len());
+
+    // Resolve each pool to a supported pair, dropping swaps on unsupported pools.
+    let mut pool_addresses = swap_cores.iter().map(|core| core.pool()).collect::<Vec<Address>>();
+    pool_addresses.sort();
+    pool_addresses.dedup();
+
+    let pools = resolve_pools(db_connection, blockchain, blockchain_str_id, &pool_addresses).await?;
+
+    let swaps = swap_cores.into_iter()
+        .filter_map(|core| {
+            let pool = pools.get(&core.pool())?;
+            Some(to_wrapped(core, blockchain.native_token.decimals, &pool.base, &pool.quote))
+        })
+        .collect::<Vec<Swap>>();
+
+    if swaps.len() < 2 {
+        return Ok(()); // there can't be any sandwiches or arbitrages in this block
+    }
+
+    // Look up who sent each transaction in the block.
+    let senders = fetch_block_senders(&provider, block).await?;
+
+    // Find the multi-pool sandwiches and cyclic arbitrages in this block.
+    let transactions = group_by_transaction(&swaps, &senders);
+    let sandwiches = find_multipool_sandwiches(&transactions);
+    let arbitrages = find_arbitrages(&transactions, &transfers);
+
+    // Save them all in one transaction, so a failed scan leaves
+    // nothing behind and can be retried from scratch.
+    db_connection.transaction::<_, Box<dyn Error>, _>(|| {
+        save_multipool_sandwiches(db_connection, blockchain_str_id, block, &sandwiches, &pools)?;
+        save_arbitrages(db_connection, blockchain_str_id, block, &arbitrages, &pools)
+    })
+}
+
+// Save the given multi-pool sandwiches found in the given block.
+fn save_multipool_sandwiches(
+    db_connection: &db::DbConnection,
+    blockchain_str_id: &str,
+    block: u64,
+    sandwiches: &[MultipoolSandwich],
+    pools: &HashMap<Address, Pool>
+) -> Result<(), Box<dyn Error>> {
+    for sandwich in sandwiches.iter() {
+        let db_sandwich = db::insert_multipool_sandwich(
+            db_connection,
+            blockchain_str_id,
+            i64::try_from(block)?,
+            &format!("{:?}", sandwich.attacker),
+            (&format!("{:?}", sandwich.frontrun.tx_hash), sandwich.frontrun.tx_index),
+            (&format!("{:?}", sandwich.backrun.tx_hash), sandwich.backrun.tx_index))?;
+
+        for victim in sandwich.victims.iter() {
+            db::insert_multipool_victim_transaction(
+                db_connection,
+                &format!("{:?}", victim.tx_hash),
+                victim.tx_index,
+                db_sandwich.multipool_sandwich_id)?;
+        }
+
+        for pool in sandwich.pools() {
+            db::insert_multipool_sandwich_pair(
+                db_connection,
+                db_sandwich.multipool_sandwich_id,
+                pools[&pool].pair.pair_id)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Save the given cyclic arbitrages found in the given block.
+fn save_arbitrages(
+    db_connection: &db::DbConnection,
+    blockchain_str_id: &str,
+    block: u64,
+    arbitrages: &[Arbitrage],
+    pools: &HashMap<Address, Pool>
+) -> Result<(), Box<dyn Error>> {
+    for arbitrage in arbitrages.iter() {
+        let target = arbitrage.target.as_ref()
+            .map(|target| (format!("{:?}", target.tx_hash), target.tx_index));
+
+        let db_arbitrage = db::insert_arbitrage(
+            db_connection,
+            blockchain_str_id,
+            i64::try_from(block)?,
+            &db::NewArbitrage {
+                tx: (&format!("{:?}", arbitrage.transaction.tx_hash), arbitrage.transaction.tx_index),
+                kind: arbitrage.kind.as_str(),
+                searcher: &format!("{:?}", arbitrage.searcher),
+                profit_token_id: arbitrage.token.token_id,
+                profit: arbitrage.profit,
+                target: target.as_ref().map(|(hash, index)| (hash.as_str(), *index))
+            })?;
+
+        for (hop, swap) in arbitrage.transaction.swaps.iter().enumerate() {
+            db::insert_arbitrage_pair(
+                db_connection,
+                db_arbitrage.arbitrage_id,
+                i32::try_from(hop)?,
+                pools[&swap.swap.pool()].pair.pair_id)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Fetch every V2 and V3 Swap log, and every token transfer,
+// emitted in the given block, from any contract.
+pub async fn fetch_block_logs(
+    provider: &Provider<Http>,
+    block: u64
+) -> Result<Vec<Log>, Box<dyn Error>> {
+    let mut topics = vec![RawSwapV2::signature(), RawSwapV3::signature()];
+    topics.extend(transfer_signatures());
+
+    let filter = Filter::new()
+        .from_block(block)
+        .to_block(block)
+        .topic0(ValueOrArray::Array(topics));
+
+    Ok(provider.get_logs(&filter).await?)
+}
+
+// Decode a V2 or V3 Swap log, or return `None` if it doesn't decode
+// (e.g. another contract emitted an event with the same signature).
+pub fn decode_swap_log(log: &Log) -> Option<SwapCore> {
+    let raw_log = RawLog { topics: log.topics.clone(), data: log.data.to_vec() };
+    let meta = LogMeta::from(log);
+
+    match log.topics.first() {
+        Some(topic) if *topic == RawSwapV2::signature() => RawSwapV2::decode_log(&raw_log).ok()
+            .map(|raw_swap| SwapCore::from((raw_swap, meta))),
+        Some(topic) if *topic == RawSwapV3::signature() => RawSwapV3::decode_log(&raw_log).ok()
+            .map(|raw_swap| SwapCore::from((raw_swap, meta))),
+        _ => None
+    }
+}
+
+// Map each transaction hash in the given block to its sender.
+pub async fn fetch_block_senders(
+    provider: &Provider<Http>,
+    block: u64
+) -> Result<HashMap<TxHash, Address>, Box<dyn Error>> {
+    let block = provider.get_block_with_txs(block).await?.ok_or("block not found")?;
+
+    Ok(block.transactions.into_iter().map(|tx| (tx.hash, tx.from)).collect())
+}
+
+// Find the pair for each of the given pools in the database, or fetch the
+// metadata of those that are missing (in batches) and save them (and their
+// tokens) if their factories are supported exchanges. Pools on unsupported
+// exchanges, or that aren't pairs at all, are left out.
+pub async fn resolve_pools(
+    db_connection: &db::DbConnection,
+    blockchain: &Blockchain,
+    blockchain_str_id: &str,
+    pool_addresses: &[Address]
+) -> Result<HashMap<Address, Pool>, Box<dyn Error>> {
+    let mut pairs = Vec::new();
+    let mut missing = Vec::new();
+
+    for pool_address in pool_addresses.iter() {
+        match db::fetch_pair_by_params(db_connection, blockchain_str_id, &format!("{:?}", pool_address)) {
+            Ok(pair) => pairs.push((*pool_address, pair)),
+            Err(NotFound) => missing.push(*pool_address),
+            Err(e) => return Err(Box::new(e))
+        }
+    }
+
+    if !missing.is_empty() {
+        let metadata = fetch_many_pair_metadata(
+            &blockchain.provider_url,
+            &missing,
+            blockchain.data_aggregator_address.as_deref()
+        ).await.map_err(|e| e.to_string())?;
+
+        for (pool_address, metadata) in missing.into_iter().zip(metadata) {
+            let metadata = match metadata {
+                Ok(metadata) => metadata,
+                Err(_) => continue // not a pair the aggregator understands
+            };
+
+            let factory_address = format!("0x{}", metadata.factory_address);
+
+            if blockchain.exchange(&factory_address).is_none() {
+                continue;
+            }
+
+            let base_id = db::fetch_or_insert_token(
+                db_connection,
+                &metadata.base_name,
+                &metadata.base_symbol,
+                metadata.base_decimals as i16,
+                blockchain_str_id,
+                &format!("0x{}", metadata.base_address))?;
+
+            let quote_id = db::fetch_or_insert_token(
+                db_connection,
+                &metadata.quote_name,
+                &metadata.quote_symbol,
+                metadata.quote_decimals as i16,
+                blockchain_str_id,
+                &format!("0x{}", metadata.quote_address))?;
+
+            let pair_id = db::insert_pair(
+                db_connection,
+                blockchain_str_id,
+                &factory_address,
+                &format!("{:?}", pool_address),
+                base_id,
+                quote_id,
+                None)?;
+
+            pairs.push((pool_address, db::fetch_pair_by_id(db_connection, pair_id)?));
+        }
+    }
+
+    let mut pools = HashMap::new();
+
+    for (pool_address, pair) in pairs {
+        if blockchain.exchange(&pair.factory_address).is_none() {
+            continue;
+        }
+
+        let base = db::fetch_token_by_id(db_connection, pair.base_token_id)?;
+        let quote = db::fetch_token_by_id(db_connection, pair.quote_token_id)?;
+        pools.insert(pool_address, Pool { pair, base, quote });
+    }
+
+    Ok(pools)
+}
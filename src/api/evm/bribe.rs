@@ -0,0 +1,343 @@
+use ethers::prelude::{ Provider, Http, Middleware, JsonRpcClient };
+use ethers::types::{ Address, U256, TxHash, Transaction, Action };
+use ethers::utils::format_units;
+use serde::{ Serialize, Deserialize };
+use super::sandwich::Sandwich;
+use std::error::Error;
+
+// The options passed to `debug_traceTransaction`.
+#[derive(Debug, Serialize)]
+struct TracerOptions {
+    tracer: &'static str
+}
+
+// A (partial) call frame returned by geth's `callTracer`.
+#[derive(Debug, Deserialize)]
+struct CallFrame {
+    to: Option<Address>,
+    value: Option<U256>,
+    error: Option<String>,
+    #[serde(default)]
+    calls: Vec<CallFrame>
+}
+
+// What an attacker paid the block's fee recipient directly,
+// on top of the gas fees for the sandwich's transactions.
+pub struct Bribe {
+    pub amount: U256,
+    pub tx_hash: Option<TxHash> // set when paid by a separate transaction
+}
+
+impl Bribe {
+    pub fn amount(&self, native_decimals: u8) -> f64 {
+        format_units(self.amount, native_decimals as u32)
+            .unwrap_or("0.0".to_string())
+            .parse::<f64>()
+            .unwrap()
+    }
+}
+
+// Find the native token the sandwich's attacker transferred to the block's
+// fee recipient (`coinbase`). The frontrun and backrun are traced with
+// `trace_transaction`, or `debug_traceTransaction` if the provider doesn't
+// support the former. Without tracing, only top-level transfers can be seen:
+// those made by the legs themselves, or by a bribe transaction from the same
+// sender that directly follows the backrun. `block_transactions` must hold
+// the block's transactions, in order.
+pub async fn find_sandwich_bribe(
+    provider: &Provider<Http>,
+    sandwich: &Sandwich<'_>,
+    coinbase: Address,
+    block_transactions: &[Transaction]
+) -> Result<Bribe, Box<dyn Error>> {
+    let mut amount = U256::zero();
+    let mut is_traced = true;
+
+    for leg in [&sandwich.frontrun, &sandwich.backrun] {
+        match trace_coinbase_transfers(provider, leg.swap.tx_hash, coinbase).await {
+            Some(transferred) => amount = amount.saturating_add(transferred),
+            None => {
+                is_traced = false;
+                break;
+            }
+        }
+    }
+
+    if is_traced && !amount.is_zero() {
+        return Ok(Bribe { amount, tx_hash: None });
+    }
+
+    // Fall back on top-level transfers in the legs themselves...
+    let backrun_index = sandwich.backrun.swap.tx_index() as usize;
+    let legs = [sandwich.frontrun.swap.tx_index() as usize, backrun_index];
+    let amount = legs.iter()
+        .filter_map(|index| block_transactions.get(*index))
+        .filter(|tx| tx.to == Some(coinbase))
+        .fold(U256::zero(), |total, tx| total.saturating_add(tx.value));
+
+    if !amount.is_zero() {
+        return Ok(Bribe { amount, tx_hash: None });
+    }
+
+    // ...or in a bribe transaction sent right after the backrun.
+    let senders = legs.iter()
+        .filter_map(|index| block_transactions.get(*index))
+        .map(|tx| tx.from)
+        .collect::<Vec<Address>>();
+
+    if let Some(tx) = block_transactions.get(backrun_index + 1) {
+        if senders.contains(&tx.from) && !tx.value.is_zero() {
+            let amount = if tx.to == Some(coinbase) {
+                tx.value
+            } else {
+                trace_coinbase_transfers(provider, tx.hash, coinbase).await.unwrap_or_default()
+            };
+
+            if !amount.is_zero() {
+                return Ok(Bribe { amount, tx_hash: Some(tx.hash) });
+            }
+        }
+    }
+
+    Ok(Bribe { amount: U256::zero(), tx_hash: None })
+}
+
+// Sum the native token sent to `coinbase` by the given transaction
+// (including internal calls that didn't revert), or return `None`
+// if the provider supports neither tracing method.
+pub async fn trace_coinbase_transfers(
+    provider: &Provider<Http>,
+    tx_hash: TxHash,
+    coinbase: Address
+) -> Option<U256> {
+    if let Ok(traces) = provider.trace_transaction(tx_hash).await {
+        let failed = traces.iter()
+            .filter(|trace| trace.error.is_some())
+            .map(|trace| trace.trace_address.clone())
+            .collect::<Vec<Vec<usize>>>();
+
+        let amount = traces.iter()
+            .filter(|trace| !failed.iter().any(|f| trace.trace_address.starts_with(f)))
+            .map(|trace| match &trace.action {
+                Action::Call(call) if call.to == coinbase => call.value,
+                Action::Suicide(suicide) if suicide.refund_address == coinbase => suicide.balance,
+                _ => U256::zero()
+            })
+            .fold(U256::zero(), |total, value| total.saturating_add(value));
+
+        return Some(amount);
+    }
+
+    let params = (tx_hash, TracerOptions { tracer: "callTracer" });
+
+    match provider.as_ref().request::<_, CallFrame>("debug_traceTransaction", params).await {
+        Ok(frame) => Some(sum_call_frame(&frame, coinbase)),
+        Err(_) => None
+    }
+}
+
+// Recursively sum the value sent to `coinbase` in a `callTracer` call frame,
+// skipping frames (and their children) that reverted.
+fn sum_call_frame(frame: &CallFrame, coinbase: Address) -> U256 {
+    if frame.error.is_some() {
+        return U256::zero();
+    }
+
+    let own = match (frame.to, frame.value) {
+        (Some(to), Some(value)) if to == coinbase => value,
+        _ => U256::zero()
+    };
+
+    frame.calls.iter()
+        .fold(own, |total, call| total.saturating_add(sum_call_frame(call, coinbase)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::mock_rpc::{ Cassette, MockRpc, RecordedCallFrame };
+    use super::super::swap::{ SwapCore, to_wrapped };
+    use crate::api::models::Token;
+    use ethers::types::{ H256, U64 };
+    use serde_json::{ json, Value };
+    use tokio::runtime::Runtime;
+
+    const COINBASE: Address = Address::repeat_byte(0xcb);
+    const ATTACKER: Address = Address::repeat_byte(0xaa);
+    const VICTIM: Address = Address::repeat_byte(0xbb);
+    const ROUTER: Address = Address::repeat_byte(0x0e);
+    const POOL: Address = Address::repeat_byte(1);
+
+    // A frontrun, a victim and a backrun, in transactions 0 to 2.
+    fn sandwich(token: &Token) -> Sandwich<'_> {
+        let swap = |tx_index: u64, sender: Address, amounts: (u64, u64, u64, u64)| to_wrapped(
+            SwapCore::mock(tx_index, POOL, sender, sender, amounts), 18, token, token);
+
+        Sandwich {
+            frontrun: swap(0, ATTACKER, (10, 0, 0, 10)),
+            lunchmeat: vec![swap(1, VICTIM, (10, 0, 0, 10))],
+            backrun: swap(2, ATTACKER, (0, 10, 10, 0))
+        }
+    }
+
+    // The transaction at the given index, with the hash `SwapCore::mock` gives its swaps.
+    fn transaction(tx_index: u64, from: Address, to: Address, value: u64) -> Transaction {
+        Transaction {
+            hash: TxHash::from_low_u64_be(tx_index + 1),
+            transaction_index: Some(U64::from(tx_index)),
+            from,
+            to: Some(to),
+            value: U256::from(value),
+            ..Default::default()
+        }
+    }
+
+    // The sandwich's transactions, all sent to a router without value.
+    fn sandwich_transactions() -> Vec<Transaction> {
+        vec![
+            transaction(0, ATTACKER, ROUTER, 0),
+            transaction(1, VICTIM, ROUTER, 0),
+            transaction(2, ATTACKER, ROUTER, 0)
+        ]
+    }
+
+    // A `trace_transaction` call trace in the given transaction.
+    fn trace(tx_index: u64, trace_address: &[usize], to: Address, value: u64, error: Option<&str>) -> Value {
+        json!({
+            "action": {
+                "callType": "call",
+                "from": ATTACKER,
+                "to": to,
+                "value": U256::from(value),
+                "gas": "0x0",
+                "input": "0x"
+            },
+            "result": null,
+            "error": error,
+            "traceAddress": trace_address,
+            "subtraces": 0,
+            "transactionPosition": tx_index,
+            "transactionHash": TxHash::from_low_u64_be(tx_index + 1),
+            "blockNumber": 1,
+            "blockHash": H256::zero(),
+            "type": "call"
+        })
+    }
+
+    fn call_frame(tx_index: u64, frame: Value) -> RecordedCallFrame {
+        RecordedCallFrame {
+            tx_hash: format!("{:?}", TxHash::from_low_u64_be(tx_index + 1)),
+            result: frame
+        }
+    }
+
+    fn find_bribe(cassette: Cassette, block_transactions: &[Transaction]) -> Bribe {
+        let token = Token::mock(1, 18);
+        let rpc = MockRpc::start(cassette);
+        let provider = Provider::<Http>::try_from(rpc.url.as_str()).unwrap();
+
+        Runtime::new().unwrap()
+            .block_on(find_sandwich_bribe(&provider, &sandwich(&token), COINBASE, block_transactions))
+            .unwrap()
+    }
+
+    #[test]
+    fn call_frames_sum_what_reaches_the_coinbase() {
+        let frame: CallFrame = serde_json::from_value(json!({
+            "to": ROUTER,
+            "value": "0x0",
+            "calls": [
+                { "to": COINBASE, "value": "0x1" },
+                { "to": VICTIM, "value": "0x10" },
+                { "to": ROUTER, "calls": [{ "to": COINBASE, "value": "0x2" }] },
+                { "to": ROUTER, "error": "execution reverted", "calls": [{ "to": COINBASE, "value": "0x20" }] },
+                { "to": COINBASE, "value": "0x40", "error": "out of gas" }
+            ]
+        })).unwrap();
+
+        assert_eq!(sum_call_frame(&frame, COINBASE), U256::from(3));
+        assert_eq!(sum_call_frame(&frame, VICTIM), U256::from(0x10));
+    }
+
+    #[test]
+    fn reverted_transactions_send_nothing() {
+        let frame: CallFrame = serde_json::from_value(json!({
+            "to": COINBASE,
+            "value": "0x1",
+            "error": "execution reverted",
+            "calls": [{ "to": COINBASE, "value": "0x2" }]
+        })).unwrap();
+
+        assert_eq!(sum_call_frame(&frame, COINBASE), U256::zero());
+    }
+
+    #[test]
+    fn legs_are_traced_with_trace_transaction() {
+        let mut cassette = Cassette::load("classic");
+        cassette.traces = vec![
+            trace(0, &[], ROUTER, 0, None),
+            trace(0, &[0], COINBASE, 1, None),
+            trace(0, &[1], ROUTER, 0, Some("Reverted")),
+            trace(0, &[1, 0], COINBASE, 0x20, None),
+            trace(2, &[], ROUTER, 0, None),
+            trace(2, &[0], COINBASE, 2, None)
+        ];
+
+        let bribe = find_bribe(cassette, &sandwich_transactions());
+        assert_eq!((bribe.amount, bribe.tx_hash), (U256::from(3), None));
+    }
+
+    #[test]
+    fn legs_are_traced_with_debug_trace_transaction_otherwise() {
+        let mut cassette = Cassette::load("classic");
+        cassette.call_frames = vec![
+            call_frame(0, json!({ "to": ROUTER, "calls": [{ "to": COINBASE, "value": "0x1" }] })),
+            call_frame(2, json!({ "to": ROUTER, "calls": [
+                { "to": COINBASE, "value": "0x20", "error": "execution reverted" },
+                { "to": COINBASE, "value": "0x4" }
+            ] }))
+        ];
+
+        let bribe = find_bribe(cassette, &sandwich_transactions());
+        assert_eq!((bribe.amount, bribe.tx_hash), (U256::from(5), None));
+    }
+
+    #[test]
+    fn top_level_transfers_are_found_without_tracing() {
+        let mut transactions = sandwich_transactions();
+        transactions[2] = transaction(2, ATTACKER, COINBASE, 3);
+
+        let bribe = find_bribe(Cassette::load("classic"), &transactions);
+        assert_eq!((bribe.amount, bribe.tx_hash), (U256::from(3), None));
+    }
+
+    #[test]
+    fn bribe_transactions_after_the_backrun_are_found() {
+        let mut transactions = sandwich_transactions();
+        transactions.push(transaction(3, ATTACKER, COINBASE, 6));
+
+        let bribe = find_bribe(Cassette::load("classic"), &transactions);
+        assert_eq!((bribe.amount, bribe.tx_hash), (U256::from(6), Some(TxHash::from_low_u64_be(4))));
+
+        // A transfer from anyone else is no bribe.
+        transactions[3] = transaction(3, VICTIM, COINBASE, 6);
+
+        let bribe = find_bribe(Cassette::load("classic"), &transactions);
+        assert_eq!((bribe.amount, bribe.tx_hash), (U256::zero(), None));
+    }
+
+    #[test]
+    fn bribe_transactions_through_a_contract_are_traced() {
+        // Only the bribe transaction can be traced, so the legs aren't.
+        let mut cassette = Cassette::load("classic");
+        cassette.call_frames = vec![
+            call_frame(3, json!({ "to": ROUTER, "value": "0x6", "calls": [{ "to": COINBASE, "value": "0x6" }] }))
+        ];
+
+        let mut transactions = sandwich_transactions();
+        transactions.push(transaction(3, ATTACKER, ROUTER, 6));
+
+        let bribe = find_bribe(cassette, &transactions);
+        assert_eq!((bribe.amount, bribe.tx_hash), (U256::from(6), Some(TxHash::from_low_u64_be(4))));
+    }
+}
@@ -0,0 +1,353 @@
+use super::swap::{ Swap, Direction };
+use std::collections::HashMap;
+
+// The tolerance used when neither the blockchain
+// nor the exchange configures its own.
+pub const DEFAULT_TOLERANCE: f64 = 1.005;
+
+// Bump this whenever any detector's matching logic changes, so that
+// completed ranges are re-detected and their old results superseded.
+pub const DETECTOR_REVISION: u32 = 1;
+
+// A `SandwichDetector` decides which swaps in a bundle
+// (i.e. a single block's swaps on a single pair, sorted
+// by transaction index) form frontrun and backrun pairs.
+pub trait SandwichDetector: Send + Sync {
+    // A short, human-readable name for logging and comparison.
+    fn name(&self) -> &'static str;
+
+    // Identifies which results this detector produces;
+    // stored with every sandwich and range it detects.
+    fn version(&self) -> String {
+        format!("{}/{}", self.name(), DETECTOR_REVISION)
+    }
+
+    // Given two swaps, determine if they match as a frontrun and backrun pair.
+    // Implementations should require that the backrun reverses the frontrun.
+    fn is_match(&self, frontrun: &Swap, backrun: &Swap) -> bool;
+
+    // Score a matching frontrun and backrun pair; lower scores
+    // are closer matches and win when two candidates conflict.
+    fn score(&self, _frontrun: &Swap, _backrun: &Swap) -> f64 {
+        0.0
+    }
+
+    // Find every non-conflicting (frontrun, backrun) index pair in the
+    // given bundle, sorted by frontrun index. Candidates may be nested
+    // or overlapping, but each swap is used as a leg at most once.
+    // Conflicts are settled by the lowest score, then the shortest span,
+    // then the earliest frontrun, so the result is deterministic.
+    fn find_matches(&self, bundle: &[Swap]) -> Vec<(usize, usize)> {
+        let num_swaps = bundle.len();
+        let mut candidates = vec![];
+
+        for i in 0..num_swaps {
+            for j in i+2..num_swaps { // a swap at index `i` needs at least two more swaps
+                if self.is_match(&bundle[i], &bundle[j]) {
+                    candidates.push((self.score(&bundle[i], &bundle[j]), j - i, i, j));
+                }
+            }
+        }
+
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0)
+            .then(a.1.cmp(&b.1))
+            .then(a.2.cmp(&b.2)));
+
+        // Each sandwich needs a victim between its legs that isn't itself a leg.
+        let has_victim = |is_leg: &[bool], (i, j): (usize, usize)| {
+            (i+1..j).any(|k| !is_leg[k] && is_victim(&bundle[i], &bundle[k]))
+        };
+
+        // Greedily take the best candidates whose legs are still free, as long
+        // as neither they nor any sandwich already taken is left without a victim.
+        let mut is_leg = vec![false; num_swaps];
+        let mut matches = vec![];
+
+        for (_, _, i, j) in candidates {
+            if is_leg[i] || is_leg[j] {
+                continue;
+            }
+
+            is_leg[i] = true;
+            is_leg[j] = true;
+            matches.push((i, j));
+
+            if !matches.iter().all(|m| has_victim(&is_leg, *m)) {
+                is_leg[i] = false;
+                is_leg[j] = false;
+                matches.pop();
+            }
+        }
+
+        matches.sort();
+        matches
+    }
+}
+
+// The original detector: the frontrun's input amount must match
+// the backrun's output amount (in either token) within the tolerance.
+pub struct AmountRatioDetector {
+    pub tolerance: f64
+}
+
+impl SandwichDetector for AmountRatioDetector {
+    fn name(&self) -> &'static str {
+        "amount-ratio"
+    }
+
+    fn version(&self) -> String {
+        format!("{}/{}/{}", self.name(), DETECTOR_REVISION, self.tolerance)
+    }
+
+    fn is_match(&self, a: &Swap, b: &Swap) -> bool {
+        if !is_reversal(a, b) {
+            return false;
+        }
+
+        within_tolerance(a.in0(), b.out0(), self.tolerance) ||
+            within_tolerance(a.in1(), b.out1(), self.tolerance)
+    }
+
+    fn score(&self, a: &Swap, b: &Swap) -> f64 {
+        distance(a.in0(), b.out0()).min(distance(a.in1(), b.out1()))
+    }
+}
+
+// Match swaps where the contract that received the frontrun's output
+// is the same contract that sends the backrun. Amounts are not compared.
+pub struct SameSenderDetector;
+
+impl SandwichDetector for SameSenderDetector {
+    fn name(&self) -> &'static str {
+        "same-sender"
+    }
+
+    fn is_match(&self, a: &Swap, b: &Swap) -> bool {
+        if !is_reversal(a, b) {
+            return false;
+        }
+
+        a.swap.recipient() == b.swap.sender()
+    }
+}
+
+// Match swaps where the amount of the token bought in the frontrun
+// is sold again in the backrun, within the tolerance.
+pub struct TokenFlowDetector {
+    pub tolerance: f64
+}
+
+impl SandwichDetector for TokenFlowDetector {
+    fn name(&self) -> &'static str {
+        "token-flow"
+    }
+
+    fn version(&self) -> String {
+        format!("{}/{}/{}", self.name(), DETECTOR_REVISION, self.tolerance)
+    }
+
+    fn is_match(&self, a: &Swap, b: &Swap) -> bool {
+        if !is_reversal(a, b) {
+            return false;
+        }
+
+        within_tolerance(a.out0(), b.in0(), self.tolerance) ||
+            within_tolerance(a.out1(), b.in1(), self.tolerance)
+    }
+
+    fn score(&self, a: &Swap, b: &Swap) -> f64 {
+        distance(a.out0(), b.in0()).min(distance(a.out1(), b.in1()))
+    }
+}
+
+// Each kind of detector that can be selected in a blockchain's config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectorKind {
+    AmountRatio,
+    SameSender,
+    TokenFlow
+}
+
+impl DetectorKind {
+    pub const ALL: [DetectorKind; 3] = [
+        DetectorKind::AmountRatio,
+        DetectorKind::SameSender,
+        DetectorKind::TokenFlow
+    ];
+
+    pub fn build(&self, tolerance: f64) -> Box<dyn SandwichDetector> {
+        match self {
+            DetectorKind::AmountRatio => Box::new(AmountRatioDetector { tolerance }),
+            DetectorKind::SameSender => Box::new(SameSenderDetector),
+            DetectorKind::TokenFlow => Box::new(TokenFlowDetector { tolerance })
+        }
+    }
+}
+
+// This struct holds detection parameters.
+#[derive(Debug, Clone)]
+pub struct DetectorParams {
+    pub kind: DetectorKind,
+    pub tolerance: f64,
+    pub exchange_tolerances: HashMap<String, f64>, // factory address (key) -> tolerance (value)
+    pub compare: bool // also run every other detector and log how their results differ
+}
+
+impl DetectorParams {
+    // Get the tolerance for the given exchange, falling back
+    // to the blockchain-wide tolerance if it has no override.
+    pub fn tolerance_for(&self, factory_address: &str) -> f64 {
+        match self.exchange_tolerances.get(&factory_address.to_lowercase()) {
+            Some(tolerance) => *tolerance,
+            None => self.tolerance
+        }
+    }
+
+    // Build the configured detector for the given exchange.
+    pub fn build(&self, factory_address: &str) -> Box<dyn SandwichDetector> {
+        self.kind.build(self.tolerance_for(factory_address))
+    }
+
+    // Build every other detector for the given exchange, for comparison.
+    pub fn build_alternatives(&self, factory_address: &str) -> Vec<Box<dyn SandwichDetector>> {
+        let tolerance = self.tolerance_for(factory_address);
+
+        DetectorKind::ALL.iter()
+            .filter(|kind| **kind != self.kind)
+            .map(|kind| kind.build(tolerance))
+            .collect()
+    }
+}
+
+// Log how each alternative detector's matches differ
+// from the configured detector's matches on a bundle.
+pub fn compare_detectors(
+    block_number: u64,
+    bundle: &[Swap],
+    detector: &dyn SandwichDetector,
+    alternatives: &[Box<dyn SandwichDetector>]
+) {
+    let expected = detector.find_matches(bundle);
+
+    for alternative in alternatives {
+        let found = alternative.find_matches(bundle);
+
+        if found != expected {
+            let missing = expected.iter().filter(|m| !found.contains(m)).count();
+            let extra = found.iter().filter(|m| !expected.contains(m)).count();
+
+            println!(
+                " -- Block {block_number}: {} found {} sandwiches ({missing} missing, {extra} extra) \
+                vs. {} sandwiches from {}.",
+                alternative.name(), found.len(), expected.len(), detector.name());
+        }
+    }
+}
+
+// Both swaps must be on the same base and quote tokens,
+// and the backrun must trade in the opposite direction of the frontrun.
+fn is_reversal(a: &Swap, b: &Swap) -> bool {
+    if a.base.token_id != b.base.token_id || a.quote.token_id != b.quote.token_id {
+        return false;
+    }
+
+    let direction = a.swap.direction();
+    direction != Direction::Unknown && b.swap.direction() == direction.reverse()
+}
+
+// Check whether the ratio of `a` to `b` lies strictly within (1/tol, tol).
+fn within_tolerance(a: f64, b: f64, tol: f64) -> bool {
+    let ratio = a / b;
+    1.0/tol < ratio && ratio < tol
+}
+
+// How far the ratio of `a` to `b` is from 1, on a log scale,
+// so that `a` being 1% above `b` scores the same as 1% below.
+fn distance(a: f64, b: f64) -> f64 {
+    let distance = (a / b).ln().abs();
+    if distance.is_nan() { f64::INFINITY } else { distance }
+}
+
+// A swap between the frontrun and the backrun is a victim
+// if it trades in the same direction as the frontrun.
+pub fn is_victim(frontrun: &Swap, swap: &Swap) -> bool {
+    swap.swap.direction() == frontrun.swap.direction()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::swap::{ SwapCore, to_wrapped };
+    use crate::api::models::Token;
+    use ethers::types::Address;
+    use Direction::{ BaseToQuote as Buy, QuoteToBase as Sell };
+
+    // Matches the given (frontrun, backrun) transaction indices with the given scores.
+    struct FixedDetector {
+        candidates: Vec<(i32, i32, f64)>
+    }
+
+    impl FixedDetector {
+        fn candidate(&self, frontrun: &Swap, backrun: &Swap) -> Option<f64> {
+            self.candidates.iter()
+                .find(|(i, j, _)| *i == frontrun.swap.tx_index() && *j == backrun.swap.tx_index())
+                .map(|(_, _, score)| *score)
+        }
+    }
+
+    impl SandwichDetector for FixedDetector {
+        fn name(&self) -> &'static str {
+            "Fixed"
+        }
+
+        fn is_match(&self, frontrun: &Swap, backrun: &Swap) -> bool {
+            self.candidate(frontrun, backrun).is_some()
+        }
+
+        fn score(&self, frontrun: &Swap, backrun: &Swap) -> f64 {
+            self.candidate(frontrun, backrun).unwrap()
+        }
+    }
+
+    // Build a bundle with one swap per direction, in transaction order.
+    fn bundle<'a>(directions: &[Direction], base: &'a Token, quote: &'a Token) -> Vec<Swap<'a>> {
+        directions.iter().enumerate().map(|(index, direction)| {
+            let amounts = match direction {
+                Direction::BaseToQuote => (10, 0, 0, 10),
+                Direction::QuoteToBase => (0, 10, 10, 0),
+                Direction::Unknown => (10, 0, 10, 0)
+            };
+            let core = SwapCore::mock(index as u64, Address::zero(), Address::zero(), Address::zero(), amounts);
+            to_wrapped(core, 18, base, quote)
+        }).collect()
+    }
+
+    #[test]
+    fn nested_sandwiches_are_both_found() {
+        let (base, quote) = (Token::mock(1, 18), Token::mock(2, 18));
+        let bundle = bundle(&[Buy, Buy, Buy, Sell, Sell], &base, &quote);
+        let detector = FixedDetector { candidates: vec![(0, 4, 1.0), (1, 3, 0.0)] };
+
+        assert_eq!(detector.find_matches(&bundle), vec![(0, 4), (1, 3)]);
+    }
+
+    #[test]
+    fn sandwiches_sharing_a_leg_keep_one_with_a_victim() {
+        let (base, quote) = (Token::mock(1, 18), Token::mock(2, 18));
+
+        // The best candidate has no victim, so it mustn't
+        // keep the candidate sharing its frontrun from matching.
+        let bundle_a = bundle(&[Buy, Sell, Sell, Buy, Sell], &base, &quote);
+        let detector = FixedDetector { candidates: vec![(0, 2, 0.0), (0, 4, 1.0)] };
+
+        assert_eq!(detector.find_matches(&bundle_a), vec![(0, 4)]);
+
+        // The second candidate would take the first's only victim as its
+        // frontrun, so it's rejected rather than dropping both of them,
+        // and the third candidate shares the first's backrun.
+        let bundle_b = bundle(&[Buy, Buy, Buy, Sell, Sell], &base, &quote);
+        let detector = FixedDetector { candidates: vec![(1, 3, 0.0), (2, 4, 1.0), (0, 3, 2.0)] };
+
+        assert_eq!(detector.find_matches(&bundle_b), vec![(1, 3)]);
+    }
+}
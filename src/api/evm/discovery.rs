@@ -0,0 +1,570 @@
+use ethers::prelude::{ Provider, Http, Middleware, EthEvent };
+use ethers::abi::{ self, RawLog, Token };
+use ethers::types::{ Address, U256, I256, Log, Filter, ValueOrArray };
+use ethers::utils::id;
+use diesel::prelude::*;
+use diesel::result::Error as DbError;
+use super::super::{ db, models };
+use super::metadata::{ PairMetadata, fetch_many_pair_metadata, call_all_with_data, decode_address };
+use crate::state::Exchange;
+use std::collections::HashMap;
+
+type RpcError = Box<dyn std::error::Error + Send + Sync>;
+
+// The fee tiers (in hundredths of a basis point) that V3 factories create pools for.
+pub const V3_FEE_TIERS: [u32; 4] = [100, 500, 3_000, 10_000];
+
+// Emitted by V2 factories for every new pair.
+#[derive(Clone, Debug, EthEvent)]
+#[ethevent(name = "PairCreated", abi = "PairCreated(address,address,address,uint256)")]
+pub struct RawPairCreated {
+    #[ethevent(indexed)]
+    pub token0: Address,
+    #[ethevent(indexed)]
+    pub token1: Address,
+    pub pair: Address,
+    pub pair_count: U256
+}
+
+// Emitted by V3 factories for every new pool, one per pair of tokens and fee tier.
+#[derive(Clone, Debug, EthEvent)]
+#[ethevent(name = "PoolCreated", abi = "PoolCreated(address,address,uint24,int24,address)")]
+pub struct RawPoolCreated {
+    #[ethevent(indexed)]
+    pub token0: Address,
+    #[ethevent(indexed)]
+    pub token1: Address,
+    #[ethevent(indexed)]
+    pub fee: U256,
+    #[ethevent(name = "tickSpacing")]
+    pub tick_spacing: I256,
+    pub pool: Address
+}
+
+// A pair (or pool) created by a factory, with its fee tier
+// (in hundredths of a basis point) if it's a V3 pool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatedPair {
+    pub pair: Address,
+    pub token0: Address,
+    pub token1: Address,
+    pub fee_tier: Option<u32>
+}
+
+// Decode a PairCreated or PoolCreated log, or return `None` if it isn't one.
+pub fn decode_created_pair_log(log: &Log) -> Option<CreatedPair> {
+    let raw_log = RawLog { topics: log.topics.clone(), data: log.data.to_vec() };
+
+    match log.topics.first() {
+        Some(topic) if *topic == RawPairCreated::signature() => {
+            let created = RawPairCreated::decode_log(&raw_log).ok()?;
+            Some(CreatedPair {
+                pair: created.pair,
+                token0: created.token0,
+                token1: created.token1,
+                fee_tier: None
+            })
+        },
+        Some(topic) if *topic == RawPoolCreated::signature() => {
+            let created = RawPoolCreated::decode_log(&raw_log).ok()?;
+            Some(CreatedPair {
+                pair: created.pool,
+                token0: created.token0,
+                token1: created.token1,
+                fee_tier: Some(created.fee.low_u32())
+            })
+        },
+        _ => None
+    }
+}
+
+// Fetch the pairs the given factory created from block `from` up to block `to`
+// (inclusive), over at most `max_blocks` blocks, along with their metadata.
+// Whenever the provider refuses a request (e.g. because it caps how many logs
+// it returns), the window is halved and tried again, down to a single block.
+// Returns the last block fetched, along with the pairs.
+pub async fn fetch_created_pairs(
+    provider_url: &str,
+    data_aggregator_address: Option<&str>,
+    factory_address: &str,
+    (from, to): (u64, u64),
+    max_blocks: u64
+) -> Result<(u64, Vec<(CreatedPair, Option<PairMetadata>)>), RpcError> {
+    let mut blocks = max_blocks.max(1);
+
+    let (upper, created) = loop {
+        let upper = to.min(from + blocks - 1);
+
+        match fetch_created_pair_logs(provider_url, factory_address, from, upper).await {
+            Ok(created) => break (upper, created),
+            Err(_) if upper > from => blocks = (upper - from).div_ceil(2),
+            Err(e) => return Err(e)
+        }
+    };
+
+    Ok((upper, fetch_created_pair_metadata(provider_url, data_aggregator_address, created).await?))
+}
+
+// Fetch the PairCreated and PoolCreated events the given factory
+// emitted from block `from` to `to` (inclusive).
+async fn fetch_created_pair_logs(
+    provider_url: &str,
+    factory_address: &str,
+    from: u64,
+    to: u64
+) -> Result<Vec<CreatedPair>, RpcError> {
+    let provider = Provider::<Http>::try_from(provider_url)?;
+    let filter = Filter::new()
+        .address(ValueOrArray::Value(factory_address.parse::<Address>()?))
+        .from_block(from)
+        .to_block(to)
+        .topic0(ValueOrArray::Array(vec![RawPairCreated::signature(), RawPoolCreated::signature()]));
+
+    Ok(provider.get_logs(&filter).await?
+        .iter()
+        .filter_map(decode_created_pair_log)
+        .collect())
+}
+
+// Fetch the metadata of each of the given pairs, if it can be fetched.
+pub async fn fetch_created_pair_metadata(
+    provider_url: &str,
+    data_aggregator_address: Option<&str>,
+    created: Vec<CreatedPair>
+) -> Result<Vec<(CreatedPair, Option<PairMetadata>)>, RpcError> {
+    if created.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let pair_addresses = created.iter().map(|c| c.pair).collect::<Vec<Address>>();
+    let metadata = fetch_many_pair_metadata(provider_url, &pair_addresses, data_aggregator_address).await?;
+
+    Ok(created.into_iter()
+        .zip(metadata)
+        .map(|(created, metadata)| (created, metadata.ok()))
+        .collect())
+}
+
+// Rebuild a created pair from its record of failed metadata,
+// or return `None` if the record has a malformed address.
+pub fn created_pair_from_failed(failed: &models::FailedPair) -> Option<CreatedPair> {
+    Some(CreatedPair {
+        pair: failed.pair_address.parse::<Address>().ok()?,
+        token0: failed.token0_address.parse::<Address>().ok()?,
+        token1: failed.token1_address.parse::<Address>().ok()?,
+        fee_tier: failed.fee_tier.map(|fee| fee as u32)
+    })
+}
+
+// Save the given pairs (and their tokens) from the given factory, skipping
+// pairs that are already saved, and record the pairs without metadata so
+// they can be retried. If `last_block` is set, also record that the factory
+// has been indexed up to it, all in one transaction. Returns how many pairs
+// were new.
+pub fn save_created_pairs(
+    db_connection: &db::DbConnection,
+    blockchain_str_id: &str,
+    factory_address: &str,
+    created_pairs: &[(CreatedPair, Option<PairMetadata>)],
+    last_block: Option<i64>
+) -> Result<usize, DbError> {
+    db_connection.transaction(|| {
+        let mut saved = 0;
+
+        for (created, metadata) in created_pairs.iter() {
+            let pair_address = format!("{:?}", created.pair);
+
+            let metadata = match metadata {
+                Some(metadata) => metadata,
+                None => {
+                    db::upsert_failed_pair(
+                        db_connection,
+                        blockchain_str_id,
+                        factory_address,
+                        &pair_address,
+                        (&format!("{:?}", created.token0), &format!("{:?}", created.token1)),
+                        created.fee_tier.map(|fee| fee as i32))?;
+
+                    continue;
+                }
+            };
+
+            db::delete_failed_pair(db_connection, blockchain_str_id, &pair_address)?;
+
+            // Pairs added through `/api/pair` are saved without
+            // a fee tier, so fill it in if the pair already exists.
+            match db::fetch_pair_by_params(db_connection, blockchain_str_id, &pair_address) {
+                Ok(pair) => {
+                    if let (None, Some(fee)) = (pair.fee_tier, created.fee_tier) {
+                        db::update_pair_fee_tier(db_connection, pair.pair_id, fee as i32)?;
+                    }
+
+                    continue;
+                },
+                Err(DbError::NotFound) => {},
+                Err(e) => return Err(e)
+            }
+
+            // Token names and symbols are arbitrary, so fit them to their columns.
+            let base_id = db::fetch_or_insert_token(
+                db_connection,
+                &fit(&metadata.base_name, 64),
+                &fit(&metadata.base_symbol, 16),
+                metadata.base_decimals as i16,
+                blockchain_str_id,
+                &format!("0x{}", metadata.base_address))?;
+
+            let quote_id = db::fetch_or_insert_token(
+                db_connection,
+                &fit(&metadata.quote_name, 64),
+                &fit(&metadata.quote_symbol, 16),
+                metadata.quote_decimals as i16,
+                blockchain_str_id,
+                &format!("0x{}", metadata.quote_address))?;
+
+            db::insert_pair(
+                db_connection,
+                blockchain_str_id,
+                factory_address,
+                &pair_address,
+                base_id,
+                quote_id,
+                created.fee_tier.map(|fee| fee as i32))?;
+
+            saved += 1;
+        }
+
+        if let Some(last_block) = last_block {
+            db::upsert_factory_scan(db_connection, blockchain_str_id, factory_address, last_block)?;
+        }
+
+        Ok(saved)
+    })
+}
+
+// A pair (or pool) of two tokens found by asking its factory for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FactoryPair {
+    pub factory_address: String,
+    pub pair: Address,
+    pub fee_tier: Option<u32>
+}
+
+// Ask each of the given exchanges' factories for its pair of the given tokens:
+// `getPair` on V2 factories, and `getPool` on V3 factories for every fee tier.
+// If a fee tier is given, only V3 factories are asked, for only that fee tier,
+// since V2 pairs have none.
+pub async fn fetch_factory_pairs(
+    provider_url: &str,
+    exchanges: &HashMap<String, Exchange>,
+    token_a: Address,
+    token_b: Address,
+    fee_tier: Option<u32>
+) -> Result<Vec<FactoryPair>, RpcError> {
+    let provider = Provider::<Http>::try_from(provider_url)?;
+    let tokens = [Token::Address(token_a), Token::Address(token_b)];
+
+    let mut factory_addresses = exchanges.keys().collect::<Vec<&String>>();
+    factory_addresses.sort();
+
+    let mut queries = Vec::new();
+    let mut calls = Vec::new();
+
+    for factory_address in factory_addresses {
+        let factory = factory_address.parse::<Address>()?;
+
+        match exchanges[factory_address] {
+            Exchange::V2 { name: _ } if fee_tier.is_none() => {
+                queries.push((factory_address.clone(), None));
+                calls.push((factory, [id("getPair(address,address)").to_vec(), abi::encode(&tokens)].concat()));
+            },
+            Exchange::V3 { name: _ } => {
+                for fee in V3_FEE_TIERS.into_iter().filter(|fee| fee_tier.is_none_or(|tier| tier == *fee)) {
+                    let arguments = [tokens[0].clone(), tokens[1].clone(), Token::Uint(U256::from(fee))];
+                    queries.push((factory_address.clone(), Some(fee)));
+                    calls.push((factory, [id("getPool(address,address,uint24)").to_vec(), abi::encode(&arguments)].concat()));
+                }
+            },
+            Exchange::V2 { name: _ } => {}
+        }
+    }
+
+    let outputs = call_all_with_data(&provider, &calls).await?;
+
+    // Factories return the zero address for pairs they haven't created.
+    Ok(queries.into_iter()
+        .zip(outputs)
+        .filter_map(|((factory_address, fee_tier), output)| match decode_address(&output) {
+            Ok(pair) if !pair.is_zero() => Some(FactoryPair { factory_address, pair, fee_tier }),
+            _ => None
+        })
+        .collect())
+}
+
+// Drop any NUL characters (which Postgres rejects) and cut the given string
+// down to at most `length` characters.
+fn fit(string: &str, length: usize) -> String {
+    string.chars().filter(|c| *c != '\0').take(length).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::mock_rpc::{ Cassette, MockRpc };
+    use crate::api::test_db::test_db_connection;
+    use ethers::types::{ H256, Bytes };
+    use tokio::runtime::Runtime;
+
+    const V2_FACTORY_ADDRESS: &str = "0x5c69bee701ef814a2b6a3edd4b1652cb9cc5aa6f";
+    const V3_FACTORY_ADDRESS: &str = "0x1f98431c8ad98523631ae4a59f267346ea31f984";
+    const PAIR_ADDRESS: &str = "0x11982c91f8f0438475b20e67ff9dbe5aa0d25db2";
+    const POOL_ADDRESS: &str = "0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640";
+
+    // The pairs the cassette's factories created in block 17000000.
+    fn fetch_mock_created_pairs(rpc: &MockRpc) -> Vec<(CreatedPair, Option<PairMetadata>)> {
+        let runtime = Runtime::new().unwrap();
+
+        [V2_FACTORY_ADDRESS, V3_FACTORY_ADDRESS].into_iter()
+            .flat_map(|factory_address| runtime
+                .block_on(fetch_created_pairs(&rpc.url, None, factory_address, (17000000, 17000000), 1))
+                .unwrap().1)
+            .collect()
+    }
+
+    fn address(byte: u8) -> Address {
+        Address::repeat_byte(byte)
+    }
+
+    fn log(topics: Vec<H256>, data: Vec<Token>) -> Log {
+        serde_json::from_value(serde_json::json!({
+            "address": Address::zero(),
+            "topics": topics,
+            "data": Bytes::from(abi::encode(&data))
+        })).unwrap()
+    }
+
+    #[test]
+    fn created_pairs_are_decoded() {
+        let pair_created = log(
+            vec![RawPairCreated::signature(), address(1).into(), address(2).into()],
+            vec![Token::Address(address(3)), Token::Uint(U256::from(7))]);
+
+        assert_eq!(decode_created_pair_log(&pair_created), Some(CreatedPair {
+            pair: address(3),
+            token0: address(1),
+            token1: address(2),
+            fee_tier: None
+        }));
+
+        let pool_created = log(
+            vec![RawPoolCreated::signature(), address(1).into(), address(2).into(), H256::from_low_u64_be(500)],
+            vec![Token::Int(U256::from(10)), Token::Address(address(4))]);
+
+        assert_eq!(decode_created_pair_log(&pool_created), Some(CreatedPair {
+            pair: address(4),
+            token0: address(1),
+            token1: address(2),
+            fee_tier: Some(500)
+        }));
+
+        let swap = log(vec![H256::repeat_byte(9)], vec![]);
+
+        assert_eq!(decode_created_pair_log(&swap), None);
+    }
+
+    #[test]
+    fn pairs_are_resolved_from_their_factory() {
+        let rpc = MockRpc::start(Cassette::load("classic"));
+        let factory_address = V2_FACTORY_ADDRESS;
+        let exchanges = HashMap::from([
+            (factory_address.to_string(), Exchange::V2 { name: "Uniswap V2".to_string() })
+        ]);
+        let weth = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2".parse::<Address>().unwrap();
+        let usdc = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".parse::<Address>().unwrap();
+        let runtime = Runtime::new().unwrap();
+
+        assert_eq!(
+            runtime.block_on(fetch_factory_pairs(&rpc.url, &exchanges, weth, usdc, None)).unwrap(),
+            vec![FactoryPair {
+                factory_address: factory_address.to_string(),
+                pair: PAIR_ADDRESS.parse::<Address>().unwrap(),
+                fee_tier: None
+            }]);
+
+        // The cassette's factory hasn't created a pair of WETH and some other token.
+        assert!(runtime.block_on(fetch_factory_pairs(&rpc.url, &exchanges, weth, address(1), None))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn pools_are_resolved_by_fee_tier() {
+        let rpc = MockRpc::start(Cassette::load("classic"));
+        let exchanges = HashMap::from([
+            (V2_FACTORY_ADDRESS.to_string(), Exchange::V2 { name: "Uniswap V2".to_string() }),
+            (V3_FACTORY_ADDRESS.to_string(), Exchange::V3 { name: "Uniswap V3".to_string() })
+        ]);
+        let weth = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2".parse::<Address>().unwrap();
+        let usdc = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".parse::<Address>().unwrap();
+        let runtime = Runtime::new().unwrap();
+
+        let pair = FactoryPair {
+            factory_address: V2_FACTORY_ADDRESS.to_string(),
+            pair: PAIR_ADDRESS.parse::<Address>().unwrap(),
+            fee_tier: None
+        };
+        let pool = FactoryPair {
+            factory_address: V3_FACTORY_ADDRESS.to_string(),
+            pair: POOL_ADDRESS.parse::<Address>().unwrap(),
+            fee_tier: Some(500)
+        };
+
+        // Without a fee tier, the V2 pair and the V3 pools of every fee tier are found.
+        assert_eq!(
+            runtime.block_on(fetch_factory_pairs(&rpc.url, &exchanges, weth, usdc, None)).unwrap(),
+            vec![pool.clone(), pair]);
+
+        // With one, only the V3 pool of that fee tier is.
+        assert_eq!(
+            runtime.block_on(fetch_factory_pairs(&rpc.url, &exchanges, weth, usdc, Some(500))).unwrap(),
+            vec![pool]);
+
+        // The cassette's V3 factory hasn't created a pool with a 0.3% fee.
+        assert!(runtime.block_on(fetch_factory_pairs(&rpc.url, &exchanges, weth, usdc, Some(3_000)))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn created_pairs_are_fetched_from_their_factory() {
+        let rpc = MockRpc::start(Cassette::load("classic"));
+        let weth = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2".parse::<Address>().unwrap();
+        let usdc = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".parse::<Address>().unwrap();
+        let created_pairs = fetch_mock_created_pairs(&rpc);
+
+        assert_eq!(created_pairs.len(), 2);
+
+        let (pair, metadata) = &created_pairs[0];
+        assert_eq!(*pair, CreatedPair {
+            pair: PAIR_ADDRESS.parse::<Address>().unwrap(),
+            token0: weth,
+            token1: usdc,
+            fee_tier: None
+        });
+        assert_eq!(metadata.as_ref().map(|m| (m.base_symbol.as_str(), m.quote_symbol.as_str())), Some(("WETH", "USDC")));
+
+        // The pool's metadata isn't recorded, so it comes without it.
+        let (pool, metadata) = &created_pairs[1];
+        assert_eq!(*pool, CreatedPair {
+            pair: POOL_ADDRESS.parse::<Address>().unwrap(),
+            token0: usdc,
+            token1: weth,
+            fee_tier: Some(500)
+        });
+        assert!(metadata.is_none());
+
+        // Nothing was created in the blocks after.
+        let runtime = Runtime::new().unwrap();
+        assert!(runtime.block_on(fetch_created_pairs(&rpc.url, None, V2_FACTORY_ADDRESS, (17000001, 17000003), 3))
+            .unwrap()
+            .1
+            .is_empty());
+    }
+
+    #[test]
+    fn dense_ranges_are_fetched_in_smaller_windows() {
+        // The provider refuses to return more than one log per request, and the
+        // factory created a pair in block 17000000 and another in block 17000004.
+        let mut cassette = Cassette::load("classic");
+        cassette.max_logs = Some(1);
+        let rpc = MockRpc::start(cassette);
+        let runtime = Runtime::new().unwrap();
+
+        let fetch = |from: u64, max_blocks: u64| runtime
+            .block_on(fetch_created_pairs(&rpc.url, None, V2_FACTORY_ADDRESS, (from, 17000010), max_blocks))
+            .map(|(upper, created_pairs)| (upper, created_pairs.into_iter().map(|(c, _)| c.pair).collect::<Vec<Address>>()));
+
+        // Both pairs are in the first window of ten blocks, so it's halved twice.
+        assert_eq!(fetch(17000000, 10).unwrap(), (17000001, vec![PAIR_ADDRESS.parse::<Address>().unwrap()]));
+
+        // The rest of the range fits.
+        assert_eq!(
+            fetch(17000002, 10).unwrap(),
+            (17000010, vec!["0xc2adda861f89bbb333c90c492cb837741916a225".parse::<Address>().unwrap()]));
+
+        // A single block over the limit can't be split, so it's an error.
+        let mut cassette = Cassette::load("classic");
+        cassette.max_logs = Some(0);
+        let rpc = MockRpc::start(cassette);
+
+        assert!(runtime
+            .block_on(fetch_created_pairs(&rpc.url, None, V2_FACTORY_ADDRESS, (17000000, 17000010), 10))
+            .is_err());
+    }
+
+    #[test]
+    #[ignore = "requires TEST_DATABASE_URL"]
+    fn created_pairs_are_saved_and_failures_retried() {
+        let db_connection = test_db_connection();
+        let rpc = MockRpc::start(Cassette::load("classic"));
+        let created_pairs = fetch_mock_created_pairs(&rpc);
+        let (v2_pairs, v3_pairs) = created_pairs.split_at(1);
+
+        assert_eq!(save_created_pairs(&db_connection, "ethereum", V2_FACTORY_ADDRESS, v2_pairs, Some(17000000)), Ok(1));
+        assert_eq!(save_created_pairs(&db_connection, "ethereum", V3_FACTORY_ADDRESS, v3_pairs, Some(17000000)), Ok(0));
+
+        // The pair is saved with its tokens, and both factories are indexed up to the block.
+        let pair = db::fetch_pair_by_params(&db_connection, "ethereum", PAIR_ADDRESS).unwrap();
+        let base = db::fetch_token_by_id(&db_connection, pair.base_token_id).unwrap();
+        assert_eq!((pair.factory_address.as_str(), pair.fee_tier), (V2_FACTORY_ADDRESS, None));
+        assert_eq!(base.token_symbol, "WETH");
+
+        for factory_address in [V2_FACTORY_ADDRESS, V3_FACTORY_ADDRESS] {
+            assert_eq!(db::fetch_factory_scan_last_block(&db_connection, "ethereum", factory_address), Ok(Some(17000000)));
+        }
+
+        // Saving the pair again doesn't duplicate it.
+        assert_eq!(save_created_pairs(&db_connection, "ethereum", V2_FACTORY_ADDRESS, v2_pairs, Some(17000001)), Ok(0));
+
+        // The pool isn't saved, but recorded for retrying until it has failed too often.
+        assert!(db::fetch_pair_by_params(&db_connection, "ethereum", POOL_ADDRESS).is_err());
+
+        let failed = db::fetch_failed_pairs(&db_connection, "ethereum", V3_FACTORY_ADDRESS, 2).unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(created_pair_from_failed(&failed[0]).as_ref(), Some(&v3_pairs[0].0));
+
+        assert_eq!(save_created_pairs(&db_connection, "ethereum", V3_FACTORY_ADDRESS, v3_pairs, None), Ok(0));
+        assert!(db::fetch_failed_pairs(&db_connection, "ethereum", V3_FACTORY_ADDRESS, 2).unwrap().is_empty());
+
+        // Once its metadata is fetched, retrying saves it and forgets the failure.
+        let retried = [(v3_pairs[0].0.clone(), v2_pairs[0].1.clone())];
+        assert_eq!(save_created_pairs(&db_connection, "ethereum", V3_FACTORY_ADDRESS, &retried, None), Ok(1));
+        assert_eq!(db::fetch_pair_by_params(&db_connection, "ethereum", POOL_ADDRESS).unwrap().fee_tier, Some(500));
+        assert!(db::fetch_failed_pairs(&db_connection, "ethereum", V3_FACTORY_ADDRESS, 10).unwrap().is_empty());
+        assert_eq!(db::fetch_factory_scan_last_block(&db_connection, "ethereum", V3_FACTORY_ADDRESS), Ok(Some(17000000)));
+    }
+
+    #[test]
+    #[ignore = "requires TEST_DATABASE_URL"]
+    fn fee_tiers_are_filled_in_for_existing_pools() {
+        let db_connection = test_db_connection();
+        let rpc = MockRpc::start(Cassette::load("classic"));
+        let created_pairs = fetch_mock_created_pairs(&rpc);
+        let pool = [(created_pairs[1].0.clone(), created_pairs[0].1.clone())];
+
+        // The pool was added without its fee tier, as `/api/pair` does.
+        let base_id = db::insert_token(&db_connection, "USD Coin", "USDC", 6, "ethereum", "0xa0b8").unwrap();
+        let quote_id = db::insert_token(&db_connection, "Wrapped Ether", "WETH", 18, "ethereum", "0xc02a").unwrap();
+        db::insert_pair(&db_connection, "ethereum", V3_FACTORY_ADDRESS, POOL_ADDRESS, base_id, quote_id, None).unwrap();
+
+        // Discovering it fills the fee tier in, without saving it again.
+        assert_eq!(save_created_pairs(&db_connection, "ethereum", V3_FACTORY_ADDRESS, &pool, None), Ok(0));
+        assert_eq!(db::fetch_pair_by_params(&db_connection, "ethereum", POOL_ADDRESS).unwrap().fee_tier, Some(500));
+    }
+
+    #[test]
+    fn token_strings_fit_their_columns() {
+        assert_eq!(fit("Wrapped\0 Ether", 64), "Wrapped Ether");
+        assert_eq!(fit("A VERY LONG SYMBOL INDEED", 16), "A VERY LONG SYMB");
+    }
+}
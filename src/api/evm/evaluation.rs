@@ -0,0 +1,288 @@
+// Measures the sandwich detectors against labeled blocks. Each fixture in
+// `fixtures/detection` makes up one pair's swaps over a few blocks, along with
+// the sandwiches in them. The fixtures are synthetic: the swaps were written
+// by hand rather than recorded from a chain, and the sandwiches were labeled
+// by hand. A detector is scored on how many of its sandwiches are labeled
+// (precision) and how many of the labeled sandwiches it finds (recall). Run
+// with `cargo test evaluation -- --nocapture` to print the report and every
+// sandwich that was missed or wrongly found.
+
+use super::detector::{ DetectorKind, SandwichDetector, DEFAULT_TOLERANCE };
+use super::sandwich::match_sandwiches;
+use super::swap::{ Swap, SwapCore, to_wrapped };
+use crate::api::models::{ Token, Swap as StoredSwap };
+use ethers::types::Address;
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{ Path, PathBuf };
+
+// The lowest precision and recall each detector may score over every fixture
+// at the default tolerance. Raise these whenever detection improves.
+const BASELINES: [(DetectorKind, f64, f64); 3] = [
+    (DetectorKind::AmountRatio, 1.0, 0.875),
+    (DetectorKind::SameSender, 1.0, 0.875),
+    (DetectorKind::TokenFlow, 0.875, 0.875)
+];
+
+// A sandwich, identified by its block and its frontrun and backrun hashes.
+type SandwichKey = (i64, String, String);
+
+#[derive(Debug, Deserialize)]
+struct Fixture {
+    description: String,
+    pair_address: String,
+    base: FixtureToken,
+    quote: FixtureToken,
+    blocks: Vec<FixtureBlock>
+}
+
+#[derive(Debug, Deserialize)]
+struct FixtureToken {
+    symbol: String,
+    decimals: i16
+}
+
+#[derive(Debug, Deserialize)]
+struct FixtureBlock {
+    block_number: i64,
+    note: String,
+    swaps: Vec<FixtureSwap>,
+    sandwiches: Vec<LabeledSandwich> // the ground truth for this block
+}
+
+// A swap as it would be stored by a range scan, with raw token amounts.
+#[derive(Debug, Deserialize)]
+struct FixtureSwap {
+    tx_hash: String,
+    tx_index: i32,
+    log_index: i32,
+    sender: String,
+    recipient: String,
+    amount0_in: String,
+    amount1_in: String,
+    amount0_out: String,
+    amount1_out: String,
+    #[serde(default)]
+    sqrt_price_x96: Option<String>,
+    #[serde(default)]
+    liquidity: Option<String>,
+    #[serde(default)]
+    tick: Option<i32>
+}
+
+#[derive(Debug, Deserialize)]
+struct LabeledSandwich {
+    frontrun: String,
+    backrun: String
+}
+
+// How a detector's sandwiches compare with the labeled ones.
+#[derive(Debug, Default)]
+struct Evaluation {
+    true_positives: usize,
+    false_positives: Vec<SandwichKey>, // detected, but not labeled
+    false_negatives: Vec<SandwichKey> // labeled, but not detected
+}
+
+impl Evaluation {
+    fn precision(&self) -> f64 {
+        ratio(self.true_positives, self.true_positives + self.false_positives.len())
+    }
+
+    fn recall(&self) -> f64 {
+        ratio(self.true_positives, self.true_positives + self.false_negatives.len())
+    }
+
+    fn merge(&mut self, other: Evaluation) {
+        self.true_positives += other.true_positives;
+        self.false_positives.extend(other.false_positives);
+        self.false_negatives.extend(other.false_negatives);
+    }
+
+    // Print the scores, then each sandwich found in error (+) or missed (-).
+    fn report(&self, label: &str) {
+        println!(
+            "{label}: precision {:.3}, recall {:.3} ({} found, {} extra, {} missed)",
+            self.precision(), self.recall(),
+            self.true_positives, self.false_positives.len(), self.false_negatives.len());
+
+        for (block, frontrun, backrun) in self.false_positives.iter() {
+            println!("    + Block {block}: frontrun {frontrun}, backrun {backrun}");
+        }
+
+        for (block, frontrun, backrun) in self.false_negatives.iter() {
+            println!("    - Block {block}: frontrun {frontrun}, backrun {backrun}");
+        }
+    }
+}
+
+// The fraction `a / b`, or 1 if there was nothing to count
+// (e.g. precision when nothing was detected).
+fn ratio(a: usize, b: usize) -> f64 {
+    if b == 0 { 1.0 } else { a as f64 / b as f64 }
+}
+
+impl Fixture {
+    // Load a fixture, checking that every label refers to swaps in its block.
+    fn load(path: &Path) -> Self {
+        let json = fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("could not read {}: {e}", path.display()));
+        let fixture: Fixture = serde_json::from_str(&json)
+            .unwrap_or_else(|e| panic!("could not parse {}: {e}", path.display()));
+
+        for block in fixture.blocks.iter() {
+            let hashes = block.swaps.iter()
+                .map(|swap| swap.tx_hash.to_lowercase())
+                .collect::<BTreeSet<String>>();
+
+            for sandwich in block.sandwiches.iter() {
+                for leg in [&sandwich.frontrun, &sandwich.backrun] {
+                    assert!(
+                        hashes.contains(&leg.to_lowercase()),
+                        "{}: block {} labels {leg}, which is not one of its swaps",
+                        path.display(), block.block_number);
+                }
+            }
+        }
+
+        fixture
+    }
+
+    fn tokens(&self) -> (Token, Token) {
+        let token = |token_id: i32, token: &FixtureToken| Token {
+            token_id,
+            token_name: token.symbol.clone(),
+            token_symbol: token.symbol.clone(),
+            decimals: token.decimals,
+            blockchain_str_id: "ethereum".to_string(),
+            token_address: format!("{:?}", Address::from_low_u64_be(token_id as u64))
+        };
+
+        (token(1, &self.base), token(2, &self.quote))
+    }
+
+    // Run the given detector over every block, as the scanner would
+    // for stored swaps, and compare its sandwiches with the labels.
+    fn evaluate(&self, detector: &dyn SandwichDetector) -> Evaluation {
+        let (base, quote) = self.tokens();
+        let pool = self.pair_address.parse::<Address>().expect("invalid pair address");
+        let mut evaluation = Evaluation::default();
+
+        for block in self.blocks.iter() {
+            let mut bundle = block.swaps.iter()
+                .map(|swap| {
+                    let swap = SwapCore::from_stored(&swap.to_stored(block.block_number), pool)
+                        .unwrap_or_else(|e| panic!("invalid swap {}: {e}", swap.tx_hash));
+                    to_wrapped(swap, 18, &base, &quote)
+                })
+                .collect::<Vec<Swap>>();
+
+            bundle.sort_by_key(|s| s.swap.tx_index());
+
+            let detected = match_sandwiches(&bundle, detector).iter()
+                .map(|sandwich| (
+                    block.block_number,
+                    format!("0x{}", sandwich.frontrun.swap.tx_hash()),
+                    format!("0x{}", sandwich.backrun.swap.tx_hash())))
+                .collect::<BTreeSet<SandwichKey>>();
+
+            let labeled = block.sandwiches.iter()
+                .map(|sandwich| (
+                    block.block_number,
+                    sandwich.frontrun.to_lowercase(),
+                    sandwich.backrun.to_lowercase()))
+                .collect::<BTreeSet<SandwichKey>>();
+
+            evaluation.true_positives += detected.intersection(&labeled).count();
+            evaluation.false_positives.extend(detected.difference(&labeled).cloned());
+            evaluation.false_negatives.extend(labeled.difference(&detected).cloned());
+        }
+
+        evaluation
+    }
+}
+
+impl FixtureSwap {
+    fn to_stored(&self, block_number: i64) -> StoredSwap {
+        StoredSwap {
+            swap_id: 0,
+            pair_id: 0,
+            block_number,
+            tx_hash: self.tx_hash.clone(),
+            tx_index: self.tx_index,
+            log_index: self.log_index,
+            sender: self.sender.clone(),
+            recipient: self.recipient.clone(),
+            amount0_in: self.amount0_in.clone(),
+            amount1_in: self.amount1_in.clone(),
+            amount0_out: self.amount0_out.clone(),
+            amount1_out: self.amount1_out.clone(),
+            sqrt_price_x96: self.sqrt_price_x96.clone(),
+            liquidity: self.liquidity.clone(),
+            tick: self.tick
+        }
+    }
+}
+
+// Load every fixture in `fixtures/detection`, sorted by file name.
+fn load_fixtures() -> Vec<(String, Fixture)> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("detection");
+    let mut paths = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("could not read {}: {e}", dir.display()))
+        .map(|entry| entry.expect("could not read fixture entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect::<Vec<PathBuf>>();
+
+    paths.sort();
+
+    paths.iter()
+        .map(|path| {
+            let name = path.file_stem().unwrap().to_string_lossy().to_string();
+            (name, Fixture::load(path))
+        })
+        .collect()
+}
+
+#[test]
+fn fixtures_are_labeled() {
+    let fixtures = load_fixtures();
+    assert!(!fixtures.is_empty(), "no detection fixtures found");
+
+    for (name, fixture) in fixtures.iter() {
+        println!("{name}: {}", fixture.description);
+
+        for block in fixture.blocks.iter() {
+            println!(
+                " -- Block {}: {} swaps, {} sandwiches. {}",
+                block.block_number, block.swaps.len(), block.sandwiches.len(), block.note);
+        }
+    }
+}
+
+#[test]
+fn detectors_meet_baselines() {
+    let fixtures = load_fixtures();
+
+    for (kind, min_precision, min_recall) in BASELINES {
+        let detector = kind.build(DEFAULT_TOLERANCE);
+        let mut total = Evaluation::default();
+
+        println!("\n{}:", detector.version());
+
+        for (name, fixture) in fixtures.iter() {
+            let evaluation = fixture.evaluate(detector.as_ref());
+            evaluation.report(&format!("  {name}"));
+            total.merge(evaluation);
+        }
+
+        total.report("  total");
+
+        assert!(
+            total.precision() >= min_precision,
+            "{} precision {:.3} fell below {min_precision}", detector.name(), total.precision());
+        assert!(
+            total.recall() >= min_recall,
+            "{} recall {:.3} fell below {min_recall}", detector.name(), total.recall());
+    }
+}
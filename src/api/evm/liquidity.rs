@@ -0,0 +1,378 @@
+use ethers::prelude::{ EthEvent, LogMeta };
+use ethers::types::{ Address, U64, U128, U256, I256, TxHash };
+use ethers::utils::format_units;
+use super::swap::Swap;
+use super::super::models::Token;
+
+#[derive(Clone, Debug, EthEvent)]
+#[ethevent(name = "Mint", abi = "Mint(address,address,int24,int24,uint128,uint256,uint256)")]
+pub struct RawMintV3 {
+    pub sender: Address,
+    #[ethevent(indexed)]
+    pub owner: Address,
+    #[ethevent(indexed, name = "tickLower")]
+    pub tick_lower: I256,
+    #[ethevent(indexed, name = "tickUpper")]
+    pub tick_upper: I256,
+    pub amount: U128,
+    pub amount0: U256,
+    pub amount1: U256
+}
+
+#[derive(Clone, Debug, EthEvent)]
+#[ethevent(name = "Burn", abi = "Burn(address,int24,int24,uint128,uint256,uint256)")]
+pub struct RawBurnV3 {
+    #[ethevent(indexed)]
+    pub owner: Address,
+    #[ethevent(indexed, name = "tickLower")]
+    pub tick_lower: I256,
+    #[ethevent(indexed, name = "tickUpper")]
+    pub tick_upper: I256,
+    pub amount: U128,
+    pub amount0: U256,
+    pub amount1: U256
+}
+
+#[derive(Clone, Debug, EthEvent)]
+#[ethevent(name = "Collect", abi = "Collect(address,address,int24,int24,uint128,uint128)")]
+pub struct RawCollectV3 {
+    #[ethevent(indexed)]
+    pub owner: Address,
+    pub recipient: Address,
+    #[ethevent(indexed, name = "tickLower")]
+    pub tick_lower: I256,
+    #[ethevent(indexed, name = "tickUpper")]
+    pub tick_upper: I256,
+    pub amount0: U128,
+    pub amount1: U128
+}
+
+// The kind of change made to a V3 liquidity position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiquidityAction {
+    Mint,
+    Burn,
+    Collect
+}
+
+// A Mint, Burn or Collect on a V3 pool, reduced to what JIT detection needs.
+#[derive(Debug, Clone)]
+pub struct LiquidityEvent {
+    pub action: LiquidityAction,
+    block_number: U64,
+    pub tx_hash: TxHash,
+    tx_index: U64,
+    log_index: U256,
+    pub owner: Address,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128, // zero for Collect events
+    amount0: U256,
+    amount1: U256
+}
+
+impl LiquidityEvent {
+    pub fn block_number(&self) -> u64 {
+        self.block_number.as_u64()
+    }
+
+    pub fn tx_index(&self) -> i32 {
+        self.tx_index.as_u32().try_into().unwrap_or(i32::MAX)
+    }
+
+    // The position this event changed: its owner and tick range.
+    fn position(&self) -> (Address, i32, i32) {
+        (self.owner, self.tick_lower, self.tick_upper)
+    }
+
+    // Whether this event comes after the given one in the block.
+    fn is_after(&self, other: &LiquidityEvent) -> bool {
+        (self.tx_index, self.log_index) > (other.tx_index, other.log_index)
+    }
+
+    fn new(
+        action: LiquidityAction,
+        meta: LogMeta,
+        (owner, tick_lower, tick_upper): (Address, I256, I256),
+        liquidity: u128,
+        amount0: U256,
+        amount1: U256
+    ) -> Self {
+        Self {
+            action,
+            block_number: meta.block_number,
+            tx_hash: meta.transaction_hash,
+            tx_index: meta.transaction_index,
+            log_index: meta.log_index,
+            owner,
+            tick_lower: tick_lower.low_i32(),
+            tick_upper: tick_upper.low_i32(),
+            liquidity,
+            amount0,
+            amount1
+        }
+    }
+}
+
+impl From<(RawMintV3, LogMeta)> for LiquidityEvent {
+    fn from((mint, meta): (RawMintV3, LogMeta)) -> Self {
+        Self::new(
+            LiquidityAction::Mint,
+            meta,
+            (mint.owner, mint.tick_lower, mint.tick_upper),
+            mint.amount.as_u128(),
+            mint.amount0,
+            mint.amount1)
+    }
+}
+
+impl From<(RawBurnV3, LogMeta)> for LiquidityEvent {
+    fn from((burn, meta): (RawBurnV3, LogMeta)) -> Self {
+        Self::new(
+            LiquidityAction::Burn,
+            meta,
+            (burn.owner, burn.tick_lower, burn.tick_upper),
+            burn.amount.as_u128(),
+            burn.amount0,
+            burn.amount1)
+    }
+}
+
+impl From<(RawCollectV3, LogMeta)> for LiquidityEvent {
+    fn from((collect, meta): (RawCollectV3, LogMeta)) -> Self {
+        Self::new(
+            LiquidityAction::Collect,
+            meta,
+            (collect.owner, collect.tick_lower, collect.tick_upper),
+            0,
+            U256::from(collect.amount0.as_u128()),
+            U256::from(collect.amount1.as_u128()))
+    }
+}
+
+// A just-in-time liquidity attack: a position minted right before
+// one or more swaps and burned (and collected) right after them.
+pub struct JitAttack<'a> {
+    pub mint: LiquidityEvent,
+    pub swaps: Vec<Swap<'a>>,
+    pub burn: LiquidityEvent,
+    pub collect: Option<LiquidityEvent>
+}
+
+impl<'a> JitAttack<'a> {
+    pub fn base_minted(&self, base: &Token) -> f64 {
+        to_f64(self.mint.amount0, base)
+    }
+
+    pub fn quote_minted(&self, quote: &Token) -> f64 {
+        to_f64(self.mint.amount1, quote)
+    }
+
+    pub fn base_burned(&self, base: &Token) -> f64 {
+        to_f64(self.burn.amount0, base)
+    }
+
+    pub fn quote_burned(&self, quote: &Token) -> f64 {
+        to_f64(self.burn.amount1, quote)
+    }
+
+    // Collect pays out the burned principal plus the swap fees earned,
+    // so the fees are whatever was collected beyond the burned amounts.
+    pub fn base_fees(&self, base: &Token) -> f64 {
+        match &self.collect {
+            Some(collect) => to_f64(collect.amount0.saturating_sub(self.burn.amount0), base),
+            None => 0f64
+        }
+    }
+
+    pub fn quote_fees(&self, quote: &Token) -> f64 {
+        match &self.collect {
+            Some(collect) => to_f64(collect.amount1.saturating_sub(self.burn.amount1), quote),
+            None => 0f64
+        }
+    }
+}
+
+// Find JIT attacks in a single block's liquidity events and swaps on one pool.
+// A Mint is matched with the first later Burn of the same position (owner and
+// tick range) and liquidity amount, provided at least one swap lands between
+// them; the first unused Collect of that position after the Burn, in the
+// Burn's transaction, holds the fees.
+pub fn find_jit_attacks<'a>(
+    events: &[LiquidityEvent],
+    swaps: &[Swap<'a>]
+) -> Vec<JitAttack<'a>> {
+    let mut events = events.to_vec();
+    events.sort_by_key(|e| (e.tx_index, e.log_index));
+
+    let mut attacks = vec![];
+    let mut used_burns = vec![false; events.len()];
+    let mut used_collects = vec![false; events.len()];
+
+    for mint in events.iter().filter(|e| e.action == LiquidityAction::Mint) {
+        let burn = events.iter().enumerate().find(|(b, e)| {
+            !used_burns[*b] &&
+                e.action == LiquidityAction::Burn &&
+                e.position() == mint.position() &&
+                e.liquidity == mint.liquidity &&
+                e.is_after(mint)
+        });
+
+        let (b, burn) = match burn {
+            Some(burn) => burn,
+            None => continue
+        };
+
+        let swaps_between = swaps.iter()
+            .filter(|s| mint.tx_index() < s.swap.tx_index() && s.swap.tx_index() < burn.tx_index())
+            .cloned()
+            .collect::<Vec<Swap>>();
+
+        if swaps_between.is_empty() {
+            continue;
+        }
+
+        let collect = events.iter().enumerate().find(|(c, e)| {
+            !used_collects[*c] &&
+                e.action == LiquidityAction::Collect &&
+                e.position() == burn.position() &&
+                e.tx_index == burn.tx_index &&
+                e.is_after(burn)
+        });
+
+        if let Some((c, _)) = collect {
+            used_collects[c] = true;
+        }
+
+        used_burns[b] = true;
+        attacks.push(JitAttack {
+            mint: mint.clone(),
+            swaps: swaps_between,
+            burn: burn.clone(),
+            collect: collect.map(|(_, collect)| collect.clone())
+        });
+    }
+
+    attacks
+}
+
+fn to_f64(amount: U256, token: &Token) -> f64 {
+    format_units(amount, token.decimals as u32).unwrap_or("0.0".to_string()).parse::<f64>().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::swap::{ SwapCore, to_wrapped };
+    use ethers::types::H256;
+
+    const OWNER: Address = Address::repeat_byte(0xaa);
+
+    fn event(
+        action: LiquidityAction,
+        (tx_index, log_index): (u64, u64),
+        ticks: (i32, i32),
+        liquidity: u128,
+        (amount0, amount1): (u64, u64)
+    ) -> LiquidityEvent {
+        let meta = LogMeta {
+            address: Address::zero(),
+            block_number: U64::one(),
+            block_hash: H256::zero(),
+            transaction_hash: TxHash::from_low_u64_be(tx_index + 1),
+            transaction_index: U64::from(tx_index),
+            log_index: U256::from(log_index)
+        };
+
+        LiquidityEvent::new(
+            action,
+            meta,
+            (OWNER, I256::from(ticks.0), I256::from(ticks.1)),
+            liquidity,
+            U256::from(amount0),
+            U256::from(amount1))
+    }
+
+    #[test]
+    fn jit_attacks_are_found_with_their_fees() {
+        let (base, quote) = (Token::mock(1, 0), Token::mock(2, 0));
+        let swaps = vec![
+            to_wrapped(SwapCore::mock(1, Address::zero(), Address::zero(), Address::zero(), (50, 0, 0, 40)), 18, &base, &quote)
+        ];
+        let events = vec![
+            event(LiquidityAction::Collect, (2, 4), (-60, 60), 0, (1_003, 1_996)),
+            event(LiquidityAction::Burn, (2, 3), (-60, 60), 500, (1_000, 1_990)),
+            event(LiquidityAction::Mint, (0, 0), (-60, 60), 500, (1_000, 2_000))
+        ];
+
+        let attacks = find_jit_attacks(&events, &swaps);
+
+        assert_eq!(attacks.len(), 1);
+        assert_eq!(attacks[0].swaps.len(), 1);
+        assert_eq!((attacks[0].base_minted(&base), attacks[0].quote_minted(&quote)), (1_000.0, 2_000.0));
+        assert_eq!((attacks[0].base_burned(&base), attacks[0].quote_burned(&quote)), (1_000.0, 1_990.0));
+        assert_eq!((attacks[0].base_fees(&base), attacks[0].quote_fees(&quote)), (3.0, 6.0));
+    }
+
+    #[test]
+    fn collects_are_only_counted_once() {
+        let (base, quote) = (Token::mock(1, 0), Token::mock(2, 0));
+        let swaps = vec![
+            to_wrapped(SwapCore::mock(1, Address::zero(), Address::zero(), Address::zero(), (50, 0, 0, 40)), 18, &base, &quote),
+            to_wrapped(SwapCore::mock(4, Address::zero(), Address::zero(), Address::zero(), (50, 0, 0, 40)), 18, &base, &quote)
+        ];
+
+        // Two attacks on the same position, the first of which is collected later.
+        let events = vec![
+            event(LiquidityAction::Mint, (0, 0), (-60, 60), 500, (1_000, 2_000)),
+            event(LiquidityAction::Burn, (2, 1), (-60, 60), 500, (1_000, 1_990)),
+            event(LiquidityAction::Mint, (3, 0), (-60, 60), 500, (1_000, 2_000)),
+            event(LiquidityAction::Burn, (5, 1), (-60, 60), 500, (1_000, 1_990)),
+            event(LiquidityAction::Collect, (5, 2), (-60, 60), 0, (1_003, 1_996)),
+            event(LiquidityAction::Collect, (6, 0), (-60, 60), 0, (1_003, 1_996))
+        ];
+
+        let attacks = find_jit_attacks(&events, &swaps);
+
+        assert_eq!(attacks.len(), 2);
+        assert!(attacks[0].collect.is_none());
+        assert_eq!((attacks[1].base_fees(&base), attacks[1].quote_fees(&quote)), (3.0, 6.0));
+
+        // Two attacks burned and collected in one transaction each take their own Collect.
+        let events = vec![
+            event(LiquidityAction::Mint, (0, 0), (-60, 60), 500, (1_000, 2_000)),
+            event(LiquidityAction::Mint, (0, 1), (-60, 60), 500, (1_000, 2_000)),
+            event(LiquidityAction::Burn, (2, 0), (-60, 60), 500, (1_000, 1_990)),
+            event(LiquidityAction::Collect, (2, 1), (-60, 60), 0, (1_003, 1_996)),
+            event(LiquidityAction::Burn, (2, 2), (-60, 60), 500, (1_000, 1_990)),
+            event(LiquidityAction::Collect, (2, 3), (-60, 60), 0, (1_001, 1_992))
+        ];
+
+        let attacks = find_jit_attacks(&events, &swaps[..1]);
+
+        assert_eq!(attacks.len(), 2);
+        assert_eq!((attacks[0].base_fees(&base), attacks[0].quote_fees(&quote)), (3.0, 6.0));
+        assert_eq!((attacks[1].base_fees(&base), attacks[1].quote_fees(&quote)), (1.0, 2.0));
+    }
+
+    #[test]
+    fn positions_must_match_around_a_swap() {
+        let (base, quote) = (Token::mock(1, 0), Token::mock(2, 0));
+        let swaps = vec![
+            to_wrapped(SwapCore::mock(1, Address::zero(), Address::zero(), Address::zero(), (50, 0, 0, 40)), 18, &base, &quote)
+        ];
+        let mint = event(LiquidityAction::Mint, (0, 0), (-60, 60), 500, (1_000, 2_000));
+
+        // A burn of another tick range, or of a different amount of liquidity.
+        for burn in [
+            event(LiquidityAction::Burn, (2, 0), (-120, 120), 500, (1_000, 1_990)),
+            event(LiquidityAction::Burn, (2, 0), (-60, 60), 400, (800, 1_590))
+        ] {
+            assert!(find_jit_attacks(&[mint.clone(), burn], &swaps).is_empty());
+        }
+
+        // A burn before the swap.
+        let burn = event(LiquidityAction::Burn, (0, 1), (-60, 60), 500, (1_000, 2_000));
+        assert!(find_jit_attacks(&[mint, burn], &swaps).is_empty());
+    }
+}
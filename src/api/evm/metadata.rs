@@ -1,13 +1,20 @@
 use ethers::prelude::{ Provider, Http, Middleware, Contract };
-use ethers::abi::AbiParser;
-use ethers::types::Address;
-use ethers::utils::hex;
+use ethers::abi::{ self, AbiParser, ParamType, Token };
+use ethers::types::{ Address, Bytes, TransactionRequest };
+use ethers::utils::{ hex, id };
 use std::sync::Arc;
 use std::convert::From;
 
 type RpcError = Box<dyn std::error::Error + Send + Sync>;
 type Metadata = (Address, Address, String, String, u8, Address, String, String, u8);
 
+// Multicall3 is deployed at the same address on most chains.
+const MULTICALL3_ADDRESS: &str = "0xcA11bde05977b3631167028862bE2a173976CA11";
+
+// How many pairs to fetch the metadata of per request, which keeps each
+// `eth_call` well under providers' gas and response size limits.
+const METADATA_BATCH_SIZE: usize = 100;
+
 #[derive(Debug, Clone)]
 pub struct PairMetadata {
     pub factory_address: String,
@@ -37,6 +44,17 @@ impl From<Metadata> for PairMetadata {
     }
 }
 
+// Fetches the hash of the given block from the given provider.
+pub async fn fetch_block_hash(
+    provider_url: &str,
+    block_number: u64
+) -> Result<String, RpcError> {
+    let provider = Provider::<Http>::try_from(provider_url)?;
+    let block = provider.get_block(block_number).await?.ok_or("block not found")?;
+
+    Ok(format!("{:?}", block.hash.ok_or("block hash not found")?))
+}
+
 // Fetches the latest block number from the given provider.
 pub async fn fetch_latest_block_number(
     provider_url: &str
@@ -45,9 +63,32 @@ pub async fn fetch_latest_block_number(
     Ok(provider.get_block_number().await?.as_u64())
 }
 
-// Fetches the pair (and base and quote token) metadata
-// from the blockchain via the DataAggregator contract.
+// Fetches the chain id reported by the given provider.
+pub async fn fetch_chain_id(
+    provider_url: &str
+) -> Result<u64, RpcError> {
+    let provider = Provider::<Http>::try_from(provider_url)?;
+    Ok(provider.get_chainid().await?.as_u64())
+}
+
+// Fetches the pair (and base and quote token) metadata from the blockchain,
+// via the DataAggregator contract if one is deployed on the chain, or else
+// by calling the pair and its tokens directly.
 pub async fn fetch_pair_metadata(
+    provider_url: &str, 
+    pair_address: &str,
+    data_aggregator_address: Option<&str>
+) -> Result<PairMetadata, RpcError> {
+    match data_aggregator_address {
+        Some(data_aggregator_address) => {
+            fetch_pair_metadata_from_aggregator(provider_url, pair_address, data_aggregator_address).await
+        },
+        None => fetch_pair_metadata_directly(provider_url, pair_address).await
+    }
+}
+
+// Fetches the pair metadata in one call to the DataAggregator contract.
+async fn fetch_pair_metadata_from_aggregator(
     provider_url: &str, 
     pair_address: &str,
     data_aggregator_address: &str
@@ -70,4 +111,378 @@ pub async fn fetch_pair_metadata(
         .await?;
 
     Ok(PairMetadata::from(metadata))
-}
\ No newline at end of file
+}
+
+// Fetches the metadata of many pairs, `METADATA_BATCH_SIZE` pairs per request,
+// returning each pair's metadata or why it couldn't be fetched (e.g. because
+// the address isn't a pair). Uses the DataAggregator's `getMetadataBatch`
+// if it has one (older deployments don't), or else calls the pairs directly.
+pub async fn fetch_many_pair_metadata(
+    provider_url: &str,
+    pair_addresses: &[Address],
+    data_aggregator_address: Option<&str>
+) -> Result<Vec<Result<PairMetadata, RpcError>>, RpcError> {
+    let provider = Provider::<Http>::try_from(provider_url)?;
+    let data_aggregator_address = data_aggregator_address.map(|a| a.parse::<Address>()).transpose()?;
+    let mut metadata = Vec::new();
+
+    for batch in pair_addresses.chunks(METADATA_BATCH_SIZE) {
+        let batch_metadata = match data_aggregator_address {
+            Some(data_aggregator_address) => {
+                match fetch_batch_from_aggregator(&provider, batch, data_aggregator_address).await {
+                    Ok(batch_metadata) => batch_metadata,
+                    Err(_) => fetch_batch_directly(&provider, batch).await?
+                }
+            },
+            None => fetch_batch_directly(&provider, batch).await?
+        };
+
+        metadata.extend(batch_metadata);
+    }
+
+    Ok(metadata)
+}
+
+// Fetches the pair metadata with the same calls the DataAggregator makes.
+async fn fetch_pair_metadata_directly(
+    provider_url: &str,
+    pair_address: &str
+) -> Result<PairMetadata, RpcError> {
+    let provider = Provider::<Http>::try_from(provider_url)?;
+    let pair = pair_address.parse::<Address>()?;
+
+    fetch_batch_directly(&provider, &[pair]).await?.remove(0)
+}
+
+// Fetches a batch of pairs' metadata with one call to `getMetadataBatch`.
+async fn fetch_batch_from_aggregator(
+    provider: &Provider<Http>,
+    pair_addresses: &[Address],
+    data_aggregator_address: Address
+) -> Result<Vec<Result<PairMetadata, RpcError>>, RpcError> {
+    let data = [
+        id("getMetadataBatch(address[])").to_vec(),
+        abi::encode(&[Token::Array(pair_addresses.iter().map(|a| Token::Address(*a)).collect())])
+    ].concat();
+
+    let call = TransactionRequest::new().to(data_aggregator_address).data(data);
+    let output = provider.call(&call.into(), None).await?;
+
+    let entry = ParamType::Tuple(vec![
+        ParamType::Bool,
+        ParamType::Address,
+        ParamType::Address, ParamType::String, ParamType::String, ParamType::Uint(8),
+        ParamType::Address, ParamType::String, ParamType::String, ParamType::Uint(8)
+    ]);
+
+    let entries = match abi::decode(&[ParamType::Array(Box::new(entry))], output.as_ref())?.pop() {
+        Some(Token::Array(entries)) if entries.len() == pair_addresses.len() => entries,
+        _ => return Err("invalid getMetadataBatch output".into())
+    };
+
+    Ok(entries.into_iter()
+        .map(|entry| match entry {
+            Token::Tuple(fields) => match fields.as_slice() {
+                [
+                    Token::Bool(true),
+                    Token::Address(factory),
+                    Token::Address(token0), Token::String(name0), Token::String(symbol0), Token::Uint(decimals0),
+                    Token::Address(token1), Token::String(name1), Token::String(symbol1), Token::Uint(decimals1)
+                ] => Ok(PairMetadata::from((
+                    *factory,
+                    *token0, name0.clone(), symbol0.clone(), decimals0.low_u32() as u8,
+                    *token1, name1.clone(), symbol1.clone(), decimals1.low_u32() as u8
+                ))),
+                [Token::Bool(false), ..] => Err("metadata not found".into()),
+                _ => Err("invalid metadata".into())
+            },
+            _ => Err("invalid metadata".into())
+        })
+        .collect())
+}
+
+// Fetches a batch of pairs' metadata with the calls the DataAggregator makes:
+// `factory()`, `token0()` and `token1()` on each pair, then `name()`,
+// `symbol()` and `decimals()` on each of their tokens, in two rounds of calls.
+async fn fetch_batch_directly(
+    provider: &Provider<Http>,
+    pair_addresses: &[Address]
+) -> Result<Vec<Result<PairMetadata, RpcError>>, RpcError> {
+    let calls = pair_addresses.iter()
+        .flat_map(|pair| [(*pair, "factory()"), (*pair, "token0()"), (*pair, "token1()")])
+        .collect::<Vec<(Address, &str)>>();
+
+    let pairs = call_all(provider, &calls).await?
+        .chunks(3)
+        .map(|outputs| Ok((decode_address(&outputs[0])?, decode_address(&outputs[1])?, decode_address(&outputs[2])?)))
+        .collect::<Vec<Result<(Address, Address, Address), RpcError>>>();
+
+    // Only call the tokens of the pairs whose calls succeeded.
+    let calls = pairs.iter()
+        .flatten()
+        .flat_map(|(_, token0, token1)| [
+            (*token0, "name()"), (*token0, "symbol()"), (*token0, "decimals()"),
+            (*token1, "name()"), (*token1, "symbol()"), (*token1, "decimals()")
+        ])
+        .collect::<Vec<(Address, &str)>>();
+
+    let mut outputs = call_all(provider, &calls).await?.into_iter();
+
+    Ok(pairs.into_iter()
+        .map(|pair| {
+            let (factory, token0, token1) = pair?;
+            let outputs = outputs.by_ref().take(6).collect::<Vec<Option<Bytes>>>();
+
+            Ok(PairMetadata::from((
+                factory,
+                token0,
+                decode_string(&outputs[0])?,
+                decode_string(&outputs[1])?,
+                decode_decimals(&outputs[2])?,
+                token1,
+                decode_string(&outputs[3])?,
+                decode_string(&outputs[4])?,
+                decode_decimals(&outputs[5])?
+            )))
+        })
+        .collect())
+}
+
+// Makes the given calls (of functions without arguments), returning each
+// one's output, or `None` if it failed.
+pub(super) async fn call_all(
+    provider: &Provider<Http>,
+    calls: &[(Address, &str)]
+) -> Result<Vec<Option<Bytes>>, RpcError> {
+    let calls = calls.iter()
+        .map(|(to, signature)| (*to, id(signature).to_vec()))
+        .collect::<Vec<(Address, Vec<u8>)>>();
+
+    call_all_with_data(provider, &calls).await
+}
+
+// Makes the given calls (each with its own input data), returning each
+// one's output, or `None` if it failed. They're batched into one request
+// through Multicall3 where it's deployed, or else made one at a time.
+pub(super) async fn call_all_with_data(
+    provider: &Provider<Http>,
+    calls: &[(Address, Vec<u8>)]
+) -> Result<Vec<Option<Bytes>>, RpcError> {
+    if let Ok(outputs) = multicall(provider, calls).await {
+        return Ok(outputs);
+    }
+
+    let mut outputs = Vec::new();
+
+    for (to, data) in calls.iter() {
+        let call = TransactionRequest::new().to(*to).data(data.clone());
+        outputs.push(provider.call(&call.into(), None).await.ok());
+    }
+
+    Ok(outputs)
+}
+
+// Makes the given calls through Multicall3's `aggregate3`, allowing each to fail.
+async fn multicall(
+    provider: &Provider<Http>,
+    calls: &[(Address, Vec<u8>)]
+) -> Result<Vec<Option<Bytes>>, RpcError> {
+    let calls = calls.iter()
+        .map(|(to, data)| Token::Tuple(vec![
+            Token::Address(*to),
+            Token::Bool(true), // allow failure
+            Token::Bytes(data.clone())
+        ]))
+        .collect();
+
+    let data = [
+        id("aggregate3((address,bool,bytes)[])").to_vec(),
+        abi::encode(&[Token::Array(calls)])
+    ].concat();
+
+    let call = TransactionRequest::new().to(MULTICALL3_ADDRESS.parse::<Address>()?).data(data);
+    let output = provider.call(&call.into(), None).await?;
+
+    // Calling an address without code "succeeds" with no output,
+    // so this also fails where Multicall3 isn't deployed.
+    let results = abi::decode(
+        &[ParamType::Array(Box::new(ParamType::Tuple(vec![ParamType::Bool, ParamType::Bytes])))],
+        output.as_ref())?;
+
+    let results = match results.into_iter().next() {
+        Some(Token::Array(results)) => results,
+        _ => return Err("invalid multicall output".into())
+    };
+
+    Ok(results.into_iter()
+        .map(|result| match result {
+            Token::Tuple(result) => match result.as_slice() {
+                [Token::Bool(true), Token::Bytes(output)] => Some(Bytes::from(output.clone())),
+                _ => None
+            },
+            _ => None
+        })
+        .collect())
+}
+
+pub(super) fn decode_address(output: &Option<Bytes>) -> Result<Address, RpcError> {
+    let output: &[u8] = output.as_ref().ok_or("call failed")?.as_ref();
+
+    match abi::decode(&[ParamType::Address], output)?.as_slice() {
+        [Token::Address(address)] => Ok(*address),
+        _ => Err("invalid address".into())
+    }
+}
+
+fn decode_decimals(output: &Option<Bytes>) -> Result<u8, RpcError> {
+    let output: &[u8] = output.as_ref().ok_or("call failed")?.as_ref();
+
+    match abi::decode(&[ParamType::Uint(8)], output)?.as_slice() {
+        [Token::Uint(decimals)] if *decimals <= u8::MAX.into() => Ok(decimals.as_u32() as u8),
+        _ => Err("invalid decimals".into())
+    }
+}
+
+// Decodes a `name()` or `symbol()` output as a string or, for tokens like
+// MKR that return a bytes32, as the bytes before the first zero byte
+// (like `DataUtils.bytes32ToString`).
+fn decode_string(output: &Option<Bytes>) -> Result<String, RpcError> {
+    let output: &[u8] = output.as_ref().ok_or("call failed")?.as_ref();
+
+    if let Ok(tokens) = abi::decode(&[ParamType::String], output) {
+        if let [Token::String(string)] = tokens.as_slice() {
+            return Ok(string.clone());
+        }
+    }
+
+    match abi::decode(&[ParamType::FixedBytes(32)], output)?.as_slice() {
+        [Token::FixedBytes(bytes)] => {
+            let length = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+            Ok(String::from_utf8_lossy(&bytes[..length]).to_string())
+        },
+        _ => Err("invalid string".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::mock_rpc::{ Cassette, MockRpc };
+    use tokio::runtime::Runtime;
+
+    const PAIR_ADDRESS: &str = "0x11982c91f8f0438475b20e67ff9dbe5aa0d25db2";
+    const MKR_PAIR_ADDRESS: &str = "0xc2adda861f89bbb333c90c492cb837741916a225";
+    const DATA_AGGREGATOR_ADDRESS: &str = "0xda7ada7ada7ada7ada7ada7ada7ada7ada7ada7a";
+
+    #[test]
+    fn pair_metadata_is_decoded() {
+        let rpc = MockRpc::start(Cassette::load("classic"));
+        let metadata = Runtime::new().unwrap()
+            .block_on(fetch_pair_metadata(&rpc.url, PAIR_ADDRESS, Some(DATA_AGGREGATOR_ADDRESS)))
+            .unwrap();
+
+        assert_eq!(metadata.factory_address, "5c69bee701ef814a2b6a3edd4b1652cb9cc5aa6f");
+        assert_eq!(metadata.base_address, "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        assert_eq!(metadata.quote_address, "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        assert_eq!((metadata.base_name.as_str(), metadata.base_symbol.as_str()), ("Wrapped Ether", "WETH"));
+        assert_eq!((metadata.quote_name.as_str(), metadata.quote_symbol.as_str()), ("USD Coin", "USDC"));
+        assert_eq!((metadata.base_decimals, metadata.quote_decimals), (18, 6));
+    }
+
+    #[test]
+    fn pair_metadata_is_fetched_without_the_aggregator() {
+        // The cassette has no Multicall3, so the calls are made one at a time.
+        let rpc = MockRpc::start(Cassette::load("classic"));
+        let runtime = Runtime::new().unwrap();
+        let direct = runtime.block_on(fetch_pair_metadata(&rpc.url, PAIR_ADDRESS, None)).unwrap();
+        let aggregated = runtime
+            .block_on(fetch_pair_metadata(&rpc.url, PAIR_ADDRESS, Some(DATA_AGGREGATOR_ADDRESS)))
+            .unwrap();
+
+        assert_eq!(format!("{direct:?}"), format!("{aggregated:?}"));
+    }
+
+    #[test]
+    fn bytes32_names_are_decoded_through_multicall() {
+        let rpc = MockRpc::start(Cassette::load("classic"));
+        let metadata = Runtime::new().unwrap()
+            .block_on(fetch_pair_metadata(&rpc.url, MKR_PAIR_ADDRESS, None))
+            .unwrap();
+
+        assert_eq!(metadata.factory_address, "5c69bee701ef814a2b6a3edd4b1652cb9cc5aa6f");
+        assert_eq!(metadata.base_address, "9f8f72aa9304c8b593d555f12ef6589cc3a579a2");
+        assert_eq!((metadata.base_name.as_str(), metadata.base_symbol.as_str()), ("Maker", "MKR"));
+        assert_eq!((metadata.quote_name.as_str(), metadata.quote_symbol.as_str()), ("Wrapped Ether", "WETH"));
+        assert_eq!((metadata.base_decimals, metadata.quote_decimals), (18, 18));
+    }
+
+    #[test]
+    fn unknown_pairs_are_an_error() {
+        let rpc = MockRpc::start(Cassette::load("classic"));
+        let result = Runtime::new().unwrap().block_on(fetch_pair_metadata(
+            &rpc.url, "0x0000000000000000000000000000000000000001", Some(DATA_AGGREGATOR_ADDRESS)));
+
+        assert!(result.is_err());
+
+        let result = Runtime::new().unwrap().block_on(fetch_pair_metadata(
+            &rpc.url, "0x0000000000000000000000000000000000000001", None));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn failed_entries_dont_fail_the_batch() {
+        // The cassette's DataAggregator only answers `getMetadataBatch` for
+        // another batch, so this also falls back to calling the pairs directly.
+        let rpc = MockRpc::start(Cassette::load("classic"));
+        let pair_addresses = [PAIR_ADDRESS, "0x0000000000000000000000000000000000000001"]
+            .map(|a| a.parse::<Address>().unwrap());
+        let metadata = Runtime::new().unwrap()
+            .block_on(fetch_many_pair_metadata(&rpc.url, &pair_addresses, Some(DATA_AGGREGATOR_ADDRESS)))
+            .unwrap();
+
+        assert_eq!(metadata.len(), 2);
+        assert_eq!(metadata[0].as_ref().unwrap().base_symbol, "WETH");
+        assert!(metadata[1].is_err());
+    }
+
+    #[test]
+    fn batches_are_decoded_from_the_aggregator() {
+        let rpc = MockRpc::start(Cassette::load("classic"));
+        let provider = Provider::<Http>::try_from(rpc.url.as_str()).unwrap();
+        let pair_addresses = [PAIR_ADDRESS, MKR_PAIR_ADDRESS, "0x0000000000000000000000000000000000000001"]
+            .map(|a| a.parse::<Address>().unwrap());
+        let metadata = Runtime::new().unwrap()
+            .block_on(fetch_batch_from_aggregator(
+                &provider, &pair_addresses, DATA_AGGREGATOR_ADDRESS.parse::<Address>().unwrap()))
+            .unwrap();
+
+        assert_eq!(metadata.len(), 3);
+
+        let weth_usdc = metadata[0].as_ref().unwrap();
+        assert_eq!(weth_usdc.factory_address, "5c69bee701ef814a2b6a3edd4b1652cb9cc5aa6f");
+        assert_eq!((weth_usdc.base_symbol.as_str(), weth_usdc.quote_symbol.as_str()), ("WETH", "USDC"));
+        assert_eq!((weth_usdc.base_decimals, weth_usdc.quote_decimals), (18, 6));
+
+        // MKR's bytes32 name and symbol come back as strings, decoded by the contract.
+        let mkr_weth = metadata[1].as_ref().unwrap();
+        assert_eq!(mkr_weth.base_address, "9f8f72aa9304c8b593d555f12ef6589cc3a579a2");
+        assert_eq!((mkr_weth.base_name.as_str(), mkr_weth.base_symbol.as_str()), ("Maker", "MKR"));
+        assert_eq!(mkr_weth.quote_symbol, "WETH");
+
+        // The address that isn't a pair fails on its own.
+        assert!(metadata[2].is_err());
+    }
+
+    #[test]
+    fn chain_data_is_fetched() {
+        let rpc = MockRpc::start(Cassette::load("classic"));
+        let runtime = Runtime::new().unwrap();
+
+        assert_eq!(runtime.block_on(fetch_chain_id(&rpc.url)).unwrap(), 1);
+        assert_eq!(runtime.block_on(fetch_latest_block_number(&rpc.url)).unwrap(), 17000015);
+        assert_eq!(
+            runtime.block_on(fetch_block_hash(&rpc.url, 17000001)).unwrap(),
+            "0xecc26bd52f602b27c9c8fcb7cabb1dd152a2fb21267d834078be999139053d67");
+    }
+}
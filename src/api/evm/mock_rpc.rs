@@ -0,0 +1,247 @@
+// An in-process JSON-RPC server that stands in for a provider in tests.
+// It serves a cassette from `fixtures/rpc`: the blocks, receipts, logs and
+// contract calls of a chain, in the JSON a node would return (each cassette's
+// description says where they come from).
+// Methods it doesn't record answer with "method not found", like a provider
+// that doesn't support them; so do the tracing methods, unless traces for
+// them are recorded.
+
+use actix_web::{ web, App, HttpServer, HttpResponse };
+use serde::Deserialize;
+use serde_json::{ json, Value };
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+
+#[derive(Debug, Deserialize)]
+pub struct Cassette {
+    pub chain_id: String,
+    pub block_number: String,
+    pub blocks: Vec<Value>, // with full transaction objects
+    pub receipts: Vec<Value>,
+    pub logs: Vec<Value>,
+    pub calls: Vec<RecordedCall>,
+    #[serde(default)]
+    pub max_logs: Option<usize>, // if set, refuse `eth_getLogs` requests matching more logs, like many providers
+    #[serde(default)]
+    pub traces: Vec<Value>, // `trace_transaction` traces, each with its `transactionHash`
+    #[serde(default)]
+    pub call_frames: Vec<RecordedCallFrame> // `debug_traceTransaction` results (from geth's `callTracer`)
+}
+
+// The top-level `callTracer` call frame of the given transaction.
+#[derive(Debug, Deserialize)]
+pub struct RecordedCallFrame {
+    pub tx_hash: String,
+    pub result: Value
+}
+
+// The result of an `eth_call` with the given `to` address and input data.
+#[derive(Debug, Deserialize)]
+pub struct RecordedCall {
+    pub to: String,
+    pub data: String,
+    pub result: String
+}
+
+impl Cassette {
+    // Load the cassette with the given name from `fixtures/rpc`.
+    pub fn load(name: &str) -> Self {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures")
+            .join("rpc")
+            .join(format!("{name}.json"));
+        let json = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("could not read {}: {e}", path.display()));
+
+        serde_json::from_str(&json)
+            .unwrap_or_else(|e| panic!("could not parse {}: {e}", path.display()))
+    }
+
+    // Answer a single JSON-RPC request, with either a result or an error message.
+    fn respond(&self, method: &str, params: &[Value]) -> Result<Value, (i64, String)> {
+        match method {
+            "eth_chainId" => Ok(json!(self.chain_id)),
+            "eth_blockNumber" => Ok(json!(self.block_number)),
+            "eth_getBlockByNumber" => {
+                let number = param_quantity(params, 0)?;
+                let block = self.blocks.iter().find(|b| quantity(&b["number"]) == Some(number));
+                Ok(block.map_or(Value::Null, |b| with_full_transactions(b, param_bool(params, 1))))
+            },
+            "eth_getBlockByHash" => {
+                let hash = param_str(params, 0)?;
+                let block = self.blocks.iter().find(|b| same_hex(&b["hash"], &hash));
+                Ok(block.map_or(Value::Null, |b| with_full_transactions(b, param_bool(params, 1))))
+            },
+            "eth_getTransactionByHash" => {
+                let hash = param_str(params, 0)?;
+                Ok(self.blocks.iter()
+                    .flat_map(|b| b["transactions"].as_array().cloned().unwrap_or_default())
+                    .find(|tx| same_hex(&tx["hash"], &hash))
+                    .unwrap_or(Value::Null))
+            },
+            "eth_getTransactionReceipt" => {
+                let hash = param_str(params, 0)?;
+                Ok(self.receipts.iter()
+                    .find(|r| same_hex(&r["transactionHash"], &hash))
+                    .cloned()
+                    .unwrap_or(Value::Null))
+            },
+            "eth_getLogs" => {
+                let filter = params.first().ok_or((-32602, "missing filter".to_string()))?;
+                let logs = self.logs.iter().filter(|log| matches_filter(log, filter)).cloned().collect::<Vec<Value>>();
+
+                match self.max_logs {
+                    Some(max_logs) if logs.len() > max_logs => Err((-32005, format!("query returned more than {max_logs} results"))),
+                    _ => Ok(Value::Array(logs))
+                }
+            },
+            "trace_transaction" if !self.traces.is_empty() => {
+                let hash = param_str(params, 0)?;
+                Ok(Value::Array(self.traces.iter()
+                    .filter(|trace| same_hex(&trace["transactionHash"], &hash))
+                    .cloned()
+                    .collect()))
+            },
+            "debug_traceTransaction" if !self.call_frames.is_empty() => {
+                let hash = param_str(params, 0)?;
+                self.call_frames.iter()
+                    .find(|frame| frame.tx_hash.eq_ignore_ascii_case(&hash))
+                    .map(|frame| frame.result.clone())
+                    .ok_or((-32000, format!("transaction {hash} not found")))
+            },
+            "eth_call" => {
+                let call = params.first().ok_or((-32602, "missing call".to_string()))?;
+                let data = call.get("data").or_else(|| call.get("input")).unwrap_or(&Value::Null);
+
+                self.calls.iter()
+                    .find(|c| same_hex(&call["to"], &c.to) && same_hex(data, &c.data))
+                    .map(|c| json!(c.result))
+                    .ok_or((3, "execution reverted".to_string()))
+            },
+            _ => Err((-32601, format!("the method {method} does not exist/is not available")))
+        }
+    }
+}
+
+// A mock provider, serving its cassette until the test process exits.
+pub struct MockRpc {
+    pub url: String
+}
+
+impl MockRpc {
+    // Start serving the given cassette on a free local port.
+    pub fn start(cassette: Cassette) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let cassette = web::Data::new(cassette);
+            let system = actix_web::rt::System::new();
+
+            system.block_on(async move {
+                let server = HttpServer::new(move || {
+                    App::new()
+                        .app_data(cassette.clone())
+                        .default_service(web::to(handle_request))
+                })
+                .workers(1)
+                .bind(("127.0.0.1", 0))
+                .expect("could not bind the mock provider");
+
+                sender.send(server.addrs()[0]).unwrap();
+                server.run().await
+            }).unwrap();
+        });
+
+        let address = receiver.recv().expect("mock provider did not start");
+        Self { url: format!("http://{address}") }
+    }
+}
+
+async fn handle_request(cassette: web::Data<Cassette>, body: web::Bytes) -> HttpResponse {
+    let request: Value = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(_) => return HttpResponse::BadRequest().finish()
+    };
+
+    let params = request["params"].as_array().cloned().unwrap_or_default();
+    let response = match cassette.respond(request["method"].as_str().unwrap_or(""), &params) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+        Err((code, message)) => json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "error": { "code": code, "message": message }
+        })
+    };
+
+    HttpResponse::Ok().json(response)
+}
+
+// Return the block, replacing its transactions with their hashes unless `full`.
+fn with_full_transactions(block: &Value, full: bool) -> Value {
+    let mut block = block.clone();
+
+    if !full {
+        let hashes = block["transactions"].as_array().cloned().unwrap_or_default()
+            .iter()
+            .map(|tx| tx["hash"].clone())
+            .collect();
+        block["transactions"] = Value::Array(hashes);
+    }
+
+    block
+}
+
+// Check whether the log matches an `eth_getLogs` filter's block range,
+// address(es) and topics (where `null` matches any topic).
+fn matches_filter(log: &Value, filter: &Value) -> bool {
+    let block = quantity(&log["blockNumber"]).unwrap_or(0);
+
+    if quantity(&filter["fromBlock"]).is_some_and(|from| block < from) ||
+        quantity(&filter["toBlock"]).is_some_and(|to| block > to) {
+        return false;
+    }
+
+    if !matches_any(&log["address"], &filter["address"]) {
+        return false;
+    }
+
+    let topics = filter["topics"].as_array().cloned().unwrap_or_default();
+
+    topics.iter().enumerate().all(|(i, topic)| matches_any(&log["topics"][i], topic))
+}
+
+// Check whether `value` equals the expected value, or any of the expected
+// values if it's an array; a missing or `null` expectation matches anything.
+fn matches_any(value: &Value, expected: &Value) -> bool {
+    match expected {
+        Value::Null => true,
+        Value::Array(options) => options.iter().any(|option| same_hex(value, option.as_str().unwrap_or(""))),
+        Value::String(option) => same_hex(value, option),
+        _ => false
+    }
+}
+
+fn same_hex(value: &Value, expected: &str) -> bool {
+    value.as_str().is_some_and(|value| value.eq_ignore_ascii_case(expected))
+}
+
+// Parse a hex quantity such as "0x1a"; block tags (e.g. "latest") aren't recorded.
+fn quantity(value: &Value) -> Option<u64> {
+    u64::from_str_radix(value.as_str()?.trim_start_matches("0x"), 16).ok()
+}
+
+fn param_quantity(params: &[Value], index: usize) -> Result<u64, (i64, String)> {
+    params.get(index).and_then(quantity).ok_or((-32602, format!("invalid quantity in param {index}")))
+}
+
+fn param_str(params: &[Value], index: usize) -> Result<String, (i64, String)> {
+    params.get(index)
+        .and_then(|param| param.as_str())
+        .map(|param| param.to_string())
+        .ok_or((-32602, format!("invalid string in param {index}")))
+}
+
+fn param_bool(params: &[Value], index: usize) -> bool {
+    params.get(index).and_then(|param| param.as_bool()).unwrap_or(false)
+}
@@ -2,6 +2,21 @@ pub mod metadata;
 pub mod scanner;
 pub mod swap;
 pub mod sandwich;
+pub mod detector;
+pub mod multipool;
+pub mod block_scanner;
+pub mod liquidity;
+pub mod transfers;
+pub mod arbitrage;
+pub mod bribe;
+pub mod rollup;
+pub mod timestamps;
+pub mod probe;
+pub mod discovery;
+#[cfg(test)]
+mod evaluation;
+#[cfg(test)]
+mod mock_rpc;
 
-pub use metadata::{ fetch_pair_metadata, fetch_latest_block_number };
+pub use metadata::{ fetch_pair_metadata, fetch_latest_block_number, fetch_block_hash, fetch_chain_id };
 pub use scanner::Params;
\ No newline at end of file
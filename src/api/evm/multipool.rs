@@ -0,0 +1,260 @@
+use ethers::types::{ Address, TxHash };
+use super::swap::{ Swap, Direction };
+use std::collections::{ BTreeMap, BTreeSet, HashMap };
+
+// All the swaps made by a single transaction in a block,
+// across every supported pool, in log order.
+#[derive(Debug, Clone)]
+pub struct TxSwaps<'a> {
+    pub tx_hash: TxHash,
+    pub tx_index: i32,
+    pub from: Address,
+    pub swaps: Vec<&'a Swap<'a>>
+}
+
+impl<'a> TxSwaps<'a> {
+    // The pools this transaction traded on.
+    pub fn pools(&self) -> BTreeSet<Address> {
+        self.swaps.iter().map(|s| s.swap.pool()).collect()
+    }
+
+    // The direction this transaction traded in on the given pool, if any.
+    pub fn direction_on(&self, pool: Address) -> Option<Direction> {
+        self.swaps.iter()
+            .find(|s| s.swap.pool() == pool)
+            .map(|s| s.swap.direction())
+    }
+
+    // The net change in each token (by `token_id`) flowing to the trader
+    // across all of this transaction's swaps. Intermediate tokens
+    // on a multi-hop route net out to (roughly) zero.
+    fn net_flows(&self) -> HashMap<i32, f64> {
+        let mut flows: HashMap<i32, (f64, f64)> = HashMap::new(); // token_id -> (in, out)
+
+        for swap in self.swaps.iter() {
+            let base = flows.entry(swap.base.token_id).or_default();
+            base.0 += swap.in0();
+            base.1 += swap.out0();
+
+            let quote = flows.entry(swap.quote.token_id).or_default();
+            quote.0 += swap.in1();
+            quote.1 += swap.out1();
+        }
+
+        flows.into_iter()
+            .filter(|(_, (paid, received))| (received - paid).abs() > 1e-9 * paid.max(*received))
+            .map(|(token_id, (paid, received))| (token_id, received - paid))
+            .collect()
+    }
+
+    // The tokens this transaction ended up buying (positive net flow).
+    fn bought(&self) -> BTreeSet<i32> {
+        self.net_flows().into_iter().filter(|(_, net)| *net > 0.0).map(|(t, _)| t).collect()
+    }
+
+    // The tokens this transaction ended up selling (negative net flow).
+    fn sold(&self) -> BTreeSet<i32> {
+        self.net_flows().into_iter().filter(|(_, net)| *net < 0.0).map(|(t, _)| t).collect()
+    }
+}
+
+// A sandwich whose legs and victims span more than one pool.
+pub struct MultipoolSandwich<'a> {
+    pub attacker: Address,
+    pub frontrun: TxSwaps<'a>,
+    pub victims: Vec<TxSwaps<'a>>,
+    pub backrun: TxSwaps<'a>
+}
+
+impl<'a> MultipoolSandwich<'a> {
+    // Every pool touched by the frontrun, the backrun or a victim.
+    pub fn pools(&self) -> BTreeSet<Address> {
+        let mut pools = self.frontrun.pools();
+        pools.extend(self.backrun.pools());
+
+        for victim in self.victims.iter() {
+            pools.extend(victim.pools());
+        }
+
+        pools
+    }
+}
+
+// Group a block's swaps (across all pools) by transaction, in block order.
+pub fn group_by_transaction<'a>(
+    swaps: &'a [Swap<'a>],
+    senders: &HashMap<TxHash, Address>
+) -> Vec<TxSwaps<'a>> {
+    let mut by_index: BTreeMap<(i32, TxHash), Vec<&'a Swap<'a>>> = BTreeMap::new();
+
+    for swap in swaps.iter() {
+        by_index.entry((swap.swap.tx_index(), swap.swap.tx_hash)).or_default().push(swap);
+    }
+
+    by_index.into_iter()
+        .map(|((tx_index, tx_hash), mut swaps)| {
+            swaps.sort_by_key(|s| s.swap.log_index());
+
+            TxSwaps {
+                tx_hash,
+                tx_index,
+                from: senders.get(&tx_hash).copied().unwrap_or_default(),
+                swaps
+            }
+        })
+        .collect()
+}
+
+// Find sandwiches in a block whose frontrun, backrun and victims
+// together touch more than one pool. The frontrun and backrun are
+// correlated by a shared transaction sender (or by the frontrun's
+// recipient contract sending the backrun), and the backrun must sell
+// a token that the frontrun bought. Each frontrun is paired with the
+// earliest qualifying backrun, and no transaction is used twice as a leg.
+pub fn find_multipool_sandwiches<'a>(
+    transactions: &[TxSwaps<'a>]
+) -> Vec<MultipoolSandwich<'a>> {
+    let mut sandwiches = vec![];
+    let mut is_leg = vec![false; transactions.len()];
+
+    for i in 0..transactions.len() {
+        if is_leg[i] {
+            continue;
+        }
+
+        for j in i+2..transactions.len() {
+            if is_leg[j] {
+                continue;
+            }
+
+            let frontrun = &transactions[i];
+            let backrun = &transactions[j];
+
+            if !is_same_attacker(frontrun, backrun) || !is_reversal(frontrun, backrun) {
+                continue;
+            }
+
+            let victims = (i+1..j)
+                .filter(|k| !is_leg[*k])
+                .map(|k| &transactions[k])
+                .filter(|victim| is_victim(frontrun, victim))
+                .cloned()
+                .collect::<Vec<TxSwaps>>();
+
+            if victims.is_empty() {
+                continue;
+            }
+
+            let sandwich = MultipoolSandwich {
+                attacker: frontrun.from,
+                frontrun: frontrun.clone(),
+                victims,
+                backrun: backrun.clone()
+            };
+
+            // Single-pool sandwiches are left to the per-pair scanner.
+            if sandwich.pools().len() < 2 {
+                continue;
+            }
+
+            is_leg[i] = true;
+            is_leg[j] = true;
+            sandwiches.push(sandwich);
+            break;
+        }
+    }
+
+    sandwiches
+}
+
+// The frontrun and backrun come from the same account, or the contract
+// that received the frontrun's output also sent the backrun.
+fn is_same_attacker(frontrun: &TxSwaps, backrun: &TxSwaps) -> bool {
+    if frontrun.from != Address::zero() && frontrun.from == backrun.from {
+        return true;
+    }
+
+    frontrun.swaps.iter().any(|f| backrun.swaps.iter().any(|b| f.swap.recipient() == b.swap.sender()))
+}
+
+// The backrun sells a token the frontrun bought, for a token the frontrun sold.
+fn is_reversal(frontrun: &TxSwaps, backrun: &TxSwaps) -> bool {
+    !frontrun.bought().is_disjoint(&backrun.sold()) &&
+        !frontrun.sold().is_disjoint(&backrun.bought())
+}
+
+// A victim is a different account that trades on one of the frontrun's
+// pools in the same direction as the frontrun did on that pool.
+fn is_victim(frontrun: &TxSwaps, candidate: &TxSwaps) -> bool {
+    if candidate.from == frontrun.from {
+        return false;
+    }
+
+    candidate.swaps.iter().any(|swap| {
+        let direction = swap.swap.direction();
+        direction != Direction::Unknown && frontrun.direction_on(swap.swap.pool()) == Some(direction)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::swap::{ SwapCore, to_wrapped };
+    use crate::api::models::Token;
+
+    const ATTACKER: Address = Address::repeat_byte(0xaa);
+    const VICTIM: Address = Address::repeat_byte(0xbb);
+
+    // A frontrun and a victim buying quote on the first pool,
+    // and a backrun selling it on the given pool.
+    fn find_sandwiches(backrun_pool: Address) -> (usize, BTreeSet<Address>) {
+        let (base, quote) = (Token::mock(1, 18), Token::mock(2, 18));
+        let pool = Address::repeat_byte(1);
+        let swaps = [
+            SwapCore::mock(0, pool, ATTACKER, ATTACKER, (10, 0, 0, 10)),
+            SwapCore::mock(1, pool, VICTIM, VICTIM, (5, 0, 0, 4)),
+            SwapCore::mock(2, backrun_pool, ATTACKER, ATTACKER, (0, 10, 11, 0))
+        ].into_iter().map(|core| to_wrapped(core, 18, &base, &quote)).collect::<Vec<Swap>>();
+
+        let senders = swaps.iter()
+            .map(|swap| (swap.swap.tx_hash, swap.swap.sender()))
+            .collect::<HashMap<TxHash, Address>>();
+
+        let transactions = group_by_transaction(&swaps, &senders);
+        let sandwiches = find_multipool_sandwiches(&transactions);
+
+        (sandwiches.len(), sandwiches.first().map(|sandwich| sandwich.pools()).unwrap_or_default())
+    }
+
+    #[test]
+    fn sandwiches_across_pools_are_found() {
+        let (found, pools) = find_sandwiches(Address::repeat_byte(2));
+
+        assert_eq!(found, 1);
+        assert_eq!(pools, BTreeSet::from([Address::repeat_byte(1), Address::repeat_byte(2)]));
+    }
+
+    #[test]
+    fn single_pool_sandwiches_are_left_out() {
+        assert_eq!(find_sandwiches(Address::repeat_byte(1)).0, 0);
+    }
+
+    #[test]
+    fn transactions_are_grouped_in_block_order() {
+        let (base, quote) = (Token::mock(1, 18), Token::mock(2, 18));
+        let pool = Address::repeat_byte(1);
+        let swaps = [
+            SwapCore::mock(3, pool, VICTIM, VICTIM, (5, 0, 0, 4)),
+            SwapCore::mock(1, pool, ATTACKER, ATTACKER, (10, 0, 0, 10)),
+            SwapCore::mock(1, Address::repeat_byte(2), ATTACKER, ATTACKER, (0, 10, 11, 0))
+        ].into_iter().map(|core| to_wrapped(core, 18, &base, &quote)).collect::<Vec<Swap>>();
+
+        let senders = HashMap::from([(TxHash::from_low_u64_be(2), ATTACKER)]);
+        let transactions = group_by_transaction(&swaps, &senders);
+
+        assert_eq!(transactions.iter().map(|tx| tx.tx_index).collect::<Vec<i32>>(), vec![1, 3]);
+        assert_eq!(transactions[0].from, ATTACKER);
+        assert_eq!(transactions[0].swaps.len(), 2);
+        assert_eq!(transactions[1].from, Address::zero()); // its sender is unknown
+    }
+}
@@ -0,0 +1,65 @@
+use ethers::prelude::{ Provider, Http };
+use ethers::types::Address;
+use super::metadata::call_all;
+
+type RpcError = Box<dyn std::error::Error + Send + Sync>;
+
+// The swap interfaces a pair can be probed for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolInterface {
+    V2,
+    V3
+}
+
+impl PoolInterface {
+    // The kind stored in the exchange registry.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PoolInterface::V2 => "v2",
+            PoolInterface::V3 => "v3"
+        }
+    }
+}
+
+// Probes the given pair for the V2 interface (`getReserves()`, which returns
+// two reserves and a timestamp) and then the V3 interface (`slot0()`, which
+// returns seven words, and `fee()`). Returns `None` if it has neither,
+// e.g. because it isn't a pair, or it's a pool with some other interface.
+pub async fn probe_pool_interface(
+    provider_url: &str,
+    pair_address: &str
+) -> Result<Option<PoolInterface>, RpcError> {
+    let provider = Provider::<Http>::try_from(provider_url)?;
+    let pair = pair_address.parse::<Address>()?;
+
+    let outputs = call_all(&provider, &[(pair, "getReserves()"), (pair, "slot0()"), (pair, "fee()")]).await?;
+    let length = |i: usize| outputs[i].as_ref().map_or(0, |output| output.as_ref().len());
+
+    if length(0) == 96 {
+        Ok(Some(PoolInterface::V2))
+    } else if length(1) == 224 && length(2) == 32 {
+        Ok(Some(PoolInterface::V3))
+    } else {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::mock_rpc::{ Cassette, MockRpc };
+    use tokio::runtime::Runtime;
+
+    #[test]
+    fn pairs_are_probed_for_their_interface() {
+        let rpc = MockRpc::start(Cassette::load("classic"));
+        let runtime = Runtime::new().unwrap();
+
+        assert_eq!(
+            runtime.block_on(probe_pool_interface(&rpc.url, "0x11982c91f8f0438475b20e67ff9dbe5aa0d25db2")).unwrap(),
+            Some(PoolInterface::V2));
+        assert_eq!(
+            runtime.block_on(probe_pool_interface(&rpc.url, "0x0000000000000000000000000000000000000001")).unwrap(),
+            None);
+    }
+}
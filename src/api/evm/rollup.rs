@@ -0,0 +1,155 @@
+use ethers::prelude::{ Provider, Http, JsonRpcClient };
+use ethers::types::{ U256, TxHash };
+use serde::Deserialize;
+use crate::state::ChainKind;
+
+type RpcError = Box<dyn std::error::Error + Send + Sync>;
+
+// The fee-related fields of a transaction receipt, including
+// the rollup-specific ones that ethers' TransactionReceipt drops.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RollupReceipt {
+    #[serde(rename = "gasUsed")]
+    gas_used: Option<U256>,
+    #[serde(rename = "effectiveGasPrice")]
+    effective_gas_price: Option<U256>,
+    #[serde(rename = "l1Fee")]
+    l1_fee: Option<U256>, // OP Stack
+    #[serde(rename = "gasUsedForL1")]
+    gas_used_for_l1: Option<U256> // Arbitrum Nitro
+}
+
+impl RollupReceipt {
+    // The L1 data fee charged on top of `effectiveGasPrice * gasUsed`.
+    // Arbitrum folds its L1 data fee into `gasUsed`, so there's none there.
+    pub fn extra_fee(&self, chain_kind: ChainKind) -> U256 {
+        match chain_kind {
+            ChainKind::Optimism => self.l1_fee.unwrap_or_default(),
+            ChainKind::Arbitrum | ChainKind::L1 => U256::zero()
+        }
+    }
+
+    // The part of the total fee that paid for posting the transaction to L1.
+    pub fn l1_fee(&self, chain_kind: ChainKind) -> U256 {
+        match chain_kind {
+            ChainKind::Optimism => self.l1_fee.unwrap_or_default(),
+            ChainKind::Arbitrum => self.gas_used_for_l1.unwrap_or_default()
+                .saturating_mul(self.effective_gas_price.unwrap_or_default()),
+            ChainKind::L1 => U256::zero()
+        }
+    }
+
+    // Everything the sender paid for the transaction, in wei.
+    pub fn total_fee(&self, chain_kind: ChainKind) -> U256 {
+        self.gas_used.unwrap_or_default()
+            .saturating_mul(self.effective_gas_price.unwrap_or_default())
+            .saturating_add(self.extra_fee(chain_kind))
+    }
+}
+
+// Fetch the raw receipt for the given transaction hash,
+// keeping the rollup-specific fee fields.
+pub async fn fetch_rollup_receipt(
+    provider: &Provider<Http>,
+    tx_hash: TxHash
+) -> Result<RollupReceipt, RpcError> {
+    let receipt: Option<RollupReceipt> = provider.as_ref()
+        .request("eth_getTransactionReceipt", [tx_hash]).await?;
+
+    receipt.ok_or_else(|| "no receipt was returned from the provider".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::mock_rpc::{ Cassette, MockRpc };
+    use super::super::swap::{ SwapCore, to_wrapped };
+    use crate::api::models::Token;
+    use ethers::types::{ Address, TransactionReceipt };
+    use tokio::runtime::Runtime;
+
+    // Fetch the first receipt of the given cassette from the mock provider. The
+    // `optimism` and `arbitrum` cassettes are hand-written, not recorded (see
+    // their descriptions), so the fees below are worked out from their fields.
+    fn fetch_receipt(cassette_name: &str) -> RollupReceipt {
+        let cassette = Cassette::load(cassette_name);
+        let tx_hash = cassette.receipts[0]["transactionHash"].as_str().unwrap().parse::<TxHash>().unwrap();
+        let rpc = MockRpc::start(cassette);
+        let provider = Provider::<Http>::try_from(rpc.url.as_str()).unwrap();
+
+        Runtime::new().unwrap().block_on(fetch_rollup_receipt(&provider, tx_hash)).unwrap()
+    }
+
+    // The first receipt of the given cassette, as ethers parses it.
+    fn ethers_receipt(cassette_name: &str) -> TransactionReceipt {
+        serde_json::from_value(Cassette::load(cassette_name).receipts[0].clone()).unwrap()
+    }
+
+    #[test]
+    fn optimism_adds_the_l1_fee() {
+        let receipt = fetch_receipt("optimism");
+
+        assert_eq!(receipt.l1_fee(ChainKind::Optimism), U256::from(92_257_920_000_000u64));
+        assert_eq!(receipt.extra_fee(ChainKind::Optimism), U256::from(92_257_920_000_000u64));
+        assert_eq!(receipt.total_fee(ChainKind::Optimism), U256::from(92_430_452_862_660u64));
+    }
+
+    #[test]
+    fn arbitrum_l1_fee_is_already_in_gas_used() {
+        let receipt = fetch_receipt("arbitrum");
+
+        assert_eq!(receipt.l1_fee(ChainKind::Arbitrum), U256::from(18_140_900_000_000u64));
+        assert_eq!(receipt.extra_fee(ChainKind::Arbitrum), U256::zero());
+        assert_eq!(receipt.total_fee(ChainKind::Arbitrum), U256::from(44_054_500_000_000u64));
+    }
+
+    #[test]
+    fn l1_has_no_data_fee() {
+        // 21,000 gas at 22 gwei.
+        let receipt = fetch_receipt("classic");
+
+        assert_eq!(receipt.l1_fee(ChainKind::L1), U256::zero());
+        assert_eq!(receipt.total_fee(ChainKind::L1), U256::from(462_000_000_000_000u64));
+    }
+
+    #[test]
+    fn unknown_transactions_have_no_receipt() {
+        let rpc = MockRpc::start(Cassette::load("optimism"));
+        let provider = Provider::<Http>::try_from(rpc.url.as_str()).unwrap();
+
+        assert!(Runtime::new().unwrap().block_on(fetch_rollup_receipt(&provider, TxHash::zero())).is_err());
+    }
+
+    #[test]
+    fn rollup_fields_are_ignored_on_l1() {
+        let receipt = fetch_receipt("optimism");
+
+        assert_eq!(receipt.extra_fee(ChainKind::L1), U256::zero());
+        assert_eq!(receipt.total_fee(ChainKind::L1), U256::from(172_532_862_660u64));
+    }
+
+    #[test]
+    fn optimism_gas_fees_add_up_with_the_l1_fee() {
+        let token = Token::mock(1, 18);
+        let core = SwapCore::mock(3, Address::zero(), Address::zero(), Address::zero(), (10, 0, 0, 10));
+        let mut swap = to_wrapped(core, 18, &token, &token);
+
+        swap.add_receipt_meta(ethers_receipt("optimism"));
+        swap.add_rollup_meta(ChainKind::Optimism, &fetch_receipt("optimism"));
+
+        // The burned fee and the tip don't include the L1 fee, so together
+        // with it they make up the total, whatever the base fee.
+        let base_fee = Some(U256::from(1_000_000u64));
+        let parts = swap.gas_burned(base_fee).unwrap() + swap.gas_tip(base_fee).unwrap() + swap.gas_l1_fee().unwrap();
+
+        assert_eq!(swap.gas_l1_fee(), Some(0.00009225792));
+        assert!((swap.gas() - parts).abs() < 1e-15);
+    }
+
+    #[test]
+    fn rollup_receipts_are_valid_ethers_receipts() {
+        for cassette_name in ["optimism", "arbitrum"] {
+            assert!(ethers_receipt(cassette_name).effective_gas_price.is_some());
+        }
+    }
+}
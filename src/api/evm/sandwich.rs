@@ -1,6 +1,12 @@
 use ethers::prelude::{ Provider, Middleware, Http };
+use ethers::types::{ Address, I256 };
 use super::swap::Swap;
+use super::rollup::fetch_rollup_receipt;
+use super::transfers::{ TokenTransfer, decode_receipt_transfers, net_balance_changes };
+use super::detector::{ SandwichDetector, is_victim };
+use crate::state::ChainKind;
 use std::fmt;
+use std::collections::{ HashMap, HashSet };
 use tokio;
 
 // This helper macro spawns a new tokio task to fetch
@@ -52,11 +58,47 @@ pub struct Sandwich<'a> {
 }
 
 impl<'a> Sandwich<'a> {
+    // Lunchmeat swaps that trade in the frontrun's direction are victims;
+    // the rest are bystanders that merely landed between the two legs.
+    pub fn is_victim(&self, lunchmeat_index: usize) -> bool {
+        is_victim(&self.frontrun, &self.lunchmeat[lunchmeat_index])
+    }
+
+    // The contract (or account) that received the frontrun's output,
+    // which is usually the same one that pays for the backrun.
+    pub fn attacker(&self) -> Address {
+        let recipient = self.frontrun.swap.recipient();
+
+        if !recipient.is_zero() {
+            return recipient;
+        }
+
+        self.frontrun.transaction.as_ref().map(|tx| tx.from).unwrap_or_default()
+    }
+
+    // The attacker's net balance change in every token it moved across the
+    // frontrun and the backrun, taken from the Transfer (and wrapped native
+    // token Deposit/Withdrawal) logs in their receipts. Unlike the pair
+    // amounts, this includes tokens routed through helper contracts.
+    // Requires `add_tx_meta` to have been called.
+    pub fn token_profits(&self) -> HashMap<Address, I256> {
+        let transfers = [&self.frontrun, &self.backrun].iter()
+            .filter_map(|swap| swap.receipt.as_ref())
+            .flat_map(decode_receipt_transfers)
+            .collect::<Vec<TokenTransfer>>();
+
+        net_balance_changes(&transfers, self.attacker())
+            .into_iter()
+            .filter(|(_, amount)| !amount.is_zero())
+            .collect()
+    }
+
     // Add transaction metadata (e.g. gas information)
     // to each swap in this sandwich.
     pub async fn add_tx_meta(
         &mut self,
-        provider_url: &str
+        provider_url: &str,
+        chain_kind: ChainKind
     ) -> Result<(), SandwichError> {
         // Create vecs to hold all the transaction and receipt handles.
         let mut tx_handles = Vec::with_capacity(self.lunchmeat.len() + 2);
@@ -125,80 +167,68 @@ impl<'a> Sandwich<'a> {
             }
         }
 
+        // On rollups, also read the L1 data fees from the raw receipts.
+        if chain_kind.is_rollup() {
+            let provider = Provider::<Http>::try_from(provider_url)
+                .map_err(|_| SandwichError::ParseError)?;
+
+            let swaps = std::iter::once(&mut self.frontrun)
+                .chain(self.lunchmeat.iter_mut())
+                .chain(std::iter::once(&mut self.backrun));
+
+            for swap in swaps {
+                let receipt = fetch_rollup_receipt(&provider, swap.swap.tx_hash).await
+                    .map_err(|_| SandwichError::ProviderError)?;
+                swap.add_rollup_meta(chain_kind, &receipt);
+            }
+        }
+
         // Let the calling function know that everything worked.
         Ok(())
     }
 }
 
-// Pull sandwich data out from the given bundle of swaps.
+// Pull sandwich data out from the given bundle of swaps,
+// using the given detector to match frontruns and backruns.
 pub async fn parse_sandwiches<'a>(
-    bundle: &'a Vec<Swap<'a>>,
-    provider_url: &str
+    bundle: &[Swap<'a>],
+    detector: &dyn SandwichDetector,
+    provider_url: &str,
+    chain_kind: ChainKind
 ) -> Result<Vec<Sandwich<'a>>, SandwichError> {
-    let mut sandwiches = vec![]; // we don't know how big this vec will be
-    let num_swaps = bundle.len();
-
-    let mut i = 0;
-
-    while i < num_swaps - 2 { // stop looping if not enough swaps are left to create a sandwich
-        let mut j = i + 2; // a swap starting at index `i` needs at least two more swaps
-        while j < num_swaps {
-            let frontrun = &bundle[i];
-            let backrun = &bundle[j];
-
-            if is_match(frontrun, backrun) { // if the swaps match, create the sandwich
-                let mut lunchmeat = vec![];
+    let mut sandwiches = match_sandwiches(bundle, detector);
 
-                for k in i+1..j {
-                    lunchmeat.push(bundle[k].clone());
-                }
-
-                let mut sandwich = Sandwich {
-                    frontrun: frontrun.clone(),
-                    lunchmeat,
-                    backrun: backrun.clone()
-                };
-
-                sandwich.add_tx_meta(provider_url).await?;
-
-                sandwiches.push(sandwich);
-
-                i = j + 1;
-                j = i + 2;
-            } else { // update the indices
-                j += 1;
-                i = if j >= num_swaps { i + 1 } else { i };
-            }
-        }
+    for sandwich in sandwiches.iter_mut() {
+        sandwich.add_tx_meta(provider_url, chain_kind).await?;
     }
 
     Ok(sandwiches)
 }
 
-// Given two swaps, determine if they match as a frontrun and backrun pair.
-fn is_match(a: &Swap, b: &Swap) -> bool {
-    if a.base.token_id != b.base.token_id {
-        return false;
-    }
-
-    if a.quote.token_id != b.quote.token_id {
-        return false;
-    }
-
-    let tol = 1.005;
-
-    let base_ratio = a.swap.in0(a.base.decimals as u8) / b.swap.out0(b.base.decimals as u8);
-    let quote_ratio = a.swap.in1(a.quote.decimals as u8) / b.swap.out1(b.quote.decimals as u8);
-
-    if 1.0/tol < base_ratio && base_ratio < tol {
-        return true;
-    }
-
-    if 1.0/tol < quote_ratio && quote_ratio < tol {
-        return true;
-    }
-
-    false
+// Group the given bundle of swaps into sandwiches, using the given detector
+// to match frontruns and backruns, without fetching any transaction data.
+pub fn match_sandwiches<'a>(
+    bundle: &[Swap<'a>],
+    detector: &dyn SandwichDetector
+) -> Vec<Sandwich<'a>> {
+    let matches = detector.find_matches(bundle);
+
+    // Swaps that are legs of one sandwich can't be lunchmeat in another
+    // (e.g. the inner legs of a nested sandwich are not the outer one's victims).
+    let legs = matches.iter()
+        .flat_map(|(i, j)| [*i, *j])
+        .collect::<HashSet<usize>>();
+
+    matches.into_iter()
+        .map(|(i, j)| Sandwich {
+            frontrun: bundle[i].clone(),
+            lunchmeat: (i+1..j)
+                .filter(|k| !legs.contains(k))
+                .map(|k| bundle[k].clone())
+                .collect(),
+            backrun: bundle[j].clone()
+        })
+        .collect()
 }
 
 pub enum SandwichError {
@@ -239,4 +269,102 @@ impl From<tokio::task::JoinError> for SandwichError {
     fn from(_: tokio::task::JoinError) -> Self {
         Self::JoinError
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::detector::{ AmountRatioDetector, DEFAULT_TOLERANCE };
+    use super::super::mock_rpc::{ Cassette, MockRpc };
+    use super::super::swap::{ RawSwapV2, SwapCore, to_wrapped };
+    use crate::api::models::Token;
+    use ethers::abi::AbiParser;
+    use ethers::prelude::{ Contract, LogMeta };
+    use ethers::types::U256;
+    use tokio::runtime::Runtime;
+
+    const PAIR_ADDRESS: &str = "0x11982c91f8f0438475b20e67ff9dbe5aa0d25db2";
+    const ATTACKER: &str = "0xcab9d2ad97b9b22f4c3fc0dfa0e125c6c7d52eab";
+
+    fn token(token_id: i32, symbol: &str, decimals: i16, token_address: &str) -> Token {
+        Token {
+            token_id,
+            token_name: symbol.to_string(),
+            token_symbol: symbol.to_string(),
+            decimals,
+            blockchain_str_id: "ethereum".to_string(),
+            token_address: token_address.to_string()
+        }
+    }
+
+    #[test]
+    fn sandwiches_get_their_transactions() {
+        let rpc = MockRpc::start(Cassette::load("classic"));
+        let runtime = Runtime::new().unwrap();
+        let base = token(1, "WETH", 18, "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let quote = token(2, "USDC", 6, "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+
+        // Load the block's swaps the way the scanner does.
+        let provider = Provider::<Http>::try_from(rpc.url.as_str()).unwrap();
+        let contract = Contract::new(
+            PAIR_ADDRESS.parse::<Address>().unwrap(), AbiParser::default().parse_str("").unwrap(), provider);
+        let raw_swaps: Vec<(RawSwapV2, LogMeta)> = runtime.block_on(contract.event()
+            .from_block::<u64>(17000001).to_block::<u64>(17000001).query_with_meta()).unwrap();
+
+        let bundle = raw_swaps.into_iter()
+            .map(|swap| to_wrapped(SwapCore::from(swap), 18, &base, &quote))
+            .collect::<Vec<Swap>>();
+
+        let detector = AmountRatioDetector { tolerance: DEFAULT_TOLERANCE };
+        let sandwiches = runtime.block_on(parse_sandwiches(&bundle, &detector, &rpc.url, ChainKind::L1)).unwrap();
+
+        assert_eq!(sandwiches.len(), 1);
+        assert_eq!(sandwiches[0].lunchmeat.len(), 1);
+        assert_eq!(format!("{:?}", sandwiches[0].attacker()), ATTACKER);
+
+        for swap in [&sandwiches[0].frontrun, &sandwiches[0].lunchmeat[0], &sandwiches[0].backrun] {
+            assert_eq!(swap.transaction.as_ref().map(|tx| tx.hash), Some(swap.swap.tx_hash));
+            assert_eq!(swap.gas_used(), Some(U256::from(150000)));
+        }
+    }
+
+    #[test]
+    fn missing_transactions_are_an_error() {
+        let rpc = MockRpc::start(Cassette::load("classic"));
+        let base = token(1, "WETH", 18, "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let quote = token(2, "USDC", 6, "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let swap = |tx_hash: &str, amounts: (u64, u64, u64, u64)| {
+            let stored = crate::api::models::Swap {
+                swap_id: 0,
+                pair_id: 0,
+                block_number: 17000009,
+                tx_hash: tx_hash.to_string(),
+                tx_index: 0,
+                log_index: 0,
+                sender: ATTACKER.to_string(),
+                recipient: ATTACKER.to_string(),
+                amount0_in: amounts.0.to_string(),
+                amount1_in: amounts.1.to_string(),
+                amount0_out: amounts.2.to_string(),
+                amount1_out: amounts.3.to_string(),
+                sqrt_price_x96: None,
+                liquidity: None,
+                tick: None
+            };
+            to_wrapped(SwapCore::from_stored(&stored, Address::zero()).unwrap(), 18, &base, &quote)
+        };
+
+        // A sandwich whose transactions the provider has never seen.
+        let bundle = vec![
+            swap(&format!("0x{:064x}", 1), (0, 1000, 10, 0)),
+            swap(&format!("0x{:064x}", 2), (0, 5000, 50, 0)),
+            swap(&format!("0x{:064x}", 3), (10, 0, 0, 1001))
+        ];
+
+        let detector = AmountRatioDetector { tolerance: DEFAULT_TOLERANCE };
+        let result = Runtime::new().unwrap()
+            .block_on(parse_sandwiches(&bundle, &detector, &rpc.url, ChainKind::L1));
+
+        assert!(matches!(result, Err(SandwichError::NoTransaction)));
+    }
+}
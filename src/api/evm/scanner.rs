@@ -1,14 +1,21 @@
-use ethers::prelude::{ Provider, Http, Contract, LogMeta };
+use ethers::prelude::{ Provider, Http, Middleware, Contract, LogMeta };
 use ethers::abi::AbiParser;
-use ethers::types::{ Address };
+use ethers::types::{ Address, U256 };
 use super::super::{ db, models };
 use super::swap::{ RawSwapV2, RawSwapV3, SwapCore, Swap, to_wrapped };
-use super::sandwich::parse_sandwiches;
-use crate::state::Exchange;
+use super::sandwich::{ Sandwich, parse_sandwiches };
+use super::detector::{ DetectorParams, SandwichDetector, compare_detectors };
+use super::transfers::i256_to_f64;
+use super::bribe::{ Bribe, find_sandwich_bribe };
+use super::liquidity::{ RawMintV3, RawBurnV3, RawCollectV3, LiquidityEvent, JitAttack, find_jit_attacks };
+use crate::state::{ Exchange, ChainKind };
 use tokio::runtime::Runtime;
+use diesel::connection::Connection;
 use std::thread;
 use std::error::Error;
-use std::collections::HashMap;
+use std::collections::{ HashMap, HashSet };
+use std::sync::{ Arc, Mutex };
+use std::fmt;
 
 // This helper macro tries the given expression
 // or logs the given range (by id) as failed.
@@ -48,19 +55,29 @@ pub struct Params {
     pub blocks_per_chunk: u64,
     pub max_blocks_per_chunk: u64,
     pub target_swaps_per_chunk: u64,
-    pub max_blocks_per_request: u64
+    pub max_blocks_per_request: u64,
+    pub confirmations: u64 // blocks behind the latest block that are safe from reorgs
+}
+
+// This struct holds the blockchain a scan job runs against.
+#[derive(Debug, Clone)]
+pub struct ChainParams {
+    pub provider_url: String,
+    pub chain_id: u64, // the chain id the provider must report
+    pub chain_kind: ChainKind,
+    pub native_decimals: u8
 }
 
 // Start scanning for sandwiches on the given pair
 // over the given range of blocks, inside a new thread.
 pub fn start_scan_job(
     db_connection: db::DbConnection,
-    provider_url: String,
+    chain: ChainParams,
     pair: models::Pair,
     exchange: Exchange,
-    native_decimals: u8,
     range: models::Range,
-    params: Params
+    params: Params,
+    detector_params: DetectorParams
 ) -> bool {
     match thread::Builder::new().spawn(move || {
         // Now that we're in a brand-new thread, create a new tokio runtime
@@ -71,33 +88,127 @@ pub fn start_scan_job(
         try_or_log_error!(runtime.block_on(async { 
             run_scan_loop(
                 &db_connection, 
-                provider_url, 
+                chain,
                 pair, 
                 exchange, 
-                native_decimals,
                 range, 
-                params).await }), db_connection, range_id);
+                params,
+                detector_params).await }), db_connection, range_id);
     }) {
         Ok(_) => true, // the thread was successfully created
         Err(_) => false // failed to create the thread
     }
 }
 
+// Rescan the given block on every pair whose completed ranges contain it,
+// inside a new thread, after the block was reorged: everything previously
+// found in it on those pairs is deleted, and then its header is replaced.
+// Pairs on exchanges that are no longer supported are skipped. The block is
+// removed from `rescanning_blocks` once the rescan is over, either way.
+pub fn start_rescan_job(
+    db_connection: db::DbConnection,
+    chain: ChainParams,
+    exchanges: HashMap<String, Exchange>,
+    block: models::Block,
+    detector_params: DetectorParams,
+    rescanning_blocks: Arc<Mutex<HashSet<u64>>>
+) -> bool {
+    match thread::Builder::new().spawn(move || {
+        match Runtime::new() {
+            Ok(runtime) => if let Err(e) = runtime.block_on(async {
+                rescan_block(
+                    &db_connection,
+                    chain,
+                    exchanges,
+                    &block,
+                    detector_params).await }) {
+
+                println!("Could not rescan block {}: {e}", block.block_number);
+            },
+            Err(e) => println!("Could not rescan block {}: {e}", block.block_number)
+        }
+
+        rescanning_blocks.lock().unwrap().remove(&(block.block_number as u64));
+    }) {
+        Ok(_) => true, // the thread was successfully created
+        Err(_) => false // failed to create the thread
+    }
+}
+
+async fn rescan_block(
+    db_connection: &db::DbConnection,
+    chain: ChainParams,
+    exchanges: HashMap<String, Exchange>,
+    block: &models::Block,
+    detector_params: DetectorParams
+) -> Result<(), Box<dyn Error>> {
+    let provider = Provider::<Http>::try_from(chain.provider_url.clone())?;
+    check_chain_id(&provider, chain.chain_id).await?;
+
+    let block_number = u64::try_from(block.block_number)?;
+    let header = provider.get_block(block_number).await?.ok_or("block not found")?;
+    let hash = format!("{:?}", header.hash.ok_or("block hash not found")?);
+
+    let pairs = db::fetch_pairs_with_complete_range(
+        db_connection, &block.blockchain_str_id, block.block_number)?;
+
+    let mut scanners = Vec::new();
+
+    for pair in pairs {
+        let exchange = match exchanges.get(&pair.factory_address) {
+            Some(exchange) => exchange.clone(),
+            None => continue
+        };
+
+        scanners.push(PairScanner::new(db_connection, chain.clone(), pair, exchange, &detector_params).await?);
+    }
+
+    // Make every provider call first, so the transaction below isn't held open across them.
+    let mut scanned = Vec::with_capacity(scanners.len());
+
+    for scanner in scanners.iter() {
+        scanned.push(scanner.fetch_blocks(block_number, block_number).await?);
+    }
+
+    // Replace the block's header only once every pair has been rescanned,
+    // all in one transaction, so a failed rescan leaves the old hash behind
+    // and the block is found (and rescanned) again next time.
+    db_connection.transaction::<_, Box<dyn Error>, _>(|| {
+        // Another rescan of the block may have been saved in the meantime.
+        if db::lock_block_hash(db_connection, block.block_id)? != block.block_hash {
+            return Ok(());
+        }
+
+        for (scanner, scanned) in scanners.iter().zip(scanned.iter()) {
+            db::delete_sandwiches_by_block(db_connection, scanner.pair.pair_id, block.block_number)?;
+            db::delete_jit_attacks_by_block(db_connection, scanner.pair.pair_id, block.block_number)?;
+            db::delete_swaps_by_block(db_connection, scanner.pair.pair_id, block.block_number)?;
+
+            scanner.save_blocks(db_connection, scanned)?;
+        }
+
+        db::update_block_header(
+            db_connection,
+            block.block_id,
+            &hash,
+            &format!("{:?}", header.author),
+            i64::try_from(header.timestamp.low_u64())?,
+            header.base_fee_per_gas.map(|fee| i64::try_from(fee.low_u64())).transpose()?)?;
+
+        Ok(())
+    })
+}
+
 async fn run_scan_loop(
     db_connection: &db::DbConnection,
-    provider_url: String,
+    chain: ChainParams,
     pair: models::Pair,
     exchange: Exchange,
-    native_decimals: u8,
     range: models::Range,
-    params: Params
+    params: Params,
+    detector_params: DetectorParams
 ) -> Result<(), Box<dyn Error>> {
-    let pair_abi = AbiParser::default().parse_str("")?;
-    let provider = Provider::<Http>::try_from(provider_url.clone())?;
-    let address = pair.pair_address.parse::<Address>()?;
-    let contract = Contract::new(address, pair_abi.clone(), provider.clone());
-    let base = db::fetch_token_by_id(&db_connection, pair.base_token_id)?;
-    let quote = db::fetch_token_by_id(&db_connection, pair.quote_token_id)?;
+    let scanner = PairScanner::new(db_connection, chain, pair, exchange, &detector_params).await?;
 
     let mut blocks_per_chunk = params.blocks_per_chunk;
     let mut upper = range.upper_bound as u64;
@@ -105,29 +216,169 @@ async fn run_scan_loop(
 
     while upper >= range.lower_bound as u64 {
         println!("\nLower: {lower}\nUpper: {upper}\nLower Bound: {}", range.lower_bound);
-        let swaps = match exchange {
+        let total_swaps = scanner.scan_blocks(db_connection, lower, upper).await?;
+
+        // Update the block search range.
+        let swap_density = total_swaps as f64 / (upper - lower + 1) as f64;
+        blocks_per_chunk = (params.target_swaps_per_chunk as f64 / swap_density).floor() as u64;
+
+        if blocks_per_chunk > params.max_blocks_per_chunk {
+            blocks_per_chunk = params.max_blocks_per_chunk
+        }
+
+        upper = lower - 1;
+        lower = get_lower_bound!(upper, blocks_per_chunk, range.lower_bound as u64);
+    }
+
+    // Update and mark this range as complete.
+    db::update_range_metadata(&db_connection, range.range_id, true, false).unwrap();
+
+    // Return without error.
+    Ok(())
+}
+
+// Everything needed to scan a single pair's blocks for sandwiches
+// (and JIT liquidity attacks), loaded once per scan job.
+struct PairScanner {
+    provider_url: String,
+    provider: Provider<Http>,
+    chain_kind: ChainKind,
+    pair: models::Pair,
+    exchange: Exchange,
+    native_decimals: u8,
+    contract: Contract<Provider<Http>>,
+    base: models::Token,
+    quote: models::Token,
+    detector: Box<dyn SandwichDetector>,
+    detector_version: String,
+    alternatives: Vec<Box<dyn SandwichDetector>>
+}
+
+// The swaps decoded from a range of blocks on a pair, along with the
+// JIT liquidity attacks and sandwiches found in them, ready to be saved.
+struct ScannedBlocks<'a> {
+    swaps: Vec<SwapCore>,
+    jit_attacks: Vec<JitAttack<'a>>,
+    sandwiches: Vec<BlockSandwiches<'a>>
+}
+
+// The sandwiches found in a block, with their bribes,
+// and the block's header, ready to be saved.
+struct BlockSandwiches<'a> {
+    block: u64,
+    hash: String,
+    miner: String,
+    timestamp: i64,
+    base_fee: Option<U256>,
+    sandwiches: Vec<(Sandwich<'a>, Bribe)>
+}
+
+impl PairScanner {
+    async fn new(
+        db_connection: &db::DbConnection,
+        chain: ChainParams,
+        pair: models::Pair,
+        exchange: Exchange,
+        detector_params: &DetectorParams
+    ) -> Result<Self, Box<dyn Error>> {
+        let pair_abi = AbiParser::default().parse_str("")?;
+        let provider = Provider::<Http>::try_from(chain.provider_url.clone())?;
+
+        // Refuse to scan if the provider is not on the expected chain,
+        // since the sandwiches would be saved under the wrong blockchain.
+        check_chain_id(&provider, chain.chain_id).await?;
+
+        let address = pair.pair_address.parse::<Address>()?;
+        let contract = Contract::new(address, pair_abi.clone(), provider.clone());
+        let base = db::fetch_token_by_id(&db_connection, pair.base_token_id)?;
+        let quote = db::fetch_token_by_id(&db_connection, pair.quote_token_id)?;
+        let detector = detector_params.build(&pair.factory_address);
+        let detector_version = detector.version();
+        let alternatives = if detector_params.compare {
+            detector_params.build_alternatives(&pair.factory_address)
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            provider_url: chain.provider_url,
+            provider,
+            chain_kind: chain.chain_kind,
+            pair,
+            exchange,
+            native_decimals: chain.native_decimals,
+            contract,
+            base,
+            quote,
+            detector,
+            detector_version,
+            alternatives
+        })
+    }
+
+    // Scan the blocks from `lower` to `upper` (inclusive) for sandwiches
+    // and JIT liquidity attacks, save them (along with every swap) in one
+    // transaction, and return the number of swaps.
+    async fn scan_blocks(
+        &self,
+        db_connection: &db::DbConnection,
+        lower: u64,
+        upper: u64
+    ) -> Result<usize, Box<dyn Error>> {
+        let scanned = self.fetch_blocks(lower, upper).await?;
+
+        db_connection.transaction::<_, Box<dyn Error>, _>(|| {
+            self.save_blocks(db_connection, &scanned)
+        })?;
+
+        Ok(scanned.swaps.len())
+    }
+
+    // Fetch the swaps from `lower` to `upper` (inclusive), and look for
+    // sandwiches and JIT liquidity attacks in them, without saving anything,
+    // so that every provider call is made before the results are saved.
+    async fn fetch_blocks(
+        &self,
+        lower: u64,
+        upper: u64
+    ) -> Result<ScannedBlocks<'_>, Box<dyn Error>> {
+        let mut liquidity_events: Vec<LiquidityEvent> = Vec::new();
+        let mut decoded_swaps = match self.exchange {
             Exchange::V2 { name: _ } => {
-                let raw_swaps: Vec<(RawSwapV2, LogMeta)> = contract.event()
+                let raw_swaps: Vec<(RawSwapV2, LogMeta)> = self.contract.event()
                    .from_block::<u64>(lower).to_block::<u64>(upper).query_with_meta().await?;
 
-                raw_swaps.into_iter()
-                    .map(|raw_swap| to_wrapped(
-                        SwapCore::from(raw_swap), native_decimals, &base, &quote))
-                    .collect::<Vec<Swap>>()
+                raw_swaps.into_iter().map(SwapCore::from).collect::<Vec<SwapCore>>()
             },
             Exchange::V3 { name: _ } => {
-                let raw_swaps: Vec<(RawSwapV3, LogMeta)> = contract.event()
+                let raw_swaps: Vec<(RawSwapV3, LogMeta)> = self.contract.event()
+                    .from_block::<u64>(lower).to_block::<u64>(upper).query_with_meta().await?;
+
+                // Also load liquidity changes, to look for JIT liquidity attacks.
+                let raw_mints: Vec<(RawMintV3, LogMeta)> = self.contract.event()
+                    .from_block::<u64>(lower).to_block::<u64>(upper).query_with_meta().await?;
+                let raw_burns: Vec<(RawBurnV3, LogMeta)> = self.contract.event()
                     .from_block::<u64>(lower).to_block::<u64>(upper).query_with_meta().await?;
+                let raw_collects: Vec<(RawCollectV3, LogMeta)> = self.contract.event()
+                    .from_block::<u64>(lower).to_block::<u64>(upper).query_with_meta().await?;
+
+                liquidity_events.extend(raw_mints.into_iter().map(LiquidityEvent::from));
+                liquidity_events.extend(raw_burns.into_iter().map(LiquidityEvent::from));
+                liquidity_events.extend(raw_collects.into_iter().map(LiquidityEvent::from));
 
-                raw_swaps.into_iter()
-                    .map(|raw_swap| to_wrapped(
-                        SwapCore::from(raw_swap), native_decimals, &base, &quote))
-                    .collect::<Vec<Swap>>()
+                raw_swaps.into_iter().map(SwapCore::from).collect::<Vec<SwapCore>>()
             }
         };
 
-        let total_swaps = swaps.len();
-        println!(" -- Fetched {total_swaps} swaps!");
+        // Detect over the swaps in the order they're stored in (and loaded
+        // from by re-detection), since stored swaps are rebuilt exactly.
+        decoded_swaps.sort_by_key(|swap| (swap.block_number(), swap.log_index()));
+
+        let swaps = decoded_swaps.iter()
+            .map(|swap| to_wrapped(swap.clone(), self.native_decimals, &self.base, &self.quote))
+            .collect::<Vec<Swap>>();
+
+        println!(" -- Fetched {} swaps!", swaps.len());
 
         // Group swaps by block, and filter out blocks with less than three swaps.
         let mut swaps_per_block: HashMap<u64, u64> = HashMap::new();
@@ -135,7 +386,22 @@ async fn run_scan_loop(
 
         for swap in swaps.into_iter() {
             *swaps_per_block.entry(swap.swap.block_number()).or_insert(0) += 1;
-            swaps_by_block.entry(swap.swap.block_number()).or_insert(Vec::new()).push(swap);
+            swaps_by_block.entry(swap.swap.block_number()).or_default().push(swap);
+        }
+
+        // Look for any JIT liquidity attacks, which only
+        // need a single swap between the Mint and the Burn.
+        let mut liquidity_by_block: HashMap<u64, Vec<LiquidityEvent>> = HashMap::new();
+        let mut jit_attacks = Vec::new();
+
+        for event in liquidity_events.into_iter() {
+            liquidity_by_block.entry(event.block_number()).or_default().push(event);
+        }
+
+        for (block, events) in liquidity_by_block.iter() {
+            if let Some(block_swaps) = swaps_by_block.get(block) {
+                jit_attacks.extend(find_jit_attacks(events, block_swaps));
+            }
         }
 
         for block in swaps_per_block.keys() {
@@ -146,74 +412,645 @@ async fn run_scan_loop(
 
         println!(" -- A total of {} blocks have 3 or more swaps.", swaps_by_block.len());
 
-        // Look for and save any sandwich trades in blocks with at least three swaps. 
+        // Look for any sandwich trades in blocks with at least three swaps.
+        let sandwiches = self.find_sandwiches(&swaps_by_block).await?;
+
+        Ok(ScannedBlocks { swaps: decoded_swaps, jit_attacks, sandwiches })
+    }
+
+    // Save the given scanned blocks' swaps, JIT liquidity attacks and sandwiches;
+    // the swaps are stored so that they can be re-analyzed later without the provider.
+    fn save_blocks(
+        &self,
+        db_connection: &db::DbConnection,
+        scanned: &ScannedBlocks
+    ) -> Result<(), Box<dyn Error>> {
+        store_swaps(db_connection, self.pair.pair_id, &scanned.swaps)?;
+
+        for attack in scanned.jit_attacks.iter() {
+            let db_attack = match db::insert_jit_attack(
+                db_connection,
+                self.pair.pair_id,
+                i64::try_from(attack.mint.block_number())?,
+                &db::NewJitAttack {
+                    owner: &format!("{:?}", attack.mint.owner),
+                    ticks: (attack.mint.tick_lower, attack.mint.tick_upper),
+                    liquidity: attack.mint.liquidity as f64,
+                    mint: (&format!("{:?}", attack.mint.tx_hash), attack.mint.tx_index()),
+                    burn: (&format!("{:?}", attack.burn.tx_hash), attack.burn.tx_index()),
+                    minted: (attack.base_minted(&self.base), attack.quote_minted(&self.quote)),
+                    burned: (attack.base_burned(&self.base), attack.quote_burned(&self.quote)),
+                    fees: (attack.base_fees(&self.base), attack.quote_fees(&self.quote))
+                })? {
+                Some(db_attack) => db_attack,
+                None => continue // already saved, along with its swaps
+            };
+
+            for swap in attack.swaps.iter() {
+                db::insert_jit_swap_transaction(
+                    db_connection,
+                    &format!("{:?}", swap.swap.tx_hash),
+                    swap.swap.tx_index(),
+                    (swap.in0(), swap.in1(), swap.out0(), swap.out1()),
+                    db_attack.jit_attack_id)?;
+            }
+        }
+
+        self.save_sandwiches(db_connection, &scanned.sandwiches)
+    }
+
+    // Supersede the sandwiches from `lower` to `upper` (inclusive),
+    // and look for them again in the stored swaps.
+    async fn redetect_blocks(
+        &self,
+        db_connection: &db::DbConnection,
+        lower: u64,
+        upper: u64
+    ) -> Result<(), Box<dyn Error>> {
+        let swaps = load_stored_swaps(
+            db_connection,
+            &self.pair,
+            (lower, upper),
+            self.native_decimals,
+            &self.base,
+            &self.quote)?;
+
+        let mut swaps_by_block: HashMap<u64, Vec<Swap>> = HashMap::new();
+
+        for swap in swaps.into_iter() {
+            swaps_by_block.entry(swap.swap.block_number()).or_default().push(swap);
+        }
+
+        swaps_by_block.retain(|_, swaps| swaps.len() >= 3);
+
+        // Make every provider call first, then supersede the old sandwiches
+        // and save the new ones in one transaction, so that a failure leaves
+        // the old sandwiches current.
+        let found = self.find_sandwiches(&swaps_by_block).await?;
+
+        db_connection.transaction::<_, Box<dyn Error>, _>(|| {
+            db::supersede_sandwiches(
+                db_connection, self.pair.pair_id, i64::try_from(lower)?, i64::try_from(upper)?)?;
+            self.save_sandwiches(db_connection, &found)
+        })
+    }
+
+    // Look for sandwiches in the given bundles of swaps (by block),
+    // along with their blocks' headers and their bribes.
+    async fn find_sandwiches<'a>(
+        &self,
+        swaps_by_block: &HashMap<u64, Vec<Swap<'a>>>
+    ) -> Result<Vec<BlockSandwiches<'a>>, Box<dyn Error>> {
+        let mut found = Vec::new();
+
         for block in swaps_by_block.keys() {
             let mut bundle = swaps_by_block.get(block).unwrap().to_vec();
             bundle.sort_by_key(|s| s.swap.tx_index());
 
-            // Pull sandwich data from the bundle of swaps,
-            // and save these sandwiches to the database.
-            for sandwich in parse_sandwiches(&bundle, &provider_url).await? {
+            // Optionally log how the other detectors would have done.
+            if !self.alternatives.is_empty() {
+                compare_detectors(*block, &bundle, self.detector.as_ref(), &self.alternatives);
+            }
+
+            // Pull sandwich data from the bundle of swaps.
+            let sandwiches = parse_sandwiches(&bundle, self.detector.as_ref(), &self.provider_url, self.chain_kind).await?;
+
+            if sandwiches.is_empty() {
+                continue;
+            }
+
+            // Load the block's header and transactions, to find bribes.
+            let block_with_txs = self.provider.get_block_with_txs(*block).await?
+                .ok_or("block not found")?;
+
+            let mut bribed = Vec::with_capacity(sandwiches.len());
+
+            for sandwich in sandwiches {
+                let bribe = find_sandwich_bribe(
+                    &self.provider, &sandwich, block_with_txs.author, &block_with_txs.transactions).await?;
+                bribed.push((sandwich, bribe));
+            }
+
+            found.push(BlockSandwiches {
+                block: *block,
+                hash: format!("{:?}", block_with_txs.hash.ok_or("block hash not found")?),
+                miner: format!("{:?}", block_with_txs.author),
+                timestamp: i64::try_from(block_with_txs.timestamp.low_u64())?,
+                base_fee: block_with_txs.base_fee_per_gas,
+                sandwiches: bribed
+            });
+        }
+
+        Ok(found)
+    }
+
+    // Save the given sandwiches to the database, along with their blocks.
+    fn save_sandwiches(
+        &self,
+        db_connection: &db::DbConnection,
+        found: &[BlockSandwiches]
+    ) -> Result<(), Box<dyn Error>> {
+        for found_block in found.iter() {
+            let block = i64::try_from(found_block.block)?;
+            let base_fee = found_block.base_fee;
+
+            // Save the block's header data, to attribute its sandwiches to its builder.
+            let db_block = db::fetch_or_insert_block(
+                db_connection,
+                &self.pair.blockchain_str_id,
+                block,
+                &found_block.hash,
+                &found_block.miner,
+                found_block.timestamp,
+                base_fee.map(|fee| i64::try_from(fee.low_u64())).transpose()?)?;
+
+            for (sandwich, bribe) in found_block.sandwiches.iter() {
                 let db_sandwich = db::insert_sandwich(
                     db_connection,
-                    pair.pair_id,
-                    i64::try_from(*block)?)?;
+                    self.pair.pair_id,
+                    block,
+                    &format!("{:?}", sandwich.attacker()),
+                    (bribe.amount(self.native_decimals), bribe.tx_hash.map(|hash| format!("{:?}", hash)).as_deref()),
+                    Some(db_block.block_id),
+                    &self.detector_version)?;
+
+                // Insert the attacker's net balance change in each token it moved.
+                for (token_address, amount) in sandwich.token_profits() {
+                    let token_address = format!("{:?}", token_address);
+                    let token = if token_address == self.base.token_address {
+                        Some(self.base.clone())
+                    } else if token_address == self.quote.token_address {
+                        Some(self.quote.clone())
+                    } else {
+                        db::fetch_token_by_params(db_connection, &self.pair.blockchain_str_id, &token_address).ok()
+                    };
+
+                    db::insert_sandwich_token_profit(
+                        db_connection,
+                        db_sandwich.sandwich_id,
+                        &token_address,
+                        token.as_ref().map(|t| t.token_id),
+                        i256_to_f64(amount, token.map(|t| t.decimals as u32).unwrap_or(0)))?;
+                }
 
                 // Insert the frontrun transaction.
                 db::insert_frontrun_transaction(
                     db_connection,
-                    &format!("0x{}", sandwich.frontrun.swap.tx_hash()),
+                    &format!("{:?}", sandwich.frontrun.swap.tx_hash),
                     sandwich.frontrun.swap.tx_index(),
-                    sandwich.frontrun.in0(),
-                    sandwich.frontrun.in1(),
-                    sandwich.frontrun.out0(),
-                    sandwich.frontrun.out1(),
-                    sandwich.frontrun.gas(),
+                    amounts(&sandwich.frontrun),
+                    &gas_fees(&sandwich.frontrun, base_fee),
                     db_sandwich.sandwich_id)?;
 
                 // Insert the lunchmeat transaction(s).
-                for i in 0..sandwich.lunchmeat.len() {
+                for (i, lunchmeat) in sandwich.lunchmeat.iter().enumerate() {
                     db::insert_lunchmeat_transaction(
                         db_connection,
-                        &format!("0x{}", sandwich.lunchmeat[i].swap.tx_hash()),
-                        sandwich.lunchmeat[i].swap.tx_index(),
-                        sandwich.lunchmeat[i].in0(),
-                        sandwich.lunchmeat[i].in1(),
-                        sandwich.lunchmeat[i].out0(),
-                        sandwich.lunchmeat[i].out1(),
-                        sandwich.lunchmeat[i].gas(),
-                        db_sandwich.sandwich_id)?;
+                        &format!("{:?}", lunchmeat.swap.tx_hash),
+                        lunchmeat.swap.tx_index(),
+                        amounts(lunchmeat),
+                        &gas_fees(lunchmeat, base_fee),
+                        db_sandwich.sandwich_id,
+                        sandwich.is_victim(i))?;
                 }
 
                 // Insert the backrun transaction.
                 db::insert_backrun_transaction(
                     db_connection,
-                    &format!("0x{}", sandwich.backrun.swap.tx_hash()),
+                    &format!("{:?}", sandwich.backrun.swap.tx_hash),
                     sandwich.backrun.swap.tx_index(),
-                    sandwich.backrun.in0(),
-                    sandwich.backrun.in1(),
-                    sandwich.backrun.out0(),
-                    sandwich.backrun.out1(),
-                    sandwich.backrun.gas(),
+                    amounts(&sandwich.backrun),
+                    &gas_fees(&sandwich.backrun, base_fee),
                     db_sandwich.sandwich_id)?;
             }
         }
 
-        // Update the block search range.
-        let swap_density = total_swaps as f64 / (upper - lower + 1) as f64;
-        blocks_per_chunk = (params.target_swaps_per_chunk as f64 / swap_density).floor() as u64;
+        Ok(())
+    }
+}
 
-        if blocks_per_chunk > params.max_blocks_per_chunk {
-            blocks_per_chunk = params.max_blocks_per_chunk
+// Re-run detection over the swaps stored for the given completed range with
+// the current detector, block on block, superseding the range's previous
+// sandwiches; the sandwiches' transactions are still fetched from the provider.
+pub fn redetect_range(
+    db_connection: &db::DbConnection,
+    chain: ChainParams,
+    pair: models::Pair,
+    exchange: Exchange,
+    range: models::Range,
+    params: Params,
+    detector_params: DetectorParams
+) -> Result<(), String> {
+    let runtime = Runtime::new().map_err(|e| e.to_string())?;
+
+    runtime.block_on(async {
+        let scanner = PairScanner::new(db_connection, chain, pair, exchange, &detector_params).await?;
+
+        let mut upper = range.upper_bound as u64;
+
+        while upper >= range.lower_bound as u64 {
+            let lower = get_lower_bound!(upper, params.max_blocks_per_chunk, range.lower_bound as u64);
+            scanner.redetect_blocks(db_connection, lower, upper).await?;
+            upper = lower - 1;
         }
 
-        upper = lower - 1;
-        lower = get_lower_bound!(upper, blocks_per_chunk, range.lower_bound as u64);
+        db::update_range_detector_version(db_connection, range.range_id, &scanner.detector_version)?;
+        Ok(())
+    }).map_err(|e: Box<dyn Error>| e.to_string())
+}
+
+// Save the given decoded swaps on the given pair to the database.
+fn store_swaps(
+    db_connection: &db::DbConnection,
+    pair_id: i32,
+    swaps: &[SwapCore]
+) -> Result<usize, Box<dyn Error>> {
+    let mut new_swaps = Vec::with_capacity(swaps.len());
+
+    for swap in swaps.iter() {
+        let (in0, in1, out0, out1) = swap.raw_amounts();
+
+        new_swaps.push(db::NewSwap {
+            block: i64::try_from(swap.block_number())?,
+            tx: (format!("{:?}", swap.tx_hash), swap.tx_index()),
+            log_index: i32::try_from(swap.log_index())?,
+            addresses: (format!("{:?}", swap.sender()), format!("{:?}", swap.recipient())),
+            amounts: (in0.to_string(), in1.to_string(), out0.to_string(), out1.to_string()),
+            price: (
+                swap.sqrt_price().map(|price| price.to_string()),
+                swap.liquidity().map(|liquidity| liquidity.to_string()),
+                swap.tick())
+        });
     }
 
-    // Update and mark this range as complete.
-    db::update_range_metadata(&db_connection, range.range_id, true, false).unwrap();
+    Ok(db::insert_swaps(db_connection, pair_id, &new_swaps)?)
+}
+
+// Load the swaps stored for the given pair over the given (lower, upper)
+// block range (inclusive), wrapped with the pair's token metadata.
+pub fn load_stored_swaps<'a>(
+    db_connection: &db::DbConnection,
+    pair: &models::Pair,
+    (lower, upper): (u64, u64),
+    native_decimals: u8,
+    base: &'a models::Token,
+    quote: &'a models::Token
+) -> Result<Vec<Swap<'a>>, Box<dyn Error>> {
+    let pool = pair.pair_address.parse::<Address>()?;
+    let stored_swaps = db::fetch_swaps_by_params(
+        db_connection, pair.pair_id, i64::try_from(lower)?, i64::try_from(upper)?)?;
+
+    let mut swaps = Vec::with_capacity(stored_swaps.len());
+
+    for stored_swap in stored_swaps.iter() {
+        let swap = SwapCore::from_stored(stored_swap, pool).map_err(|e| e.to_string())?;
+        swaps.push(to_wrapped(swap, native_decimals, base, quote));
+    }
+
+    Ok(swaps)
+}
+
+// The given swap's (base in, quote in, base out, quote out) amounts.
+fn amounts(swap: &Swap) -> (f64, f64, f64, f64) {
+    (swap.in0(), swap.in1(), swap.out0(), swap.out1())
+}
+
+// The gas fees paid by the given swap's transaction, given its block's base fee.
+fn gas_fees(swap: &Swap, base_fee: Option<U256>) -> db::GasFees {
+    db::GasFees {
+        gas: swap.gas(),
+        gas_used: swap.gas_used().map(|used| i64::try_from(used.low_u64()).unwrap_or(i64::MAX)),
+        gas_burned: swap.gas_burned(base_fee),
+        gas_tip: swap.gas_tip(base_fee),
+        gas_l1_fee: swap.gas_l1_fee()
+    }
+}
+
+// Return an error if the provider is not on the expected chain.
+pub async fn check_chain_id(
+    provider: &Provider<Http>,
+    chain_id: u64
+) -> Result<(), Box<dyn Error>> {
+    let reported_chain_id = provider.get_chainid().await?.as_u64();
+
+    if reported_chain_id != chain_id {
+        return Err(Box::new(ScanError::ChainIdMismatch {
+            expected: chain_id,
+            reported: reported_chain_id
+        }));
+    }
 
-    // Return without error.
     Ok(())
-}
\ No newline at end of file
+}
+
+pub enum ScanError {
+    ChainIdMismatch { expected: u64, reported: u64 }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ChainIdMismatch { expected, reported } => write!(
+                f, "the provider reported chain id {reported} instead of {expected}")
+        }
+    }
+}
+
+impl fmt::Debug for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for ScanError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::detector::{ DetectorKind, DEFAULT_TOLERANCE };
+    use super::super::mock_rpc::{ Cassette, MockRpc };
+    use crate::api::test_db::test_db_connection;
+    use ethers::types::H256;
+
+    const PAIR_ADDRESS: &str = "0x11982c91f8f0438475b20e67ff9dbe5aa0d25db2";
+    const FACTORY_ADDRESS: &str = "0x5c69bee701ef814a2b6a3edd4b1652cb9cc5aa6f";
+
+    fn mock_chain(rpc: &MockRpc) -> ChainParams {
+        ChainParams {
+            provider_url: rpc.url.clone(),
+            chain_id: 1,
+            chain_kind: ChainKind::L1,
+            native_decimals: 18
+        }
+    }
+
+    fn mock_params() -> Params {
+        Params {
+            blocks_per_chunk: 2,
+            max_blocks_per_chunk: 4,
+            target_swaps_per_chunk: 10,
+            max_blocks_per_request: 100,
+            confirmations: 12
+        }
+    }
+
+    fn mock_detector_params() -> DetectorParams {
+        DetectorParams {
+            kind: DetectorKind::AmountRatio,
+            tolerance: DEFAULT_TOLERANCE,
+            exchange_tolerances: HashMap::new(),
+            compare: false
+        }
+    }
+
+    fn mock_exchanges() -> HashMap<String, Exchange> {
+        HashMap::from([
+            (FACTORY_ADDRESS.to_string(), Exchange::V2 { name: "Uniswap V2".to_string() })
+        ])
+    }
+
+    // Save the cassette's pair and scan its blocks, returning its id.
+    fn scan_mock_pair(db_connection: &db::DbConnection, rpc: &MockRpc) -> i32 {
+        let base_id = db::insert_token(
+            db_connection, "Wrapped Ether", "WETH", 18, "mock", "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2").unwrap();
+        let quote_id = db::insert_token(
+            db_connection, "USD Coin", "USDC", 6, "mock", "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48").unwrap();
+        let pair_id = db::insert_pair(db_connection, "mock", FACTORY_ADDRESS, PAIR_ADDRESS, base_id, quote_id, None).unwrap();
+        let pair = db::fetch_pair_by_id(db_connection, pair_id).unwrap();
+
+        let detector_params = mock_detector_params();
+        let range = db::insert_range(
+            db_connection, pair_id, 17000000, 17000005, false, false,
+            &detector_params.build(FACTORY_ADDRESS).version()).unwrap();
+
+        Runtime::new().unwrap().block_on(run_scan_loop(
+            db_connection,
+            mock_chain(rpc),
+            pair,
+            mock_exchanges()[FACTORY_ADDRESS].clone(),
+            range,
+            mock_params(),
+            detector_params)).unwrap();
+
+        pair_id
+    }
+
+    #[test]
+    #[ignore = "requires TEST_DATABASE_URL"]
+    fn scanned_sandwiches_are_saved() {
+        let db_connection = test_db_connection();
+
+        let rpc = MockRpc::start(Cassette::load("classic"));
+        let pair_id = scan_mock_pair(&db_connection, &rpc);
+
+        // Every swap is stored, and the range is marked complete.
+        assert_eq!(db::fetch_swaps_by_params(&db_connection, pair_id, 17000000, 17000005).unwrap().len(), 14);
+        assert_eq!(db::fetch_complete_ranges_by_pair_id(&db_connection, pair_id).unwrap().len(), 1);
+
+        // Exactly the labeled sandwiches are saved, with their blocks' builders and bribes.
+        let mut sandwiches = db::fetch_all_sandwiches_by_params(&db_connection, pair_id, None, None, None).unwrap();
+        sandwiches.sort_by_key(|sandwich| sandwich.block_number);
+
+        let found = sandwiches.iter()
+            .map(|sandwich| (
+                sandwich.block_number,
+                db::fetch_frontrun_transaction_by_sandwich_id(&db_connection, sandwich.sandwich_id).unwrap().tx_hash,
+                db::fetch_backrun_transaction_by_sandwich_id(&db_connection, sandwich.sandwich_id).unwrap().tx_hash))
+            .collect::<Vec<(i64, String, String)>>();
+
+        assert_eq!(found, vec![
+            (
+                17000001,
+                "0x30e6bf46f626d725278baa0646f6d801cef44dc30fa8ca06e8efa490beda689f".to_string(),
+                "0xb0385d514f5b76c1cd84d6586036d1846ba310804061ab740ae9270644afedad".to_string()
+            ),
+            (
+                17000002,
+                "0x38bfdc5a1b300626b7156697e956cdab20d59fe36731c6179c348040a3736d93".to_string(),
+                "0x75f7eeecb6fdff23e1b3436453989b344f4523a236bfe96928f0983580fdab3f".to_string()
+            )
+        ]);
+
+        let block = db::fetch_block_by_id(&db_connection, sandwiches[0].block_id.unwrap()).unwrap();
+        assert_eq!(block.miner, "0x73e9fda8bf4a209333c3c9142f25ce2e2db14d5d");
+        assert_eq!(block.base_fee, Some(20_000_000_000));
+        assert_eq!(sandwiches[0].attacker_address.as_deref(), Some("0xcab9d2ad97b9b22f4c3fc0dfa0e125c6c7d52eab"));
+        assert_eq!(sandwiches[0].bribe, Some(0.05));
+        assert_eq!(
+            sandwiches[0].bribe_tx_hash.as_deref(),
+            Some("0x183409d7082388a4386c6b3ce083adbb4efc6b91c74ee27c7a1ecd4b0397545e"));
+
+        let lunchmeat = db::fetch_lunchmeat_transactions_by_sandwich_id(&db_connection, sandwiches[1].sandwich_id).unwrap();
+        assert_eq!(lunchmeat.len(), 3);
+
+        // The frontrun paid 22 gwei per gas over a 20 gwei base fee.
+        let frontrun = db::fetch_frontrun_transaction_by_sandwich_id(&db_connection, sandwiches[0].sandwich_id).unwrap();
+        assert_eq!(frontrun.gas_used, Some(150_000));
+        assert_eq!((frontrun.gas, frontrun.gas_burned, frontrun.gas_tip), (0.0033, Some(0.003), Some(0.0003)));
+    }
+
+    #[test]
+    #[ignore = "requires TEST_DATABASE_URL"]
+    fn reorged_blocks_are_rescanned() {
+        let db_connection = test_db_connection();
+
+        let rpc = MockRpc::start(Cassette::load("classic"));
+        let pair_id = scan_mock_pair(&db_connection, &rpc);
+
+        let sandwiches = db::fetch_all_sandwiches_by_params(&db_connection, pair_id, None, None, None).unwrap();
+        let sandwich = sandwiches.iter().find(|sandwich| sandwich.block_number == 17000001).unwrap();
+        let block = db::fetch_block_by_id(&db_connection, sandwich.block_id.unwrap()).unwrap();
+
+        // Pretend the block was recorded with a hash that has since been reorged out.
+        let stale_hash = format!("{:?}", H256::repeat_byte(1));
+        let stale_block = db::update_block_header(
+            &db_connection, block.block_id, &stale_hash, &block.miner, block.block_timestamp, block.base_fee).unwrap();
+
+        Runtime::new().unwrap().block_on(rescan_block(
+            &db_connection,
+            mock_chain(&rpc),
+            mock_exchanges(),
+            &stale_block,
+            mock_detector_params())).unwrap();
+
+        // The block's sandwich is found again, and only then is its header replaced.
+        let rescanned = db::fetch_all_sandwiches_by_params(&db_connection, pair_id, None, None, None).unwrap();
+        let resaved = rescanned.iter().find(|sandwich| sandwich.block_number == 17000001).unwrap();
+        assert_eq!(rescanned.len(), sandwiches.len());
+        assert_ne!(resaved.sandwich_id, sandwich.sandwich_id);
+        assert_eq!(db::fetch_block_by_id(&db_connection, block.block_id).unwrap().block_hash, block.block_hash);
+
+        // Another rescan of the stale block (e.g. one started before the first
+        // was saved) finds the new hash, and leaves the block's sandwiches alone.
+        Runtime::new().unwrap().block_on(rescan_block(
+            &db_connection,
+            mock_chain(&rpc),
+            mock_exchanges(),
+            &stale_block,
+            mock_detector_params())).unwrap();
+
+        let sandwich_ids = |sandwiches: &[models::Sandwich]| {
+            let mut ids = sandwiches.iter().map(|sandwich| sandwich.sandwich_id).collect::<Vec<i64>>();
+            ids.sort();
+            ids
+        };
+
+        let again = db::fetch_all_sandwiches_by_params(&db_connection, pair_id, None, None, None).unwrap();
+        assert_eq!(sandwich_ids(&again), sandwich_ids(&rescanned));
+    }
+
+    #[test]
+    #[ignore = "requires TEST_DATABASE_URL"]
+    fn rescanning_blocks_are_released_when_done() {
+        let db_connection = test_db_connection();
+        let rescanning_blocks = Arc::new(Mutex::new(HashSet::from([17000001])));
+
+        // Nothing listens here, so the rescan fails straight away.
+        let chain = ChainParams {
+            provider_url: "http://127.0.0.1:1".to_string(),
+            chain_id: 1,
+            chain_kind: ChainKind::L1,
+            native_decimals: 18
+        };
+        let block = models::Block {
+            block_id: 1,
+            blockchain_str_id: "mock".to_string(),
+            block_number: 17000001,
+            block_hash: format!("{:?}", H256::repeat_byte(1)),
+            miner: format!("{:?}", Address::zero()),
+            block_timestamp: 0,
+            base_fee: None
+        };
+
+        assert!(start_rescan_job(
+            db_connection, chain, mock_exchanges(), block, mock_detector_params(), rescanning_blocks.clone()));
+
+        for _ in 0..100 {
+            if rescanning_blocks.lock().unwrap().is_empty() {
+                return;
+            }
+
+            thread::sleep(std::time::Duration::from_millis(50));
+        }
+
+        panic!("the block was never released");
+    }
+
+    #[test]
+    #[ignore = "requires TEST_DATABASE_URL"]
+    fn jit_attacks_are_saved_once() {
+        let db_connection = test_db_connection();
+
+        let token_id = db::insert_token(
+            &db_connection, "Token", "TKN", 18, "mock", &format!("{:?}", Address::zero())).unwrap();
+        let pair_id = db::insert_pair(
+            &db_connection, "mock", FACTORY_ADDRESS, PAIR_ADDRESS, token_id, token_id, Some(500)).unwrap();
+
+        let (mint_hash, burn_hash) = (format!("{:?}", H256::repeat_byte(1)), format!("{:?}", H256::repeat_byte(2)));
+        let attack = db::NewJitAttack {
+            owner: &format!("{:?}", Address::repeat_byte(0xaa)),
+            ticks: (-10, 10),
+            liquidity: 1_000.0,
+            mint: (&mint_hash, 0),
+            burn: (&burn_hash, 2),
+            minted: (1.0, 1.0),
+            burned: (1.1, 0.9),
+            fees: (0.01, 0.01)
+        };
+
+        // Saving the same attack again (e.g. when its range is scanned again) does nothing.
+        assert!(db::insert_jit_attack(&db_connection, pair_id, 17000001, &attack).unwrap().is_some());
+        assert!(db::insert_jit_attack(&db_connection, pair_id, 17000001, &attack).unwrap().is_none());
+        assert_eq!(db::fetch_jit_attacks_by_params(&db_connection, pair_id, 17000001, 17000001).unwrap().len(), 1);
+    }
+
+    #[test]
+    #[ignore = "requires TEST_DATABASE_URL"]
+    fn redetected_sandwiches_supersede_the_old_ones() {
+        let db_connection = test_db_connection();
+
+        let rpc = MockRpc::start(Cassette::load("classic"));
+        let pair_id = scan_mock_pair(&db_connection, &rpc);
+        let pair = db::fetch_pair_by_id(&db_connection, pair_id).unwrap();
+        let range = db::fetch_complete_ranges_by_pair_id(&db_connection, pair_id).unwrap().remove(0);
+        let version = range.detector_version.clone();
+        let old = db::fetch_all_sandwiches_by_params(&db_connection, pair_id, None, None, None).unwrap();
+
+        redetect_range(
+            &db_connection,
+            mock_chain(&rpc),
+            pair,
+            mock_exchanges()[FACTORY_ADDRESS].clone(),
+            range,
+            mock_params(),
+            mock_detector_params()).unwrap();
+
+        // The same sandwiches are found again, as new rows,
+        // and only the new rows are current.
+        let current = db::fetch_all_sandwiches_by_params(&db_connection, pair_id, None, None, None).unwrap();
+        let all = db::fetch_all_sandwiches_by_params(&db_connection, pair_id, None, None, Some(&version)).unwrap();
+
+        let mut old_blocks = old.iter().map(|sandwich| sandwich.block_number).collect::<Vec<i64>>();
+        let mut current_blocks = current.iter().map(|sandwich| sandwich.block_number).collect::<Vec<i64>>();
+        old_blocks.sort();
+        current_blocks.sort();
+
+        assert_eq!(current_blocks, old_blocks);
+        assert_eq!(all.len(), old.len() + current.len());
+
+        for sandwich in all.iter() {
+            let is_old = old.iter().any(|old| old.sandwich_id == sandwich.sandwich_id);
+            assert_eq!(sandwich.superseded, is_old);
+        }
+    }
+
+    #[test]
+    fn gas_used_is_unknown_without_a_receipt() {
+        let token = models::Token::mock(1, 18);
+        let core = SwapCore::mock(0, Address::zero(), Address::zero(), Address::zero(), (10, 0, 0, 10));
+        let fees = gas_fees(&to_wrapped(core, 18, &token, &token), Some(U256::from(20_000_000_000u64)));
+
+        assert_eq!(fees.gas_used, None);
+        assert_eq!((fees.gas, fees.gas_burned, fees.gas_tip, fees.gas_l1_fee), (0.0, None, None, None));
+    }
+}
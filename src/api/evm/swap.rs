@@ -3,14 +3,19 @@ use ethers::types::{ Address, U64, U128, U256, I256, TxHash, Sign, Transaction,
 use ethers::utils::{ hex, format_units };
 use ethers::core as ethers_core;
 use ethers::contract as ethers_contract;
-use super::super::models::Token;
+use super::super::models::{ Token, Swap as StoredSwap };
+use super::rollup::RollupReceipt;
+use crate::state::ChainKind;
 use std::convert::From;
+use std::error::Error;
 
 #[derive(Debug, Clone)]
 pub struct Swap<'a> {
     pub swap: SwapCore,
     pub transaction: Option<Transaction>,
     pub receipt: Option<TransactionReceipt>,
+    pub extra_fee: U256, // fees charged outside of gas, e.g. a rollup's L1 data fee
+    pub l1_fee: U256, // the part of the total fee that paid for posting to L1, on rollups
     pub native_decimals: u8,
     pub base: &'a Token,
     pub quote: &'a Token
@@ -25,6 +30,11 @@ impl<'a> Swap<'a> {
         self.receipt = Some(receipt);
     }
 
+    pub fn add_rollup_meta(&mut self, chain_kind: ChainKind, receipt: &RollupReceipt) {
+        self.extra_fee = receipt.extra_fee(chain_kind);
+        self.l1_fee = receipt.l1_fee(chain_kind);
+    }
+
     pub fn in0(&self) -> f64 {
         self.swap.in0(self.base.decimals as u8)
     }
@@ -41,28 +51,62 @@ impl<'a> Swap<'a> {
         self.swap.out1(self.quote.decimals as u8)
     }
 
+    // The price actually paid per unit of gas. For EIP-1559 transactions
+    // this is only known from the receipt; legacy receipts may omit it.
+    pub fn gas_price(&self) -> Option<U256> {
+        match self.receipt.as_ref().and_then(|receipt| receipt.effective_gas_price) {
+            Some(price) => Some(price),
+            None => self.transaction.as_ref().and_then(|transaction| transaction.gas_price)
+        }
+    }
+
+    pub fn gas_used(&self) -> Option<U256> {
+        self.receipt.as_ref().and_then(|receipt| receipt.gas_used)
+    }
+
+    // The total fee paid, in the native token,
+    // including any fees charged outside of gas.
     pub fn gas(&self) -> f64 {
-        let transaction = match &self.transaction {
-            Some(transaction) => transaction,
-            None => return 0f64
-        };
+        match (self.gas_price(), self.gas_used()) {
+            (Some(price), Some(used)) => self.to_native(
+                price.checked_mul(used).and_then(|fee| fee.checked_add(self.extra_fee))),
+            _ => 0f64
+        }
+    }
 
-        let gas_price = match transaction.gas_price {
-            Some(price) => price,
-            None => return 0f64
-        };
+    // The part of the gas fee that was burned, given the block's base fee
+    // (which is `None` for blocks before EIP-1559, where nothing is burned),
+    // or `None` if the gas price or the gas used is unknown.
+    pub fn gas_burned(&self, base_fee: Option<U256>) -> Option<f64> {
+        match (self.gas_price(), self.gas_used()) {
+            (Some(price), Some(used)) => Some(match base_fee {
+                Some(base_fee) => self.to_native(price.min(base_fee).checked_mul(used)),
+                None => 0f64
+            }),
+            _ => None
+        }
+    }
 
-        let receipt = match &self.receipt {
-            Some(receipt) => receipt,
-            None => return 0f64
-        };
+    // The part of the gas fee that went to the block's fee recipient as a priority
+    // tip, or `None` if the gas price or the gas used is unknown.
+    pub fn gas_tip(&self, base_fee: Option<U256>) -> Option<f64> {
+        match (self.gas_price(), self.gas_used()) {
+            (Some(price), Some(used)) => Some(self.to_native(
+                price.saturating_sub(base_fee.unwrap_or_default()).checked_mul(used))),
+            _ => None
+        }
+    }
 
-        let gas_used = match receipt.gas_used {
-            Some(amount) => amount,
-            None => return 0f64
-        };
+    // The part of the total fee that paid for posting the transaction to L1,
+    // on rollups. On OP Stack chains it's charged on top of the burned fee and
+    // the tip, so the three add up to the total; Arbitrum includes it in both.
+    // It's `None` if the gas used is unknown, since it comes from the receipt.
+    pub fn gas_l1_fee(&self) -> Option<f64> {
+        self.gas_used().map(|_| self.to_native(Some(self.l1_fee)))
+    }
 
-        match gas_price.checked_mul(gas_used) {
+    fn to_native(&self, value: Option<U256>) -> f64 {
+        match value {
             Some(value) => format_units(value, self.native_decimals as u32)
                 .unwrap_or("0.0".to_string()).parse::<f64>().unwrap(),
             None => 0f64
@@ -81,6 +125,8 @@ pub fn to_wrapped<'a>(
         swap,
         transaction: None,
         receipt: None,
+        extra_fee: U256::zero(),
+        l1_fee: U256::zero(),
         native_decimals,
         base,
         quote
@@ -119,15 +165,55 @@ pub struct RawSwapV3 {
     pub tick: I256
 }
 
+// The direction of a swap, from the pair's point of view:
+// `BaseToQuote` means the trader paid in base and received quote.
+#[derive(Debug, Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Direction {
+    BaseToQuote,
+    QuoteToBase,
+    Unknown // e.g. a flash swap that pays in and takes out the same token
+}
+
+impl Direction {
+    // Derive the direction from the net flow of each token into the pair.
+    pub fn from_amounts(in0: U256, in1: U256, out0: U256, out1: U256) -> Self {
+        if in0 > out0 && out1 > in1 {
+            Direction::BaseToQuote
+        } else if in1 > out1 && out0 > in0 {
+            Direction::QuoteToBase
+        } else {
+            Direction::Unknown
+        }
+    }
+
+    // The direction that undoes this one.
+    pub fn reverse(&self) -> Self {
+        match self {
+            Direction::BaseToQuote => Direction::QuoteToBase,
+            Direction::QuoteToBase => Direction::BaseToQuote,
+            Direction::Unknown => Direction::Unknown
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, Ord, PartialEq, PartialOrd)]
 pub struct SwapCore {
     block_number: U64,
     pub tx_hash: TxHash,
     tx_index: U64,
+    log_index: U256,
+    pool: Address,
+    sender: Address,
+    recipient: Address,
+    direction: Direction,
     in0: U256,
     in1: U256,
     out0: U256,
-    out1: U256
+    out1: U256,
+    // The pool's state after a V3 swap.
+    sqrt_price: Option<U256>,
+    liquidity: Option<U128>,
+    tick: Option<i32>
 }
 
 impl SwapCore {
@@ -143,6 +229,43 @@ impl SwapCore {
         self.tx_index.as_u32().try_into().unwrap_or(i32::MAX)
     }
 
+    pub fn log_index(&self) -> u64 {
+        self.log_index.as_u64()
+    }
+
+    pub fn pool(&self) -> Address {
+        self.pool
+    }
+
+    pub fn sender(&self) -> Address {
+        self.sender
+    }
+
+    pub fn recipient(&self) -> Address {
+        self.recipient
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    // The unscaled (in0, in1, out0, out1) amounts.
+    pub fn raw_amounts(&self) -> (U256, U256, U256, U256) {
+        (self.in0, self.in1, self.out0, self.out1)
+    }
+
+    pub fn sqrt_price(&self) -> Option<U256> {
+        self.sqrt_price
+    }
+
+    pub fn liquidity(&self) -> Option<U128> {
+        self.liquidity
+    }
+
+    pub fn tick(&self) -> Option<i32> {
+        self.tick
+    }
+
     pub fn in0(&self, decimals: u8) -> f64 {
         Self::u256_to_f64(self.in0, decimals)
     }
@@ -170,10 +293,18 @@ impl From<(RawSwapV2, LogMeta)> for SwapCore {
             block_number: meta.block_number,
             tx_hash: meta.transaction_hash,
             tx_index: meta.transaction_index,
+            log_index: meta.log_index,
+            pool: meta.address,
+            sender: swap.sender,
+            recipient: swap.recipient,
+            direction: Direction::from_amounts(swap.in0, swap.in1, swap.out0, swap.out1),
             in0: swap.in0,
             in1: swap.in1,
             out0: swap.out0,
-            out1: swap.out1
+            out1: swap.out1,
+            sqrt_price: None,
+            liquidity: None,
+            tick: None
         }
     }
 }
@@ -194,10 +325,96 @@ impl From<(RawSwapV3, LogMeta)> for SwapCore {
             block_number: meta.block_number,
             tx_hash: meta.transaction_hash,
             tx_index: meta.transaction_index,
+            log_index: meta.log_index,
+            pool: meta.address,
+            sender: swap.sender,
+            recipient: swap.recipient,
+            direction: Direction::from_amounts(in0, in1, out0, out1),
             in0: in0,
             in1: in1,
             out0: out0,
-            out1: out1
+            out1: out1,
+            sqrt_price: Some(swap.sqrt_price),
+            liquidity: Some(swap.liquidity),
+            tick: Some(swap.tick.low_i32())
         }
     }
-}
\ No newline at end of file
+}
+
+impl SwapCore {
+    // Rebuild a swap saved to the database by a range scan of
+    // the given pool, without fetching its log again.
+    pub fn from_stored(swap: &StoredSwap, pool: Address) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let in0 = U256::from_dec_str(&swap.amount0_in)?;
+        let in1 = U256::from_dec_str(&swap.amount1_in)?;
+        let out0 = U256::from_dec_str(&swap.amount0_out)?;
+        let out1 = U256::from_dec_str(&swap.amount1_out)?;
+
+        Ok(Self {
+            block_number: U64::from(u64::try_from(swap.block_number)?),
+            tx_hash: swap.tx_hash.parse::<TxHash>()?,
+            tx_index: U64::from(u64::try_from(swap.tx_index)?),
+            log_index: U256::from(u64::try_from(swap.log_index)?),
+            pool,
+            sender: swap.sender.parse::<Address>()?,
+            recipient: swap.recipient.parse::<Address>()?,
+            direction: Direction::from_amounts(in0, in1, out0, out1),
+            in0,
+            in1,
+            out0,
+            out1,
+            sqrt_price: swap.sqrt_price_x96.as_deref().map(U256::from_dec_str).transpose()?,
+            liquidity: swap.liquidity.as_deref().map(U128::from_dec_str).transpose()?,
+            tick: swap.tick
+        })
+    }
+}
+
+#[cfg(test)]
+impl SwapCore {
+    // Build a swap for tests, in its own transaction at the given index,
+    // from its raw (in0, in1, out0, out1) amounts.
+    pub fn mock(
+        tx_index: u64,
+        pool: Address,
+        sender: Address,
+        recipient: Address,
+        (in0, in1, out0, out1): (u64, u64, u64, u64)
+    ) -> Self {
+        let (in0, in1, out0, out1) = (U256::from(in0), U256::from(in1), U256::from(out0), U256::from(out1));
+
+        Self {
+            block_number: U64::one(),
+            tx_hash: TxHash::from_low_u64_be(tx_index + 1),
+            tx_index: U64::from(tx_index),
+            log_index: U256::from(tx_index),
+            pool,
+            sender,
+            recipient,
+            direction: Direction::from_amounts(in0, in1, out0, out1),
+            in0,
+            in1,
+            out0,
+            out1,
+            sqrt_price: None,
+            liquidity: None,
+            tick: None
+        }
+    }
+}
+
+#[cfg(test)]
+impl Token {
+    // Build a token for tests with the given decimals (0 makes raw amounts
+    // read as plain numbers), at an address made of its id (e.g. 0x1111...).
+    pub fn mock(token_id: i32, decimals: i16) -> Self {
+        Self {
+            token_id,
+            token_name: "Token".to_string(),
+            token_symbol: "TKN".to_string(),
+            decimals,
+            blockchain_str_id: "ethereum".to_string(),
+            token_address: format!("{:?}", Address::repeat_byte(0x10 + token_id as u8))
+        }
+    }
+}
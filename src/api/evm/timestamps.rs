@@ -0,0 +1,96 @@
+use ethers::prelude::{ Provider, Http, Middleware };
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+type RpcError = Box<dyn std::error::Error + Send + Sync>;
+
+// Block number (key) -> block timestamp (value), for every block header
+// fetched while resolving dates. Shared by every request on a blockchain,
+// so each search starts from the closest blocks already seen.
+pub type BlockTimestamps = Mutex<BTreeMap<u64, u64>>;
+
+// Find the latest block, up to and including block `upper`, whose timestamp
+// is at or before `time` (in seconds since the epoch), by binary search
+// over `eth_getBlockByNumber`. Return `None` if every block is after `time`.
+pub async fn fetch_block_at_or_before_time(
+    provider_url: &str,
+    cache: &BlockTimestamps,
+    time: u64,
+    upper: u64
+) -> Result<Option<u64>, RpcError> {
+    let provider = Provider::<Http>::try_from(provider_url)?;
+
+    if fetch_block_timestamp(&provider, cache, upper).await? <= time {
+        return Ok(Some(upper));
+    }
+
+    if fetch_block_timestamp(&provider, cache, 0).await? > time {
+        return Ok(None);
+    }
+
+    // Narrow the search to the closest cached blocks on either side of `time`,
+    // keeping `timestamp(lower) <= time < timestamp(upper)`.
+    let (mut lower, mut upper) = {
+        let cache = cache.lock().unwrap();
+        let lower = cache.range(..upper)
+            .rev()
+            .find(|(_, timestamp)| **timestamp <= time)
+            .map(|(block, _)| *block)
+            .unwrap_or(0);
+        let upper = cache.range(lower..=upper)
+            .find(|(_, timestamp)| **timestamp > time)
+            .map(|(block, _)| *block)
+            .unwrap_or(upper);
+
+        (lower, upper)
+    };
+
+    while upper - lower > 1 {
+        let middle = lower + (upper - lower) / 2;
+
+        if fetch_block_timestamp(&provider, cache, middle).await? <= time {
+            lower = middle;
+        } else {
+            upper = middle;
+        }
+    }
+
+    Ok(Some(lower))
+}
+
+// Find the earliest block, up to and including block `upper`, whose timestamp
+// is at or after `time`, or `upper + 1` if every block is before `time`.
+pub async fn fetch_block_at_or_after_time(
+    provider_url: &str,
+    cache: &BlockTimestamps,
+    time: u64,
+    upper: u64
+) -> Result<u64, RpcError> {
+    if time == 0 {
+        return Ok(0);
+    }
+
+    match fetch_block_at_or_before_time(provider_url, cache, time - 1, upper).await? {
+        Some(block) => Ok(block + 1),
+        None => Ok(0)
+    }
+}
+
+// Fetch the timestamp of the given block, from the cache if it's there.
+async fn fetch_block_timestamp(
+    provider: &Provider<Http>,
+    cache: &BlockTimestamps,
+    block: u64
+) -> Result<u64, RpcError> {
+    if let Some(timestamp) = cache.lock().unwrap().get(&block) {
+        return Ok(*timestamp);
+    }
+
+    let timestamp = provider.get_block(block).await?
+        .ok_or("block not found")?
+        .timestamp
+        .low_u64();
+
+    cache.lock().unwrap().insert(block, timestamp);
+    Ok(timestamp)
+}
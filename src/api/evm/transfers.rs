@@ -0,0 +1,211 @@
+use ethers::prelude::EthEvent;
+use ethers::abi::RawLog;
+use ethers::types::{ Address, U256, I256, H256, Sign, TxHash, Log, TransactionReceipt };
+use ethers::utils::format_units;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, EthEvent)]
+#[ethevent(name = "Transfer", abi = "Transfer(address,address,uint256)")]
+pub struct RawTransfer {
+    #[ethevent(indexed)]
+    pub from: Address,
+    #[ethevent(indexed)]
+    pub to: Address,
+    pub value: U256
+}
+
+// Emitted by WETH-style wrapped native tokens when wrapping.
+#[derive(Clone, Debug, EthEvent)]
+#[ethevent(name = "Deposit", abi = "Deposit(address,uint256)")]
+pub struct RawDeposit {
+    #[ethevent(indexed)]
+    pub dst: Address,
+    pub wad: U256
+}
+
+// Emitted by WETH-style wrapped native tokens when unwrapping.
+#[derive(Clone, Debug, EthEvent)]
+#[ethevent(name = "Withdrawal", abi = "Withdrawal(address,uint256)")]
+pub struct RawWithdrawal {
+    #[ethevent(indexed)]
+    pub src: Address,
+    pub wad: U256
+}
+
+// A movement of some amount of an ERC-20 token between two accounts.
+// Wrapping shows up as a transfer from the zero address,
+// and unwrapping as a transfer to the zero address.
+#[derive(Debug, Clone)]
+pub struct TokenTransfer {
+    pub tx_hash: TxHash,
+    pub token: Address,
+    pub from: Address,
+    pub to: Address,
+    pub amount: U256
+}
+
+// The topics of every log that `decode_transfer_log` understands.
+pub fn transfer_signatures() -> Vec<H256> {
+    vec![RawTransfer::signature(), RawDeposit::signature(), RawWithdrawal::signature()]
+}
+
+// Decode an ERC-20 Transfer, or a wrapped native token Deposit or Withdrawal,
+// or return `None` if the log isn't one (e.g. an ERC-721 Transfer,
+// which has the same signature but an indexed token id and no data).
+pub fn decode_transfer_log(log: &Log) -> Option<TokenTransfer> {
+    let raw_log = RawLog { topics: log.topics.clone(), data: log.data.to_vec() };
+
+    let (from, to, amount) = match log.topics.first() {
+        Some(topic) if *topic == RawTransfer::signature() => {
+            let transfer = RawTransfer::decode_log(&raw_log).ok()?;
+            (transfer.from, transfer.to, transfer.value)
+        },
+        Some(topic) if *topic == RawDeposit::signature() => {
+            let deposit = RawDeposit::decode_log(&raw_log).ok()?;
+            (Address::zero(), deposit.dst, deposit.wad)
+        },
+        Some(topic) if *topic == RawWithdrawal::signature() => {
+            let withdrawal = RawWithdrawal::decode_log(&raw_log).ok()?;
+            (withdrawal.src, Address::zero(), withdrawal.wad)
+        },
+        _ => return None
+    };
+
+    Some(TokenTransfer {
+        tx_hash: log.transaction_hash?,
+        token: log.address,
+        from,
+        to,
+        amount
+    })
+}
+
+// Decode every token transfer in the given transaction receipt.
+pub fn decode_receipt_transfers(receipt: &TransactionReceipt) -> Vec<TokenTransfer> {
+    receipt.logs.iter().filter_map(decode_transfer_log).collect()
+}
+
+// The net change in the given account's balance of every token
+// it sent or received over the given transfers, in raw token units.
+pub fn net_balance_changes(
+    transfers: &[TokenTransfer],
+    account: Address
+) -> HashMap<Address, I256> {
+    let mut flows: HashMap<Address, (U256, U256)> = HashMap::new(); // token -> (received, sent)
+
+    for transfer in transfers.iter().filter(|t| t.from != t.to) {
+        if transfer.to == account {
+            let flow = flows.entry(transfer.token).or_default();
+            flow.0 = flow.0.saturating_add(transfer.amount);
+        }
+
+        if transfer.from == account {
+            let flow = flows.entry(transfer.token).or_default();
+            flow.1 = flow.1.saturating_add(transfer.amount);
+        }
+    }
+
+    flows.into_iter()
+        .map(|(token, (received, sent))| {
+            let received = I256::try_from(received).unwrap_or(I256::MAX);
+            let sent = I256::try_from(sent).unwrap_or(I256::MAX);
+            (token, received.saturating_sub(sent))
+        })
+        .collect()
+}
+
+// The net amount of the given token that the given account received
+// over the given transfers, scaled down by the token's decimals.
+// Negative if the account sent more than it received.
+pub fn net_flow(
+    transfers: &[TokenTransfer],
+    token: Address,
+    account: Address,
+    decimals: u32
+) -> f64 {
+    match net_balance_changes(transfers, account).get(&token) {
+        Some(amount) => i256_to_f64(*amount, decimals),
+        None => 0f64
+    }
+}
+
+// Scale a signed raw token amount down by the token's decimals.
+pub fn i256_to_f64(amount: I256, decimals: u32) -> f64 {
+    let (sign, magnitude) = amount.into_sign_and_abs();
+    let magnitude = format_units(magnitude, decimals)
+        .unwrap_or("0.0".to_string())
+        .parse::<f64>()
+        .unwrap();
+
+    if sign == Sign::Negative { -magnitude } else { magnitude }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{ self, Token };
+    use ethers::types::Bytes;
+
+    const WETH: Address = Address::repeat_byte(0xee);
+
+    fn log(topics: Vec<H256>, data: Vec<Token>) -> Log {
+        serde_json::from_value(serde_json::json!({
+            "address": WETH,
+            "topics": topics,
+            "data": Bytes::from(abi::encode(&data)),
+            "transactionHash": TxHash::repeat_byte(1)
+        })).unwrap()
+    }
+
+    fn address(byte: u8) -> Address {
+        Address::repeat_byte(byte)
+    }
+
+    #[test]
+    fn transfers_deposits_and_withdrawals_are_decoded() {
+        let transfer = decode_transfer_log(&log(
+            vec![RawTransfer::signature(), address(1).into(), address(2).into()],
+            vec![Token::Uint(U256::from(100))])).unwrap();
+
+        assert_eq!((transfer.token, transfer.from, transfer.to, transfer.amount), (WETH, address(1), address(2), U256::from(100)));
+        assert_eq!(transfer.tx_hash, TxHash::repeat_byte(1));
+
+        let deposit = decode_transfer_log(&log(
+            vec![RawDeposit::signature(), address(1).into()],
+            vec![Token::Uint(U256::from(5))])).unwrap();
+
+        assert_eq!((deposit.from, deposit.to, deposit.amount), (Address::zero(), address(1), U256::from(5)));
+
+        let withdrawal = decode_transfer_log(&log(
+            vec![RawWithdrawal::signature(), address(1).into()],
+            vec![Token::Uint(U256::from(5))])).unwrap();
+
+        assert_eq!((withdrawal.from, withdrawal.to, withdrawal.amount), (address(1), Address::zero(), U256::from(5)));
+    }
+
+    #[test]
+    fn nft_transfers_are_ignored() {
+        // ERC-721 Transfers index the token id, so they have no data.
+        let nft_transfer = log(
+            vec![RawTransfer::signature(), address(1).into(), address(2).into(), H256::from_low_u64_be(7)],
+            vec![]);
+
+        assert!(decode_transfer_log(&nft_transfer).is_none());
+    }
+
+    #[test]
+    fn net_flows_are_signed() {
+        let transfer = |from: Address, to: Address, amount: u64| TokenTransfer {
+            tx_hash: TxHash::repeat_byte(1),
+            token: WETH,
+            from,
+            to,
+            amount: U256::from(amount) * U256::exp10(18)
+        };
+        let transfers = [transfer(address(1), address(2), 3), transfer(address(2), address(1), 1)];
+
+        assert_eq!(net_flow(&transfers, WETH, address(1), 18), -2.0);
+        assert_eq!(net_flow(&transfers, WETH, address(2), 18), 2.0);
+        assert_eq!(net_flow(&transfers, address(9), address(1), 18), 0.0);
+    }
+}
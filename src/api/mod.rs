@@ -3,6 +3,8 @@ pub mod models;
 pub mod db;
 pub mod evm;
 mod schema;
+#[cfg(test)]
+pub mod test_db;
 
 pub use routes::routes;
 pub use models::{ 
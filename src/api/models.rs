@@ -7,7 +7,18 @@ use crate::api::schema::{
     sandwiches, 
     frontrun_transactions, 
     lunchmeat_transactions, 
-    backrun_transactions };
+    backrun_transactions,
+    block_scans,
+    blocks,
+    exchanges,
+    failed_pairs,
+    multipool_sandwiches,
+    multipool_victim_transactions,
+    jit_attacks,
+    jit_swap_transactions,
+    arbitrages,
+    sandwich_token_profits,
+    swaps };
 
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
 #[table_name = "tokens"]
@@ -28,7 +39,8 @@ pub struct Pair {
     pub factory_address: String,
     pub pair_address: String,
     pub base_token_id: i32,
-    pub quote_token_id: i32
+    pub quote_token_id: i32,
+    pub fee_tier: Option<i32>
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
@@ -39,7 +51,9 @@ pub struct Range {
     pub lower_bound: i64,
     pub upper_bound: i64,
     pub scan_complete: bool,
-    pub scan_failed: bool
+    pub scan_failed: bool,
+    pub swaps_stored: bool,
+    pub detector_version: String
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
@@ -47,7 +61,13 @@ pub struct Range {
 pub struct Sandwich {
     pub sandwich_id: i64,
     pair_id: i32,
-    pub block_number: i64
+    pub block_number: i64,
+    pub attacker_address: Option<String>,
+    pub bribe: Option<f64>,
+    pub bribe_tx_hash: Option<String>,
+    pub block_id: Option<i64>,
+    pub detector_version: String,
+    pub superseded: bool
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
@@ -61,7 +81,11 @@ pub struct FrontrunTransaction {
     pub base_out: f64,
     pub quote_out: f64,
     pub gas: f64,
-    sandwich_id: i64
+    sandwich_id: i64,
+    pub gas_used: Option<i64>,
+    pub gas_burned: Option<f64>,
+    pub gas_tip: Option<f64>,
+    pub gas_l1_fee: Option<f64>
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
@@ -75,7 +99,12 @@ pub struct LunchmeatTransaction {
     pub base_out: f64,
     pub quote_out: f64,
     pub gas: f64,
-    sandwich_id: i64
+    sandwich_id: i64,
+    pub is_victim: bool,
+    pub gas_used: Option<i64>,
+    pub gas_burned: Option<f64>,
+    pub gas_tip: Option<f64>,
+    pub gas_l1_fee: Option<f64>
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
@@ -89,5 +118,157 @@ pub struct BackrunTransaction {
     pub base_out: f64,
     pub quote_out: f64,
     pub gas: f64,
-    sandwich_id: i64
-}
\ No newline at end of file
+    sandwich_id: i64,
+    pub gas_used: Option<i64>,
+    pub gas_burned: Option<f64>,
+    pub gas_tip: Option<f64>,
+    pub gas_l1_fee: Option<f64>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
+#[table_name = "block_scans"]
+pub struct BlockScan {
+    pub block_scan_id: i64,
+    pub blockchain_str_id: String,
+    pub block_number: i64,
+    pub scan_complete: bool,
+    pub scan_failed: bool
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
+#[table_name = "blocks"]
+pub struct Block {
+    pub block_id: i64,
+    pub blockchain_str_id: String,
+    pub block_number: i64,
+    pub block_hash: String,
+    pub miner: String,
+    pub block_timestamp: i64,
+    pub base_fee: Option<i64>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
+#[table_name = "exchanges"]
+pub struct DetectedExchange {
+    pub exchange_id: i32,
+    pub blockchain_str_id: String,
+    pub factory_address: String,
+    pub exchange_name: String,
+    pub exchange_kind: String,
+    pub verified: bool
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
+#[table_name = "failed_pairs"]
+pub struct FailedPair {
+    pub failed_pair_id: i32,
+    pub blockchain_str_id: String,
+    pub factory_address: String,
+    pub pair_address: String,
+    pub token0_address: String,
+    pub token1_address: String,
+    pub fee_tier: Option<i32>,
+    pub attempts: i32
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
+#[table_name = "multipool_sandwiches"]
+pub struct MultipoolSandwich {
+    pub multipool_sandwich_id: i64,
+    blockchain_str_id: String,
+    pub block_number: i64,
+    pub attacker_address: String,
+    pub frontrun_tx_hash: String,
+    pub frontrun_tx_index: i32,
+    pub backrun_tx_hash: String,
+    pub backrun_tx_index: i32
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
+#[table_name = "multipool_victim_transactions"]
+pub struct MultipoolVictimTransaction {
+    multipool_victim_id: i64,
+    pub tx_hash: String,
+    pub tx_index: i32,
+    multipool_sandwich_id: i64
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
+#[table_name = "jit_attacks"]
+pub struct JitAttack {
+    pub jit_attack_id: i64,
+    pair_id: i32,
+    pub block_number: i64,
+    pub owner_address: String,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: f64,
+    pub mint_tx_hash: String,
+    pub mint_tx_index: i32,
+    pub burn_tx_hash: String,
+    pub burn_tx_index: i32,
+    pub base_minted: f64,
+    pub quote_minted: f64,
+    pub base_burned: f64,
+    pub quote_burned: f64,
+    pub base_fees: f64,
+    pub quote_fees: f64
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
+#[table_name = "jit_swap_transactions"]
+pub struct JitSwapTransaction {
+    jit_swap_id: i64,
+    pub tx_hash: String,
+    pub tx_index: i32,
+    pub base_in: f64,
+    pub quote_in: f64,
+    pub base_out: f64,
+    pub quote_out: f64,
+    jit_attack_id: i64
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
+#[table_name = "arbitrages"]
+pub struct Arbitrage {
+    pub arbitrage_id: i64,
+    blockchain_str_id: String,
+    pub block_number: i64,
+    pub tx_hash: String,
+    pub tx_index: i32,
+    pub arbitrage_kind: String,
+    pub searcher_address: String,
+    pub profit_token_id: i32,
+    pub profit: f64,
+    pub target_tx_hash: Option<String>,
+    pub target_tx_index: Option<i32>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
+#[table_name = "sandwich_token_profits"]
+pub struct SandwichTokenProfit {
+    sandwich_id: i64,
+    pub token_address: String,
+    pub token_id: Option<i32>,
+    pub amount: f64
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
+#[table_name = "swaps"]
+pub struct Swap {
+    pub swap_id: i64,
+    pub pair_id: i32,
+    pub block_number: i64,
+    pub tx_hash: String,
+    pub tx_index: i32,
+    pub log_index: i32,
+    pub sender: String,
+    pub recipient: String,
+    pub amount0_in: String,
+    pub amount1_in: String,
+    pub amount0_out: String,
+    pub amount1_out: String,
+    pub sqrt_price_x96: Option<String>,
+    pub liquidity: Option<String>,
+    pub tick: Option<i32>
+}
@@ -0,0 +1,158 @@
+use actix_web::{ get, web };
+use serde::{ Serialize, Deserialize };
+use diesel::result::Error as DbError;
+use crate::state::AppState;
+use super::super::{ evm, db };
+use super::block_scan::{ BlockScanMetadata, is_block_confirmed };
+
+#[derive(Debug, Deserialize)]
+struct ArbitragesRequest {
+    blockchain: String,
+    block: u64
+}
+
+#[derive(Debug, Serialize)]
+struct ArbitragesResponse {
+    arbitrages: Option<Vec<ArbitrageData>>,
+    scan_metadata: Option<BlockScanMetadata>,
+    error_message: String
+}
+
+impl ArbitragesResponse {
+    fn as_error(msg: String) -> Self {
+        Self {
+            arbitrages: None,
+            scan_metadata: None,
+            error_message: msg
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ArbitrageData {
+    block_number: i64,
+    kind: String, // "atomic" or "backrun"
+    hash: String,
+    index: usize,
+    searcher: String,
+    profit_symbol: String,
+    profit_token: String,
+    profit: f64,
+    target: Option<ArbitrageTargetData>, // the swap that was backrun, if any
+    pairs: Vec<String> // the address of every pair traded, in hop order
+}
+
+#[derive(Debug, Serialize)]
+struct ArbitrageTargetData {
+    hash: String,
+    index: usize
+}
+
+#[get("/arbitrages")]
+async fn fetch_arbitrages(
+    data: web::Data<AppState>,
+    info: web::Query<ArbitragesRequest>
+) -> web::Json<ArbitragesResponse> {
+    // Standardize the incoming data.
+    let blockchain_id = info.blockchain.to_lowercase();
+    let block = into_i64!(info.block, ArbitragesResponse);
+
+    // First get the blockchain state data, or return an error.
+    let blockchain = match data.blockchains.get(&blockchain_id) {
+        Some(blockchain) => blockchain,
+        None => return response_error!("blockchain not supported", ArbitragesResponse)
+    };
+
+    // Refuse to serve a blockchain whose provider is on the wrong chain.
+    if !blockchain.is_provider_verified() {
+        return response_error!("blockchain provider not verified", ArbitragesResponse);
+    }
+
+    // Refuse to scan a block that could still be reorged out.
+    match is_block_confirmed(blockchain, info.block).await {
+        Ok(true) => (),
+        Ok(false) => return response_error!("block not confirmed", ArbitragesResponse),
+        Err(_) => return response_error!("provider error", ArbitragesResponse)
+    }
+
+    // Get a database connection, and return an error
+    // if a connection cannot be established.
+    let db_connection = get_db_connection!(data, ArbitragesResponse);
+
+    // Spawn a new, non-blocking thread to fetch the block scan, if it exists,
+    // or to create it and start scanning the block in the background.
+    let app_state = data.clone();
+    let block_scan_thread_result = web::block(move || {
+        evm::block_scanner::fetch_or_start_block_scan(app_state, &db_connection, blockchain_id, block)
+    }).await;
+
+    let block_scan = match thread_unwrap!(block_scan_thread_result, ArbitragesResponse) {
+        Ok(block_scan) => block_scan,
+        Err(_) => return response_error!("block scan database error", ArbitragesResponse)
+    };
+
+    // If the block hasn't been fully scanned yet (or the scan failed),
+    // return the block scan metadata so the user can check back later.
+    if !block_scan.scan_complete {
+        let error_message = if block_scan.scan_failed { "scan failed" } else { "" };
+
+        return web::Json(ArbitragesResponse {
+            arbitrages: None,
+            scan_metadata: Some(BlockScanMetadata::from(block_scan)),
+            error_message: error_message.to_string()
+        });
+    }
+
+    // Spawn a new, non-blocking thread to fetch the block's arbitrages.
+    let db_connection = get_db_connection!(data, ArbitragesResponse);
+    let blockchain_id = info.blockchain.to_lowercase();
+    let thread_result = web::block(move || {
+        fetch_db_arbitrage_data(&db_connection, &blockchain_id, block)
+    }).await;
+
+    let arbitrages = match thread_unwrap!(thread_result, ArbitragesResponse) {
+        Ok(arbitrages) => arbitrages,
+        Err(_) => return response_error!("fetch arbitrages database error", ArbitragesResponse)
+    };
+
+    web::Json(ArbitragesResponse {
+        arbitrages: if arbitrages.is_empty() { None } else { Some(arbitrages) },
+        scan_metadata: Some(BlockScanMetadata::from(block_scan)),
+        error_message: "".to_string()
+    })
+}
+
+// Fetch the arbitrages in the given block, along with
+// their profit tokens and traded pairs, as ArbitrageData.
+fn fetch_db_arbitrage_data(
+    db_connection: &db::DbConnection,
+    blockchain_id: &str,
+    block: i64
+) -> Result<Vec<ArbitrageData>, DbError> {
+    let mut arbitrages = Vec::new();
+
+    for db_arbitrage in db::fetch_arbitrages_by_params(db_connection, blockchain_id, block)? {
+        let token = db::fetch_token_by_id(db_connection, db_arbitrage.profit_token_id)?;
+        let pairs = db::fetch_arbitrage_pair_addresses(db_connection, db_arbitrage.arbitrage_id)?;
+
+        let target = match (db_arbitrage.target_tx_hash, db_arbitrage.target_tx_index) {
+            (Some(hash), Some(index)) => Some(ArbitrageTargetData { hash, index: index as usize }),
+            _ => None
+        };
+
+        arbitrages.push(ArbitrageData {
+            block_number: db_arbitrage.block_number,
+            kind: db_arbitrage.arbitrage_kind,
+            hash: db_arbitrage.tx_hash,
+            index: db_arbitrage.tx_index as usize,
+            searcher: db_arbitrage.searcher_address,
+            profit_symbol: token.token_symbol,
+            profit_token: token.token_address,
+            profit: db_arbitrage.profit,
+            target,
+            pairs
+        });
+    }
+
+    Ok(arbitrages)
+}
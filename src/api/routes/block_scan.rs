@@ -0,0 +1,34 @@
+use serde::Serialize;
+use super::super::{ evm, models::BlockScan };
+use crate::state::Blockchain;
+use std::convert::From;
+
+// The state of a block scan, returned by every route
+// whose results come from scanning a whole block.
+#[derive(Debug, Serialize)]
+pub struct BlockScanMetadata {
+    block_number: u64,
+    complete: bool,
+    failed: bool
+}
+
+impl From<BlockScan> for BlockScanMetadata {
+    fn from(block_scan: BlockScan) -> Self {
+        Self {
+            block_number: block_scan.block_number as u64,
+            complete: block_scan.scan_complete,
+            failed: block_scan.scan_failed
+        }
+    }
+}
+
+// Whether the given block is at least the blockchain's confirmations
+// behind its latest block. Only confirmed blocks are scanned, since
+// block scans (unlike ranges) aren't reconciled with the provider.
+pub async fn is_block_confirmed(
+    blockchain: &Blockchain,
+    block: u64
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let latest = evm::fetch_latest_block_number(&blockchain.provider_url).await?;
+    Ok(block <= latest.saturating_sub(blockchain.scanner_params.confirmations))
+}
@@ -0,0 +1,126 @@
+use actix_web::{ get, web };
+use serde::{ Serialize, Deserialize };
+use diesel::result::Error as DbError;
+use crate::state::AppState;
+use super::super::db;
+use std::collections::{ HashMap, HashSet };
+
+#[derive(Debug, Deserialize)]
+struct BuildersRequest {
+    blockchain: String,
+    from_block: Option<u64>,
+    to_block: Option<u64>
+}
+
+#[derive(Debug, Serialize)]
+struct BuildersResponse {
+    builders: Option<Vec<BuilderData>>,
+    total_sandwiches: usize,
+    error_message: String
+}
+
+impl BuildersResponse {
+    fn as_error(msg: String) -> Self {
+        Self {
+            builders: None,
+            total_sandwiches: 0,
+            error_message: msg
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct BuilderData {
+    miner: String, // the fee recipient of the builder's blocks
+    sandwiches: usize,
+    blocks: usize, // the number of its blocks that hold at least one sandwich
+    share: f64, // its fraction of all the sandwiches
+    bribes: f64 // the total bribes it was paid by sandwich attackers
+}
+
+// Builder stats only cover sandwiches already found by /sandwiches scans
+// (and scanned after blocks were recorded), so no new scans are started here.
+#[get("/builders")]
+async fn fetch_builders(
+    data: web::Data<AppState>,
+    info: web::Query<BuildersRequest>
+) -> web::Json<BuildersResponse> {
+    // Standardize the incoming data.
+    let blockchain_id = info.blockchain.to_lowercase();
+    let from_block = match info.from_block {
+        Some(block) => Some(into_i64!(block, BuildersResponse)),
+        None => None
+    };
+    let to_block = match info.to_block {
+        Some(block) => Some(into_i64!(block, BuildersResponse)),
+        None => None
+    };
+
+    // First get the blockchain state data, or return an error.
+    let blockchain = match data.blockchains.get(&blockchain_id) {
+        Some(blockchain) => blockchain,
+        None => return response_error!("blockchain not supported", BuildersResponse)
+    };
+
+    // Refuse to serve a blockchain whose provider is on the wrong chain.
+    if !blockchain.is_provider_verified() {
+        return response_error!("blockchain provider not verified", BuildersResponse);
+    }
+
+    // Get a database connection, and return an error
+    // if a connection cannot be established.
+    let db_connection = get_db_connection!(data, BuildersResponse);
+
+    // Spawn a new, non-blocking thread to tally the sandwiches by builder.
+    let thread_result = web::block(move || {
+        fetch_db_builder_data(&db_connection, &blockchain_id, from_block, to_block)
+    }).await;
+
+    let (builders, total_sandwiches) = match thread_unwrap!(thread_result, BuildersResponse) {
+        Ok(result) => result,
+        Err(_) => return response_error!("fetch builders database error", BuildersResponse)
+    };
+
+    web::Json(BuildersResponse {
+        builders: if builders.is_empty() { None } else { Some(builders) },
+        total_sandwiches,
+        error_message: "".to_string()
+    })
+}
+
+// Tally the sandwiches in the given block range by the fee recipient of
+// their block, as BuilderData sorted by the number of sandwiches included,
+// along with the total number of sandwiches.
+fn fetch_db_builder_data(
+    db_connection: &db::DbConnection,
+    blockchain_id: &str,
+    from_block: Option<i64>,
+    to_block: Option<i64>
+) -> Result<(Vec<BuilderData>, usize), DbError> {
+    let sandwiches = db::fetch_sandwich_miners(db_connection, blockchain_id, from_block, to_block)?;
+    let total_sandwiches = sandwiches.len();
+
+    // miner -> (sandwiches, block ids, bribes)
+    let mut tallies: HashMap<String, (usize, HashSet<i64>, f64)> = HashMap::new();
+
+    for (miner, block_id, bribe) in sandwiches {
+        let tally = tallies.entry(miner).or_default();
+        tally.0 += 1;
+        tally.1.insert(block_id);
+        tally.2 += bribe.unwrap_or(0f64);
+    }
+
+    let mut builders = tallies.into_iter()
+        .map(|(miner, (sandwiches, blocks, bribes))| BuilderData {
+            miner,
+            sandwiches,
+            blocks: blocks.len(),
+            share: sandwiches as f64 / total_sandwiches as f64,
+            bribes
+        })
+        .collect::<Vec<BuilderData>>();
+
+    builders.sort_by(|a, b| b.sandwiches.cmp(&a.sandwiches).then(a.miner.cmp(&b.miner)));
+
+    Ok((builders, total_sandwiches))
+}
@@ -0,0 +1,244 @@
+use actix_web::{ get, web };
+use serde::{ Serialize, Deserialize };
+use diesel::NotFound;
+use diesel::result::Error as DbError;
+use crate::state::{ AppState, Exchange };
+use super::super::{ evm, db };
+use super::super::models::{ Range, JitSwapTransaction };
+use std::convert::From;
+
+#[derive(Debug, Deserialize)]
+struct JitRequest {
+    blockchain: String,
+    pair: String,
+    before: Option<u64>
+}
+
+#[derive(Debug, Serialize)]
+struct JitResponse {
+    attacks: Option<Vec<JitAttackData>>,
+    scan_metadata: Option<ScanMetadata>,
+    error_message: String
+}
+
+impl JitResponse {
+    fn as_error(msg: String) -> Self {
+        Self {
+            attacks: None,
+            scan_metadata: None,
+            error_message: msg
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ScanMetadata {
+    lower_bound: u64,
+    upper_bound: u64,
+    complete: bool,
+    failed: bool
+}
+
+impl From<Range> for ScanMetadata {
+    fn from(range: Range) -> Self {
+        Self {
+            lower_bound: range.lower_bound as u64,
+            upper_bound: range.upper_bound as u64,
+            complete: range.scan_complete,
+            failed: range.scan_failed
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct JitAttackData {
+    block_number: i64,
+    owner: String,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: f64,
+    mint: PositionTransactionData,
+    swaps: Vec<JitSwapData>,
+    burn: PositionTransactionData,
+    base_fees: f64,
+    quote_fees: f64
+}
+
+#[derive(Debug, Serialize)]
+struct PositionTransactionData {
+    hash: String,
+    index: usize,
+    base_amount: f64,
+    quote_amount: f64
+}
+
+#[derive(Debug, Serialize)]
+struct JitSwapData {
+    hash: String,
+    index: usize,
+    base_in: f64,
+    quote_in: f64,
+    base_out: f64,
+    quote_out: f64
+}
+
+impl From<&JitSwapTransaction> for JitSwapData {
+    fn from(tx: &JitSwapTransaction) -> Self {
+        Self {
+            hash: tx.tx_hash.clone(),
+            index: tx.tx_index as usize,
+            base_in: tx.base_in,
+            quote_in: tx.quote_in,
+            base_out: tx.base_out,
+            quote_out: tx.quote_out
+        }
+    }
+}
+
+// JIT liquidity attacks are found by the same range scans that find
+// sandwiches, so this only serves ranges already started via /sandwiches.
+#[get("/jit")]
+async fn fetch_jit_attacks(
+    data: web::Data<AppState>,
+    info: web::Query<JitRequest>
+) -> web::Json<JitResponse> {
+    // Standardize the incoming data.
+    let blockchain_id = info.blockchain.to_lowercase();
+    let pair_address = info.pair.to_lowercase();
+
+    // First get the blockchain state data, or return an error.
+    let blockchain = match data.blockchains.get(&blockchain_id) {
+        Some(blockchain) => blockchain,
+        None => return response_error!("blockchain not supported", JitResponse)
+    };
+
+    // Refuse to serve a blockchain whose provider is on the wrong chain.
+    if !blockchain.is_provider_verified() {
+        return response_error!("blockchain provider not verified", JitResponse);
+    }
+
+    // Get a database connection, and return an error
+    // if a connection cannot be established.
+    let db_connection = get_db_connection!(data, JitResponse);
+
+    // Spawn a new, non-blocking thread to fetch
+    // the pair from the database, if it exists.
+    let pair_thread_result = web::block(move || {
+        db::fetch_pair_by_params(&db_connection, &blockchain_id, &pair_address)
+    }).await;
+
+    // Unpack the database result.
+    let pair = match thread_unwrap!(pair_thread_result, JitResponse) {
+        Ok(pair) => pair,
+        Err(NotFound) => return response_error!("pair does not exist", JitResponse),
+        Err(_) => return response_error!("database error", JitResponse)
+    };
+
+    // Only V3 pools have concentrated liquidity positions to attack with.
+    match blockchain.exchange(&pair.factory_address) {
+        Some(Exchange::V3 { name: _ }) => (),
+        Some(_) => return response_error!("exchange does not support JIT liquidity", JitResponse),
+        None => return response_error!("exchange not supported", JitResponse)
+    };
+
+    // Determine the most recent block to return attacks from.
+    let before = match info.before {
+        Some(block_number) => block_number,
+        None => {
+            match evm::fetch_latest_block_number(&blockchain.provider_url).await {
+                Ok(block_number) => block_number,
+                _ => return response_error!("provider error", JitResponse)
+            }
+        }
+    };
+
+    // Spawn a new, non-blocking thread to fetch the encompassing range.
+    let db_connection = get_db_connection!(data, JitResponse);
+    let before_block = into_i64!(before, JitResponse);
+    let pair_id = pair.pair_id;
+
+    let in_range_thread_result = web::block(move || {
+        db::find_encompassing_range(&db_connection, pair_id, before_block)
+    }).await;
+
+    let in_range = match thread_unwrap!(in_range_thread_result, JitResponse) {
+        Ok(range) => range,
+        Err(NotFound) => return response_error!("range not scanned", JitResponse),
+        Err(_) => return response_error!("range search database error", JitResponse)
+    };
+
+    if in_range.scan_failed {
+        return web::Json(JitResponse {
+            attacks: None,
+            scan_metadata: Some(ScanMetadata::from(in_range)),
+            error_message: "scan failed".to_string()
+        });
+    }
+
+    // Return up to `max_blocks` worth of attacks from the range,
+    // regardless of whether its scan has completed or not.
+    let db_connection = get_db_connection!(data, JitResponse);
+    let max_blocks = into_i64!(blockchain.scanner_params.max_blocks_per_request, JitResponse);
+
+    let after_block = if before_block - max_blocks >= in_range.lower_bound {
+        before_block - max_blocks
+    } else {
+        in_range.lower_bound
+    };
+
+    let thread_result = web::block(move || {
+        fetch_db_jit_attack_data(&db_connection, pair_id, after_block, before_block)
+    }).await;
+
+    let attacks = match thread_unwrap!(thread_result, JitResponse) {
+        Ok(attacks) => attacks,
+        Err(_) => return response_error!("fetch attacks database error", JitResponse)
+    };
+
+    web::Json(JitResponse {
+        attacks: if attacks.is_empty() { None } else { Some(attacks) },
+        scan_metadata: Some(ScanMetadata::from(in_range)),
+        error_message: "".to_string()
+    })
+}
+
+// Fetch the JIT liquidity attacks on the given pair between
+// the given blocks, along with the swaps they targeted.
+fn fetch_db_jit_attack_data(
+    db_connection: &db::DbConnection,
+    pair_id: i32,
+    min_block: i64,
+    max_block: i64
+) -> Result<Vec<JitAttackData>, DbError> {
+    let mut attacks = Vec::new();
+
+    for db_attack in db::fetch_jit_attacks_by_params(db_connection, pair_id, min_block, max_block)? {
+        let swaps = db::fetch_jit_swap_transactions_by_attack_id(
+            db_connection, db_attack.jit_attack_id)?;
+
+        attacks.push(JitAttackData {
+            block_number: db_attack.block_number,
+            owner: db_attack.owner_address,
+            tick_lower: db_attack.tick_lower,
+            tick_upper: db_attack.tick_upper,
+            liquidity: db_attack.liquidity,
+            mint: PositionTransactionData {
+                hash: db_attack.mint_tx_hash,
+                index: db_attack.mint_tx_index as usize,
+                base_amount: db_attack.base_minted,
+                quote_amount: db_attack.quote_minted
+            },
+            swaps: swaps.iter().map(JitSwapData::from).collect(),
+            burn: PositionTransactionData {
+                hash: db_attack.burn_tx_hash,
+                index: db_attack.burn_tx_index as usize,
+                base_amount: db_attack.base_burned,
+                quote_amount: db_attack.quote_burned
+            },
+            base_fees: db_attack.base_fees,
+            quote_fees: db_attack.quote_fees
+        });
+    }
+
+    Ok(attacks)
+}
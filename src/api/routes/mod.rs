@@ -2,6 +2,12 @@
 pub mod utils;
 mod pair;
 mod sandwiches;
+mod block_scan;
+mod multipool;
+mod jit;
+mod arbitrages;
+mod builders;
+mod search;
 
 // Package up all the api routes into a ServiceConfig
 // that can be registered on startup in main.rs.
@@ -9,5 +15,11 @@ pub fn routes(cfg: &mut actix_web::web::ServiceConfig) {
     cfg.service(
         actix_web::web::scope("/api")
         .service(pair::fetch_pair)
-        .service(sandwiches::fetch_sandwiches));
+        .service(pair::resolve_pair)
+        .service(sandwiches::fetch_sandwiches)
+        .service(multipool::fetch_multipool_sandwiches)
+        .service(jit::fetch_jit_attacks)
+        .service(arbitrages::fetch_arbitrages)
+        .service(builders::fetch_builders)
+        .service(search::search_pairs));
 }
\ No newline at end of file
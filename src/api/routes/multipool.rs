@@ -0,0 +1,158 @@
+use actix_web::{ get, web };
+use serde::{ Serialize, Deserialize };
+use diesel::result::Error as DbError;
+use crate::state::AppState;
+use super::super::{ evm, db };
+use super::block_scan::{ BlockScanMetadata, is_block_confirmed };
+
+#[derive(Debug, Deserialize)]
+struct MultipoolRequest {
+    blockchain: String,
+    block: u64
+}
+
+#[derive(Debug, Serialize)]
+struct MultipoolResponse {
+    sandwiches: Option<Vec<MultipoolSandwichData>>,
+    scan_metadata: Option<BlockScanMetadata>,
+    error_message: String
+}
+
+impl MultipoolResponse {
+    fn as_error(msg: String) -> Self {
+        Self {
+            sandwiches: None,
+            scan_metadata: None,
+            error_message: msg
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MultipoolSandwichData {
+    block_number: i64,
+    attacker: String,
+    frontrun: MultipoolTransactionData,
+    victims: Vec<MultipoolTransactionData>,
+    backrun: MultipoolTransactionData,
+    pairs: Vec<String> // the address of every pair the sandwich touched
+}
+
+#[derive(Debug, Serialize)]
+struct MultipoolTransactionData {
+    hash: String,
+    index: usize
+}
+
+#[get("/multipool")]
+async fn fetch_multipool_sandwiches(
+    data: web::Data<AppState>,
+    info: web::Query<MultipoolRequest>
+) -> web::Json<MultipoolResponse> {
+    // Standardize the incoming data.
+    let blockchain_id = info.blockchain.to_lowercase();
+    let block = into_i64!(info.block, MultipoolResponse);
+
+    // First get the blockchain state data, or return an error.
+    let blockchain = match data.blockchains.get(&blockchain_id) {
+        Some(blockchain) => blockchain,
+        None => return response_error!("blockchain not supported", MultipoolResponse)
+    };
+
+    // Refuse to serve a blockchain whose provider is on the wrong chain.
+    if !blockchain.is_provider_verified() {
+        return response_error!("blockchain provider not verified", MultipoolResponse);
+    }
+
+    // Refuse to scan a block that could still be reorged out.
+    match is_block_confirmed(blockchain, info.block).await {
+        Ok(true) => (),
+        Ok(false) => return response_error!("block not confirmed", MultipoolResponse),
+        Err(_) => return response_error!("provider error", MultipoolResponse)
+    }
+
+    // Get a database connection, and return an error
+    // if a connection cannot be established.
+    let db_connection = get_db_connection!(data, MultipoolResponse);
+
+    // Spawn a new, non-blocking thread to fetch the block scan, if it exists,
+    // or to create it and start scanning the block in the background.
+    let app_state = data.clone();
+    let block_scan_thread_result = web::block(move || {
+        evm::block_scanner::fetch_or_start_block_scan(app_state, &db_connection, blockchain_id, block)
+    }).await;
+
+    let block_scan = match thread_unwrap!(block_scan_thread_result, MultipoolResponse) {
+        Ok(block_scan) => block_scan,
+        Err(_) => return response_error!("block scan database error", MultipoolResponse)
+    };
+
+    // If the block hasn't been fully scanned yet (or the scan failed),
+    // return the block scan metadata so the user can check back later.
+    if !block_scan.scan_complete {
+        let error_message = if block_scan.scan_failed { "scan failed" } else { "" };
+
+        return web::Json(MultipoolResponse {
+            sandwiches: None,
+            scan_metadata: Some(BlockScanMetadata::from(block_scan)),
+            error_message: error_message.to_string()
+        });
+    }
+
+    // Spawn a new, non-blocking thread to fetch the block's multi-pool sandwiches.
+    let db_connection = get_db_connection!(data, MultipoolResponse);
+    let blockchain_id = info.blockchain.to_lowercase();
+    let thread_result = web::block(move || {
+        fetch_db_multipool_sandwich_data(&db_connection, &blockchain_id, block)
+    }).await;
+
+    let sandwiches = match thread_unwrap!(thread_result, MultipoolResponse) {
+        Ok(sandwiches) => sandwiches,
+        Err(_) => return response_error!("fetch sandwiches database error", MultipoolResponse)
+    };
+
+    web::Json(MultipoolResponse {
+        sandwiches: if sandwiches.is_empty() { None } else { Some(sandwiches) },
+        scan_metadata: Some(BlockScanMetadata::from(block_scan)),
+        error_message: "".to_string()
+    })
+}
+
+// Fetch the multi-pool sandwiches in the given block, along with
+// their victims and affected pairs, as MultipoolSandwichData.
+fn fetch_db_multipool_sandwich_data(
+    db_connection: &db::DbConnection,
+    blockchain_id: &str,
+    block: i64
+) -> Result<Vec<MultipoolSandwichData>, DbError> {
+    let mut sandwiches = Vec::new();
+
+    for db_sandwich in db::fetch_multipool_sandwiches_by_params(db_connection, blockchain_id, block)? {
+        let victims = db::fetch_multipool_victim_transactions_by_sandwich_id(
+            db_connection, db_sandwich.multipool_sandwich_id)?;
+        let pairs = db::fetch_multipool_sandwich_pair_addresses(
+            db_connection, db_sandwich.multipool_sandwich_id)?;
+
+        sandwiches.push(MultipoolSandwichData {
+            block_number: db_sandwich.block_number,
+            attacker: db_sandwich.attacker_address,
+            frontrun: MultipoolTransactionData {
+                hash: db_sandwich.frontrun_tx_hash,
+                index: db_sandwich.frontrun_tx_index as usize
+            },
+            victims: victims.into_iter()
+                .map(|victim| MultipoolTransactionData {
+                    hash: victim.tx_hash,
+                    index: victim.tx_index as usize
+                })
+                .collect(),
+            backrun: MultipoolTransactionData {
+                hash: db_sandwich.backrun_tx_hash,
+                index: db_sandwich.backrun_tx_index as usize
+            },
+            pairs
+        });
+    }
+
+    Ok(sandwiches)
+}
@@ -1,6 +1,7 @@
-use actix_web::{ get, web };
+use actix_web::{ get, web, HttpRequest };
 use serde::{ Serialize, Deserialize };
 use diesel::NotFound;
+use ethers::types::Address;
 use crate::state::AppState;
 use super::super::{ evm, db };
 
@@ -32,7 +33,39 @@ impl PairResponse {
 #[derive(Debug, Serialize)]
 struct PairMetadata {
     address: String,
-    exchange_name: String
+    exchange_name: String,
+    exchange_verified: bool // false for exchanges only detected by probing their pairs
+}
+
+#[derive(Debug, Deserialize)]
+struct ResolveRequest {
+    blockchain: String,
+    token_a: String,
+    token_b: String,
+    fee: Option<u32> // if set, only V3 pools with this fee tier (one of `V3_FEE_TIERS`) are returned
+}
+
+#[derive(Debug, Serialize)]
+struct ResolveResponse {
+    pairs: Option<Vec<ResolvedPair>>,
+    error_message: String
+}
+
+impl ResolveResponse {
+    fn as_error(msg: String) -> Self {
+        Self {
+            pairs: None,
+            error_message: msg
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ResolvedPair {
+    address: String,
+    exchange_name: String,
+    fee_tier: Option<u32>, // unset for V2 pairs
+    inspect_url: String
 }
 
 #[derive(Debug, Serialize)]
@@ -62,6 +95,11 @@ async fn fetch_pair(
         None => return response_error!("blockchain not supported", PairResponse)
     };
 
+    // Refuse to serve a blockchain whose provider is on the wrong chain.
+    if !blockchain.is_provider_verified() {
+        return response_error!("blockchain provider not verified", PairResponse);
+    }
+
     // Spawn a new, non-blocking thread to fetch
     // the pair, base and quote from the database.
     let metadata_thread_result = web::block(move || {
@@ -107,7 +145,7 @@ async fn fetch_pair(
         };
 
         // Get the exchange name.
-        let exchange_name = match blockchain.exchanges.get(&pair.factory_address) {
+        let exchange_name = match blockchain.exchange(&pair.factory_address) {
             Some(exchange) => exchange.name().to_string(),
             None => return response_error!("exchange not found", PairResponse)
         };
@@ -115,6 +153,7 @@ async fn fetch_pair(
         // Construct and return the PairResponse JSON object.
         web::Json(PairResponse {
             pair: Some(PairMetadata {
+                exchange_verified: blockchain.is_exchange_verified(&pair.factory_address),
                 address: pair.pair_address,
                 exchange_name: exchange_name
             }),
@@ -141,7 +180,7 @@ async fn fetch_pair(
         let metadata = match evm::fetch_pair_metadata(
             &blockchain.provider_url,
             &pair_address,
-            &blockchain.data_aggregator_address
+            blockchain.data_aggregator_address.as_deref()
         ).await {
             Ok(metadata) => metadata,
             _ => return response_error!("provider error", PairResponse)
@@ -152,9 +191,44 @@ async fn fetch_pair(
         let base_address = format!("0x{}", metadata.base_address.to_lowercase());
         let quote_address = format!("0x{}", metadata.quote_address.to_lowercase());
 
-        // Check for exchange state data, or return an error.
-        let exchange_name = match blockchain.exchanges.get(&factory_address) {
+        // Check for exchange state data or, if the blockchain allows unverified
+        // exchanges, probe the pair for a known interface and register its
+        // factory as a detected exchange. Otherwise return an error.
+        let exchange_name = match blockchain.exchange(&factory_address) {
             Some(exchange) => exchange.name().to_string(),
+            None if blockchain.allow_unverified_exchanges => {
+                let interface = match evm::probe::probe_pool_interface(
+                    &blockchain.provider_url, &pair_address
+                ).await {
+                    Ok(Some(interface)) => interface,
+                    Ok(None) => return response_error!("exchange not supported", PairResponse),
+                    _ => return response_error!("provider error", PairResponse)
+                };
+
+                let db_connection = get_db_connection!(data, PairResponse);
+                let blockchain_id = info.blockchain.to_lowercase();
+                let factory_address = factory_address.clone();
+                let exchange_name = format!(
+                    "{} fork ({})", interface.kind().to_uppercase(), &factory_address[..10]);
+
+                let register_thread_result = web::block(move || {
+                    db::fetch_or_insert_detected_exchange(
+                        &db_connection,
+                        &blockchain_id,
+                        &factory_address,
+                        &exchange_name,
+                        interface.kind())
+                }).await;
+
+                match thread_unwrap!(register_thread_result, PairResponse) {
+                    Ok(detected) => {
+                        let exchange_name = detected.exchange_name.clone();
+                        blockchain.register_exchange(detected);
+                        exchange_name
+                    },
+                    Err(_) => return response_error!("database write error", PairResponse)
+                }
+            },
             None => return response_error!("exchange not supported", PairResponse)
         };
 
@@ -165,7 +239,8 @@ async fn fetch_pair(
         let pair_response = web::Json(PairResponse {
             pair: Some(PairMetadata {
                 address: pair_address.clone(),
-                exchange_name: exchange_name
+                exchange_name: exchange_name,
+                exchange_verified: blockchain.is_exchange_verified(&factory_address)
             }),
             base: Some(TokenMetadata {
                 address: base_address.clone(),
@@ -250,7 +325,8 @@ async fn fetch_pair(
                 &factory_address,
                 &pair_address,
                 base_id,
-                quote_id) {
+                quote_id,
+                None) {
                 
                 Err(e) => return Err(e),
                 _ => return Ok(())
@@ -264,4 +340,67 @@ async fn fetch_pair(
             response_error!("database write error", PairResponse)
         }
     }
-}
\ No newline at end of file
+}
+
+// Find every pair (or pool) of the two given tokens, by asking each supported
+// exchange's factory for it (see `evm::discovery::fetch_factory_pairs`),
+// and link each one to its inspect page.
+#[get("/pair/resolve")]
+async fn resolve_pair(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    info: web::Query<ResolveRequest>
+) -> web::Json<ResolveResponse> {
+    // Standardize the incoming data.
+    let blockchain_id = info.blockchain.to_lowercase();
+
+    let (token_a, token_b) = match (info.token_a.parse::<Address>(), info.token_b.parse::<Address>()) {
+        (Ok(token_a), Ok(token_b)) if token_a != token_b => (token_a, token_b),
+        (Ok(_), Ok(_)) => return response_error!("tokens must differ", ResolveResponse),
+        _ => return response_error!("invalid token address", ResolveResponse)
+    };
+
+    if info.fee.is_some_and(|fee| !evm::discovery::V3_FEE_TIERS.contains(&fee)) {
+        return response_error!("invalid fee tier", ResolveResponse);
+    }
+
+    // First get the blockchain state data, or return an error.
+    let blockchain = match data.blockchains.get(&blockchain_id) {
+        Some(blockchain) => blockchain,
+        None => return response_error!("blockchain not supported", ResolveResponse)
+    };
+
+    // Refuse to serve a blockchain whose provider is on the wrong chain.
+    if !blockchain.is_provider_verified() {
+        return response_error!("blockchain provider not verified", ResolveResponse);
+    }
+
+    let exchanges = blockchain.supported_exchanges();
+
+    let factory_pairs = match evm::discovery::fetch_factory_pairs(
+        &blockchain.provider_url, &exchanges, token_a, token_b, info.fee
+    ).await {
+        Ok(factory_pairs) => factory_pairs,
+        _ => return response_error!("provider error", ResolveResponse)
+    };
+
+    let inspect_url = req.url_for_static("inspect_pair").unwrap().to_string();
+
+    let pairs = factory_pairs.into_iter()
+        .map(|factory_pair| {
+            let address = format!("{:?}", factory_pair.pair);
+
+            ResolvedPair {
+                inspect_url: format!("{inspect_url}?blockchain={blockchain_id}&pair={address}"),
+                exchange_name: exchanges[&factory_pair.factory_address].name().to_string(),
+                fee_tier: factory_pair.fee_tier,
+                address
+            }
+        })
+        .collect::<Vec<ResolvedPair>>();
+
+    web::Json(ResolveResponse {
+        pairs: if pairs.is_empty() { None } else { Some(pairs) },
+        error_message: "".to_string()
+    })
+}
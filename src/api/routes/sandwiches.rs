@@ -11,7 +11,13 @@ use std::convert::From;
 struct SandwichesRequest {
     blockchain: String,
     pair: String,
-    before: Option<u64>
+    before: Option<u64>,
+    // Dates, in seconds since the epoch, to resolve into block numbers.
+    before_time: Option<u64>,
+    after_time: Option<u64>,
+    // Only return the sandwiches found by this detector version,
+    // including superseded ones, instead of the current sandwiches.
+    detector_version: Option<String>
 }
 
 #[derive(Debug, Serialize)]
@@ -53,7 +59,8 @@ struct ScanMetadata {
     lower_bound: u64,
     upper_bound: u64,
     complete: bool,
-    failed: bool
+    failed: bool,
+    detector_version: String // of the detector most recently run over the range
 }
 
 impl From<Range> for ScanMetadata {
@@ -62,7 +69,8 @@ impl From<Range> for ScanMetadata {
             lower_bound: range.lower_bound as u64,
             upper_bound: range.upper_bound as u64,
             complete: range.scan_complete,
-            failed: range.scan_failed
+            failed: range.scan_failed,
+            detector_version: range.detector_version
         }
     }
 }
@@ -70,9 +78,26 @@ impl From<Range> for ScanMetadata {
 #[derive(Debug, Serialize)]
 struct SandwichData {
     block_number: i64,
+    timestamp: Option<i64>, // the block's, for sandwiches scanned since blocks were recorded
+    detector_version: String,
+    superseded: bool, // by a later run of a detector over the sandwich's range
     frontrun: TransactionData,
     lunchmeat: Vec<TransactionData>,
-    backrun: TransactionData
+    backrun: TransactionData,
+    // The attacker, bribe and token-flow profits are only known
+    // for sandwiches scanned since they started being recorded.
+    attacker: Option<String>,
+    bribe: Option<f64>, // paid to the block's fee recipient, in the native token
+    base_profit: Option<f64>,
+    quote_profit: Option<f64>,
+    profits: Vec<TokenProfitData>
+}
+
+#[derive(Debug, Serialize)]
+struct TokenProfitData {
+    token: String,
+    symbol: Option<String>, // only set for tokens in the database
+    amount: f64 // in raw token units if `symbol` is not set
 }
 
 #[derive(Debug, Serialize)]
@@ -83,13 +108,24 @@ pub struct TransactionData {
     quote_in: f64,
     base_out: f64,
     quote_out: f64,
-    gas: f64
+    gas: f64, // the total gas fee, in the native token
+    // The gas used, and how much of the fee was burned or tipped to the
+    // block's fee recipient, for transactions scanned since they were recorded.
+    gas_used: Option<i64>,
+    gas_burned: Option<f64>,
+    gas_tip: Option<f64>,
+    // The part of the fee that paid for posting the transaction to L1, on
+    // rollups. It's charged on top of the burned fee and tip on OP Stack
+    // chains, while Arbitrum includes it in them.
+    gas_l1_fee: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    victim: Option<bool> // only set for lunchmeat transactions
 }
 
 // Implement the From trait to convert from the following
 // database structs into the TransactionData struct.
 implement_transaction_data_from!(FrontrunTransaction);
-implement_transaction_data_from!(LunchmeatTransaction);
+implement_transaction_data_from!(LunchmeatTransaction, is_victim);
 implement_transaction_data_from!(BackrunTransaction);
 
 #[get("/sandwiches")]
@@ -107,6 +143,11 @@ async fn fetch_sandwiches(
         None => return response_error!("blockchain not supported", SandwichesResponse)
     };
 
+    // Refuse to serve a blockchain whose provider is on the wrong chain.
+    if !blockchain.is_provider_verified() {
+        return response_error!("blockchain provider not verified", SandwichesResponse);
+    }
+
     // Get a database connection, and return an error
     // if a connection cannot be established.
     let db_connection = get_db_connection!(data, SandwichesResponse);
@@ -125,21 +166,45 @@ async fn fetch_sandwiches(
     };
 
     // Also get the exchange state data, or return an error.
-    let exchange = match blockchain.exchanges.get(&pair.factory_address) {
-        Some(exchange) => exchange.clone(),
+    let exchange = match blockchain.exchange(&pair.factory_address) {
+        Some(exchange) => exchange,
         None => return response_error!("exchange not supported", SandwichesResponse)
     };
 
-    // Determine from which block to begin the reverse-chronological scan.
+    // Determine from which block to begin the reverse-chronological scan,
+    // never starting from a block that could still be reorged out, since
+    // completed ranges are never scanned again.
+    let confirmed = match evm::fetch_latest_block_number(&blockchain.provider_url).await {
+        Ok(block_number) => block_number.saturating_sub(blockchain.scanner_params.confirmations),
+        _ => return response_error!("provider error", SandwichesResponse)
+    };
+
     let before = match info.before {
-        Some(block_number) => block_number,
-        None => {
-            match evm::fetch_latest_block_number(&blockchain.provider_url).await {
-                Ok(block_number) => block_number,
-                _ => return response_error!("provider error", SandwichesResponse)
-            }
-        }
-    };    
+        Some(block_number) if block_number < confirmed => block_number,
+        _ => confirmed
+    };
+
+    // Resolve `before_time` into the last block at or before it,
+    // if that's earlier than `before`.
+    let before = match info.before_time {
+        Some(time) => match evm::timestamps::fetch_block_at_or_before_time(
+            &blockchain.provider_url, &blockchain.block_timestamps, time, before).await {
+            Ok(Some(block_number)) => block_number,
+            Ok(None) => return response_error!("no blocks before the given time", SandwichesResponse),
+            Err(_) => return response_error!("provider error", SandwichesResponse)
+        },
+        None => before
+    };
+
+    // Resolve `after_time` into the first block at or after it.
+    let after_time_block = match info.after_time {
+        Some(time) => match evm::timestamps::fetch_block_at_or_after_time(
+            &blockchain.provider_url, &blockchain.block_timestamps, time, before).await {
+            Ok(block_number) => Some(into_i64!(block_number, SandwichesResponse)),
+            Err(_) => return response_error!("provider error", SandwichesResponse)
+        },
+        None => None
+    };
 
     // Get a database connection, and return an error
     // if a connection cannot be established.
@@ -169,10 +234,11 @@ async fn fetch_sandwiches(
             // of the preceding range, if it exists. Then calculate 
             // the lower bound of the new range, i.e. `after`,
             // and insert the new range into the database.
-            let provider_url = blockchain.provider_url.clone();
+            let chain = blockchain.chain_params();
             let pair_clone = pair.clone();
-            let native_decimals = blockchain.native_token.decimals.clone();
             let params = blockchain.scanner_params.clone();
+            let detector_params = blockchain.detector_params.clone();
+            let detector_version = detector_params.build(&pair.factory_address).version();
 
             let new_range_thread_result = web::block(move || {
                 let ub = match db::find_preceding_range_upper_bound(
@@ -199,7 +265,8 @@ async fn fetch_sandwiches(
                     after_block,
                     before_block,
                     false, // scan not complete
-                    false // scan not failed
+                    false, // scan not failed
+                    &detector_version
                 ) {
                     Ok(range) => range,
                     Err(e) => return Err(e)
@@ -210,12 +277,12 @@ async fn fetch_sandwiches(
                 // Start the scan as a background job.
                 if evm::scanner::start_scan_job(
                     db_connection,
-                    provider_url,
+                    chain,
                     pair_clone,
                     exchange,
-                    native_decimals,
                     range, 
-                    params
+                    params,
+                    detector_params
                 ) {
 
                 }
@@ -266,12 +333,25 @@ async fn fetch_sandwiches(
         in_range.lower_bound
     };
 
+    // Don't return sandwiches from before `after_time`.
+    let after_block = match after_time_block {
+        Some(block_number) if block_number > after_block => block_number,
+        _ => after_block
+    };
+
     // Spawn a new, non-blocking thread to fetch up to `max_blocks` 
     // worth of sandwiches from the range, as well as token metadata.
     let base_id = pair.base_token_id;
     let quote_id = pair.quote_token_id;
+    let detector_version = info.detector_version.clone();
     let thread_result = web::block(move || {
-        (fetch_db_sandwich_data(&db_connection, pair_id, after_block, before_block),
+        (fetch_db_sandwich_data(
+            &db_connection,
+            pair_id,
+            base_id,
+            quote_id,
+            (after_block, before_block),
+            detector_version.as_deref()),
         db::fetch_token_by_id(&db_connection, base_id),
         db::fetch_token_by_id(&db_connection, quote_id))
     }).await;
@@ -320,15 +400,18 @@ async fn fetch_sandwiches(
 fn fetch_db_sandwich_data(
     db_connection: &db::DbConnection,
     pair_id: i32,
-    min_ge_block: i64,
-    max_le_block: i64
+    base_id: i32,
+    quote_id: i32,
+    (min_ge_block, max_le_block): (i64, i64),
+    detector_version: Option<&str>
 ) -> Result<Vec<SandwichData>, DbError> {
     // Get the sandwiches in the database's Sandwich model form.
     let db_sandwiches = match db::fetch_all_sandwiches_by_params(
         &db_connection,
         pair_id,
         Some(min_ge_block),
-        Some(max_le_block)) {
+        Some(max_le_block),
+        detector_version) {
 
         Ok(sandwich_vector) => sandwich_vector,
         Err(e) => return Err(e)
@@ -364,14 +447,56 @@ fn fetch_db_sandwich_data(
             Ok(backrun) => backrun,
             Err(e) => return Err(e)
         };
-        
+
+        // Get the attacker's token-flow profits for this sandwich.
+        let db_profits = db::fetch_sandwich_token_profits_by_sandwich_id(
+            db_connection,
+            db_sandwich.sandwich_id)?;
+
+        let mut profits = Vec::with_capacity(db_profits.len());
+
+        for db_profit in db_profits.iter() {
+            let symbol = match db_profit.token_id {
+                Some(token_id) => Some(db::fetch_token_by_id(db_connection, token_id)?.token_symbol),
+                None => None
+            };
+
+            profits.push(TokenProfitData {
+                token: db_profit.token_address.clone(),
+                symbol,
+                amount: db_profit.amount
+            });
+        }
+
+        // A token missing from the profits was a net zero for the attacker.
+        let profit_of = |token_id: i32| db_sandwich.attacker_address.as_ref().map(|_| {
+            db_profits.iter()
+                .find(|p| p.token_id == Some(token_id))
+                .map(|p| p.amount)
+                .unwrap_or(0f64)
+        });
+
+        // Get the timestamp of this sandwich's block, if it was recorded.
+        let timestamp = match db_sandwich.block_id {
+            Some(block_id) => Some(db::fetch_block_by_id(db_connection, block_id)?.block_timestamp),
+            None => None
+        };
+
         sandwiches.push(SandwichData {
             block_number: db_sandwich.block_number,
+            timestamp,
+            detector_version: db_sandwich.detector_version.clone(),
+            superseded: db_sandwich.superseded,
             frontrun: TransactionData::from(&db_frontrun),
             lunchmeat: db_lunchmeats.iter()
                 .map(|db_tx| TransactionData::from(db_tx))
                 .collect::<Vec<TransactionData>>(),
-            backrun: TransactionData::from(&db_backrun)
+            backrun: TransactionData::from(&db_backrun),
+            attacker: db_sandwich.attacker_address.clone(),
+            bribe: db_sandwich.bribe,
+            base_profit: profit_of(base_id),
+            quote_profit: profit_of(quote_id),
+            profits
         });
     }
     
@@ -0,0 +1,223 @@
+use actix_web::{ get, web };
+use serde::{ Serialize, Deserialize };
+use diesel::result::Error as DbError;
+use crate::state::AppState;
+use super::super::{ db, models };
+use std::collections::HashMap;
+
+// The most pairs returned per search.
+const MAX_SEARCH_PAIRS: i64 = 50;
+
+#[derive(Debug, Deserialize)]
+struct SearchRequest {
+    q: String,
+    blockchain: Option<String> // if unset, search every blockchain
+}
+
+#[derive(Debug, Serialize)]
+struct SearchResponse {
+    pairs: Option<Vec<PairResult>>,
+    error_message: String
+}
+
+impl SearchResponse {
+    fn as_error(msg: String) -> Self {
+        Self {
+            pairs: None,
+            error_message: msg
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PairResult {
+    blockchain: String,
+    address: String,
+    exchange_name: String,
+    fee_tier: Option<i32>, // only known for V3 pools discovered from their factory
+    base: TokenResult,
+    quote: TokenResult
+}
+
+#[derive(Debug, Serialize)]
+struct TokenResult {
+    address: String,
+    name: String,
+    symbol: String
+}
+
+impl From<&models::Token> for TokenResult {
+    fn from(token: &models::Token) -> Self {
+        Self {
+            address: token.token_address.clone(),
+            name: token.token_name.clone(),
+            symbol: token.token_symbol.clone()
+        }
+    }
+}
+
+// Search the saved pairs by the symbol, name or address (prefix)
+// of either of their tokens. Pairs with a token whose symbol matches
+// the query exactly come first. Only pairs on supported exchanges,
+// on blockchains with a verified provider, are returned.
+#[get("/search")]
+async fn search_pairs(
+    data: web::Data<AppState>,
+    info: web::Query<SearchRequest>
+) -> web::Json<SearchResponse> {
+    // Standardize the incoming data.
+    let query = info.q.trim().to_lowercase();
+    let blockchain_id = info.blockchain.as_ref().map(|blockchain| blockchain.to_lowercase());
+
+    if query.is_empty() {
+        return response_error!("empty search query", SearchResponse);
+    }
+
+    if let Some(blockchain_id) = blockchain_id.as_ref() {
+        match data.blockchains.get(blockchain_id) {
+            Some(blockchain) if blockchain.is_provider_verified() => (),
+            Some(_) => return response_error!("blockchain provider not verified", SearchResponse),
+            None => return response_error!("blockchain not supported", SearchResponse)
+        }
+    }
+
+    // Only search the supported exchanges' pairs, on blockchains with a verified provider.
+    let factories = data.blockchains.iter()
+        .filter(|(str_id, blockchain)| {
+            blockchain.is_provider_verified() && blockchain_id.as_ref().is_none_or(|id| id == *str_id)
+        })
+        .map(|(str_id, blockchain)| (str_id.clone(), blockchain.supported_exchanges().into_keys().collect()))
+        .collect::<HashMap<String, Vec<String>>>();
+
+    // Get a database connection, and return an error
+    // if a connection cannot be established.
+    let db_connection = get_db_connection!(data, SearchResponse);
+
+    // Spawn a new, non-blocking thread to find the matching pairs and their tokens.
+    let thread_result = web::block(move || {
+        fetch_db_search_results(&db_connection, &factories, &query)
+    }).await;
+
+    let (pairs, tokens) = match thread_unwrap!(thread_result, SearchResponse) {
+        Ok(result) => result,
+        Err(_) => return response_error!("search database error", SearchResponse)
+    };
+
+    let mut results = Vec::new();
+
+    for pair in pairs {
+        let exchange = match data.blockchains.get(&pair.blockchain_str_id)
+            .and_then(|blockchain| blockchain.exchange(&pair.factory_address)) {
+            Some(exchange) => exchange,
+            None => continue
+        };
+
+        let (base, quote) = match (tokens.get(&pair.base_token_id), tokens.get(&pair.quote_token_id)) {
+            (Some(base), Some(quote)) => (base, quote),
+            _ => continue
+        };
+
+        results.push(PairResult {
+            blockchain: pair.blockchain_str_id,
+            address: pair.pair_address,
+            exchange_name: exchange.name().to_string(),
+            fee_tier: pair.fee_tier,
+            base: TokenResult::from(base),
+            quote: TokenResult::from(quote)
+        });
+    }
+
+    web::Json(SearchResponse {
+        pairs: if results.is_empty() { None } else { Some(results) },
+        error_message: "".to_string()
+    })
+}
+
+// Find the given factories' pairs with a token matching the given query
+// (best matches first), along with all of their tokens, by token id.
+fn fetch_db_search_results(
+    db_connection: &db::DbConnection,
+    factories: &HashMap<String, Vec<String>>,
+    query: &str
+) -> Result<(Vec<models::Pair>, HashMap<i32, models::Token>), DbError> {
+    let pairs = db::search_pairs(db_connection, factories, query, MAX_SEARCH_PAIRS)?;
+
+    let pair_token_ids = pairs.iter()
+        .flat_map(|pair| [pair.base_token_id, pair.quote_token_id])
+        .collect::<Vec<i32>>();
+    let tokens = db::fetch_tokens_by_ids(db_connection, &pair_token_ids)?
+        .into_iter()
+        .map(|token| (token.token_id, token))
+        .collect();
+
+    Ok((pairs, tokens))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_db::test_db_connection;
+
+    const FACTORY_ADDRESS: &str = "0x5c69bee701ef814a2b6a3edd4b1652cb9cc5aa6f";
+    const OTHER_FACTORY_ADDRESS: &str = "0x1f98431c8ad98523631ae4a59f267346ea31f984";
+
+    fn address(n: u64) -> String {
+        format!("0x{:040x}", n)
+    }
+
+    fn insert_token(db_connection: &db::DbConnection, blockchain_id: &str, symbol: &str, n: u64) -> i32 {
+        db::fetch_or_insert_token(db_connection, symbol, symbol, 18, blockchain_id, &address(n)).unwrap()
+    }
+
+    fn insert_pair(db_connection: &db::DbConnection, blockchain_id: &str, factory_address: &str, (base, quote): (i32, i32), n: u64) -> String {
+        db::insert_pair(db_connection, blockchain_id, factory_address, &address(n), base, quote, None).unwrap();
+        address(n)
+    }
+
+    #[test]
+    #[ignore = "requires TEST_DATABASE_URL"]
+    fn exact_matches_on_supported_exchanges_come_first() {
+        let db_connection = test_db_connection();
+
+        let usdc = insert_token(&db_connection, "ethereum", "USDC", 1);
+        let weth = insert_token(&db_connection, "ethereum", "WETH", 2);
+        let goerli_weth = insert_token(&db_connection, "goerli", "WETH", 3);
+
+        // More prefix matches than are returned, all saved before the exact matches.
+        for n in 0..MAX_SEARCH_PAIRS as u64 {
+            let wethx = insert_token(&db_connection, "ethereum", &format!("WETH{n}"), 100 + n);
+            insert_pair(&db_connection, "ethereum", FACTORY_ADDRESS, (wethx, usdc), 1_000 + n);
+        }
+
+        insert_pair(&db_connection, "ethereum", OTHER_FACTORY_ADDRESS, (weth, usdc), 2_000);
+        insert_pair(&db_connection, "goerli", FACTORY_ADDRESS, (goerli_weth, goerli_weth), 2_001);
+        let exact = insert_pair(&db_connection, "ethereum", FACTORY_ADDRESS, (usdc, weth), 2_002);
+
+        let factories = HashMap::from([("ethereum".to_string(), vec![FACTORY_ADDRESS.to_string()])]);
+        let (pairs, tokens) = fetch_db_search_results(&db_connection, &factories, "weth").unwrap();
+
+        // The exact match comes first, even though it was saved last, and the
+        // other factory's and blockchain's pairs don't take up any results.
+        assert_eq!(pairs.len(), MAX_SEARCH_PAIRS as usize);
+        assert_eq!(pairs[0].pair_address, exact);
+        assert!(pairs.iter().all(|pair| pair.factory_address == FACTORY_ADDRESS && pair.blockchain_str_id == "ethereum"));
+        assert_eq!(tokens[&pairs[1].base_token_id].token_symbol, "WETH0");
+
+        // Other pairs are in the order they were saved.
+        assert!(pairs[1..].windows(2).all(|pairs| pairs[0].pair_id < pairs[1].pair_id));
+
+        // Both factories' exact matches come first once both are searched.
+        let factories = HashMap::from([
+            ("ethereum".to_string(), vec![FACTORY_ADDRESS.to_string(), OTHER_FACTORY_ADDRESS.to_string()]),
+            ("goerli".to_string(), vec![FACTORY_ADDRESS.to_string()])
+        ]);
+        let (pairs, _) = fetch_db_search_results(&db_connection, &factories, "WETH").unwrap();
+
+        assert_eq!(
+            pairs[..3].iter().map(|pair| pair.pair_address.clone()).collect::<Vec<String>>(),
+            vec![address(2_000), address(2_001), exact]);
+
+        // Nothing is searched without any supported factories.
+        assert!(fetch_db_search_results(&db_connection, &HashMap::new(), "weth").unwrap().0.is_empty());
+    }
+}
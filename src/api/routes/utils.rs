@@ -43,10 +43,17 @@ macro_rules! thread_unwrap {
 }
 
 // This helper macro implements the From trait for the various
-// `Transaction` db structs into `sandwiches::TransactionData`
+// `Transaction` db structs into `sandwiches::TransactionData`;
+// pass the name of a victim flag field, if the struct has one.
 #[macro_export]
 macro_rules! implement_transaction_data_from {
     ($from:ident) => {
+        implement_transaction_data_from!($from, |_tx: &$from| None);
+    };
+    ($from:ident, $victim:ident) => {
+        implement_transaction_data_from!($from, |tx: &$from| Some(tx.$victim));
+    };
+    ($from:ident, $victim:expr) => {
         impl std::convert::From<&$from> for super::sandwiches::TransactionData {
             fn from(tx: &$from) -> Self {
                 Self {
@@ -56,7 +63,12 @@ macro_rules! implement_transaction_data_from {
                     quote_in: tx.quote_in,
                     base_out: tx.base_out,
                     quote_out: tx.quote_out,
-                    gas: tx.gas
+                    gas: tx.gas,
+                    gas_used: tx.gas_used,
+                    gas_burned: tx.gas_burned,
+                    gas_tip: tx.gas_tip,
+                    gas_l1_fee: tx.gas_l1_fee,
+                    victim: ($victim)(tx)
                 }
             }
         }
@@ -1,3 +1,27 @@
+table! {
+    arbitrage_pairs (arbitrage_id, hop) {
+        arbitrage_id -> Int8,
+        hop -> Int4,
+        pair_id -> Int4,
+    }
+}
+
+table! {
+    arbitrages (arbitrage_id) {
+        arbitrage_id -> Int8,
+        blockchain_str_id -> Varchar,
+        block_number -> Int8,
+        tx_hash -> Bpchar,
+        tx_index -> Int4,
+        arbitrage_kind -> Varchar,
+        searcher_address -> Bpchar,
+        profit_token_id -> Int4,
+        profit -> Float8,
+        target_tx_hash -> Nullable<Bpchar>,
+        target_tx_index -> Nullable<Int4>,
+    }
+}
+
 table! {
     backrun_transactions (backrun_id) {
         backrun_id -> Int8,
@@ -9,6 +33,65 @@ table! {
         quote_out -> Float8,
         gas -> Float8,
         sandwich_id -> Int8,
+        gas_used -> Nullable<Int8>,
+        gas_burned -> Nullable<Float8>,
+        gas_tip -> Nullable<Float8>,
+        gas_l1_fee -> Nullable<Float8>,
+    }
+}
+
+table! {
+    block_scans (block_scan_id) {
+        block_scan_id -> Int8,
+        blockchain_str_id -> Varchar,
+        block_number -> Int8,
+        scan_complete -> Bool,
+        scan_failed -> Bool,
+    }
+}
+
+table! {
+    blocks (block_id) {
+        block_id -> Int8,
+        blockchain_str_id -> Varchar,
+        block_number -> Int8,
+        block_hash -> Bpchar,
+        miner -> Bpchar,
+        block_timestamp -> Int8,
+        base_fee -> Nullable<Int8>,
+    }
+}
+
+table! {
+    exchanges (exchange_id) {
+        exchange_id -> Int4,
+        blockchain_str_id -> Varchar,
+        factory_address -> Bpchar,
+        exchange_name -> Varchar,
+        exchange_kind -> Varchar,
+        verified -> Bool,
+    }
+}
+
+table! {
+    factory_scans (factory_scan_id) {
+        factory_scan_id -> Int4,
+        blockchain_str_id -> Varchar,
+        factory_address -> Bpchar,
+        last_block -> Int8,
+    }
+}
+
+table! {
+    failed_pairs (failed_pair_id) {
+        failed_pair_id -> Int4,
+        blockchain_str_id -> Varchar,
+        factory_address -> Bpchar,
+        pair_address -> Bpchar,
+        token0_address -> Bpchar,
+        token1_address -> Bpchar,
+        fee_tier -> Nullable<Int4>,
+        attempts -> Int4,
     }
 }
 
@@ -23,6 +106,45 @@ table! {
         quote_out -> Float8,
         gas -> Float8,
         sandwich_id -> Int8,
+        gas_used -> Nullable<Int8>,
+        gas_burned -> Nullable<Float8>,
+        gas_tip -> Nullable<Float8>,
+        gas_l1_fee -> Nullable<Float8>,
+    }
+}
+
+table! {
+    jit_attacks (jit_attack_id) {
+        jit_attack_id -> Int8,
+        pair_id -> Int4,
+        block_number -> Int8,
+        owner_address -> Bpchar,
+        tick_lower -> Int4,
+        tick_upper -> Int4,
+        liquidity -> Float8,
+        mint_tx_hash -> Bpchar,
+        mint_tx_index -> Int4,
+        burn_tx_hash -> Bpchar,
+        burn_tx_index -> Int4,
+        base_minted -> Float8,
+        quote_minted -> Float8,
+        base_burned -> Float8,
+        quote_burned -> Float8,
+        base_fees -> Float8,
+        quote_fees -> Float8,
+    }
+}
+
+table! {
+    jit_swap_transactions (jit_swap_id) {
+        jit_swap_id -> Int8,
+        tx_hash -> Bpchar,
+        tx_index -> Int4,
+        base_in -> Float8,
+        quote_in -> Float8,
+        base_out -> Float8,
+        quote_out -> Float8,
+        jit_attack_id -> Int8,
     }
 }
 
@@ -37,6 +159,40 @@ table! {
         quote_out -> Float8,
         gas -> Float8,
         sandwich_id -> Int8,
+        is_victim -> Bool,
+        gas_used -> Nullable<Int8>,
+        gas_burned -> Nullable<Float8>,
+        gas_tip -> Nullable<Float8>,
+        gas_l1_fee -> Nullable<Float8>,
+    }
+}
+
+table! {
+    multipool_sandwich_pairs (multipool_sandwich_id, pair_id) {
+        multipool_sandwich_id -> Int8,
+        pair_id -> Int4,
+    }
+}
+
+table! {
+    multipool_sandwiches (multipool_sandwich_id) {
+        multipool_sandwich_id -> Int8,
+        blockchain_str_id -> Varchar,
+        block_number -> Int8,
+        attacker_address -> Bpchar,
+        frontrun_tx_hash -> Bpchar,
+        frontrun_tx_index -> Int4,
+        backrun_tx_hash -> Bpchar,
+        backrun_tx_index -> Int4,
+    }
+}
+
+table! {
+    multipool_victim_transactions (multipool_victim_id) {
+        multipool_victim_id -> Int8,
+        tx_hash -> Bpchar,
+        tx_index -> Int4,
+        multipool_sandwich_id -> Int8,
     }
 }
 
@@ -48,6 +204,7 @@ table! {
         pair_address -> Bpchar,
         base_token_id -> Int4,
         quote_token_id -> Int4,
+        fee_tier -> Nullable<Int4>,
     }
 }
 
@@ -59,6 +216,17 @@ table! {
         upper_bound -> Int8,
         scan_complete -> Bool,
         scan_failed -> Bool,
+        swaps_stored -> Bool,
+        detector_version -> Varchar,
+    }
+}
+
+table! {
+    sandwich_token_profits (sandwich_id, token_address) {
+        sandwich_id -> Int8,
+        token_address -> Bpchar,
+        token_id -> Nullable<Int4>,
+        amount -> Float8,
     }
 }
 
@@ -67,6 +235,32 @@ table! {
         sandwich_id -> Int8,
         pair_id -> Int4,
         block_number -> Int8,
+        attacker_address -> Nullable<Bpchar>,
+        bribe -> Nullable<Float8>,
+        bribe_tx_hash -> Nullable<Bpchar>,
+        block_id -> Nullable<Int8>,
+        detector_version -> Varchar,
+        superseded -> Bool,
+    }
+}
+
+table! {
+    swaps (swap_id) {
+        swap_id -> Int8,
+        pair_id -> Int4,
+        block_number -> Int8,
+        tx_hash -> Bpchar,
+        tx_index -> Int4,
+        log_index -> Int4,
+        sender -> Bpchar,
+        recipient -> Bpchar,
+        amount0_in -> Varchar,
+        amount1_in -> Varchar,
+        amount0_out -> Varchar,
+        amount1_out -> Varchar,
+        sqrt_price_x96 -> Nullable<Varchar>,
+        liquidity -> Nullable<Varchar>,
+        tick -> Nullable<Int4>,
     }
 }
 
@@ -81,18 +275,43 @@ table! {
     }
 }
 
+joinable!(arbitrage_pairs -> arbitrages (arbitrage_id));
+joinable!(arbitrage_pairs -> pairs (pair_id));
+joinable!(arbitrages -> tokens (profit_token_id));
 joinable!(backrun_transactions -> sandwiches (sandwich_id));
 joinable!(frontrun_transactions -> sandwiches (sandwich_id));
+joinable!(jit_attacks -> pairs (pair_id));
+joinable!(jit_swap_transactions -> jit_attacks (jit_attack_id));
 joinable!(lunchmeat_transactions -> sandwiches (sandwich_id));
+joinable!(multipool_sandwich_pairs -> multipool_sandwiches (multipool_sandwich_id));
+joinable!(multipool_sandwich_pairs -> pairs (pair_id));
+joinable!(multipool_victim_transactions -> multipool_sandwiches (multipool_sandwich_id));
 joinable!(ranges -> pairs (pair_id));
+joinable!(sandwich_token_profits -> sandwiches (sandwich_id));
+joinable!(sandwiches -> blocks (block_id));
 joinable!(sandwiches -> pairs (pair_id));
+joinable!(swaps -> pairs (pair_id));
 
 allow_tables_to_appear_in_same_query!(
+    arbitrage_pairs,
+    arbitrages,
     backrun_transactions,
+    block_scans,
+    blocks,
+    exchanges,
+    factory_scans,
+    failed_pairs,
     frontrun_transactions,
+    jit_attacks,
+    jit_swap_transactions,
     lunchmeat_transactions,
+    multipool_sandwich_pairs,
+    multipool_sandwiches,
+    multipool_victim_transactions,
     pairs,
     ranges,
+    sandwich_token_profits,
     sandwiches,
+    swaps,
     tokens,
 );
@@ -0,0 +1,30 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ ConnectionManager, CustomizeConnection, Error as PoolError };
+use super::db;
+use std::env;
+
+// Keep every change made through a connection in a transaction
+// that is rolled back when the connection is closed.
+#[derive(Debug)]
+struct TestTransaction;
+
+impl CustomizeConnection<PgConnection, PoolError> for TestTransaction {
+    fn on_acquire(&self, connection: &mut PgConnection) -> Result<(), PoolError> {
+        connection.begin_test_transaction().map_err(PoolError::QueryError)
+    }
+}
+
+// Connect to the database in `TEST_DATABASE_URL`, after running any
+// pending migrations on it. Tests that use it are ignored by default.
+pub fn test_db_connection() -> db::DbConnection {
+    let db_url = env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must be set");
+    db::connect_db_pool(&db_url);
+
+    let pool = db::Pool::builder()
+        .max_size(1)
+        .connection_customizer(Box::new(TestTransaction))
+        .build(ConnectionManager::<PgConnection>::new(db_url))
+        .expect("error creating test db pool");
+
+    pool.get().expect("error getting test database connection")
+}
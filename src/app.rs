@@ -11,10 +11,15 @@ async fn index(
 ) -> impl Responder {
     let app_name = data.app_name.lock().unwrap();
     let inspect_url = req.url_for_static("inspect_pair").unwrap().to_string();
+    let api_search_url = req.url_for_static("search_pairs").unwrap().to_string();
 
     let mut blockchains = Vec::new();
 
     for (str_id, blockchain) in data.blockchains.iter() {
+        if !blockchain.is_provider_verified() {
+            continue; // don't offer blockchains whose provider is on the wrong chain
+        }
+
         blockchains.push(templates::index::Blockchain {
             name: &blockchain.name,
             str_id: str_id
@@ -23,7 +28,7 @@ async fn index(
 
     blockchains.sort(); // sort the blockchains alphabetically by name
 
-    HttpResponse::Ok().body(templates::index::render(&app_name, &inspect_url, blockchains))
+    HttpResponse::Ok().body(templates::index::render(&app_name, &inspect_url, &api_search_url, blockchains))
 }
 
 // Define request params for inspect_pair, below.
@@ -45,8 +50,8 @@ async fn inspect_pair(
 
     let blockchain_str_id = info.blockchain.to_lowercase();
     let blockchain = match data.blockchains.get(&blockchain_str_id) {
-        Some(blockchain) => blockchain,
-        None => {
+        Some(blockchain) if blockchain.is_provider_verified() => blockchain,
+        _ => {
             let message = "Did you specify a valid blockchain?".to_string();
             return render_not_found(&message, &home_url); 
         }
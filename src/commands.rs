@@ -0,0 +1,205 @@
+use std::collections::{ BTreeMap, BTreeSet };
+use std::error::Error;
+use crate::api::{ db, evm, models };
+use crate::api::evm::detector::SandwichDetector;
+use crate::state::AppState;
+
+// A sandwich, identified by its block and its frontrun and backrun hashes.
+type SandwichKey = (i64, String, String);
+
+// Re-run sandwich detection over the swaps stored by previous range scans,
+// without any provider calls, and print how the results differ from the
+// sandwiches in the database. Only ranges scanned since swaps started being
+// stored are covered. Takes `<blockchain> [pair address]`.
+pub fn rescan(app_state: &AppState, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let blockchain_id = args.first().ok_or("usage: rescan <blockchain> [pair address]")?.to_lowercase();
+    let blockchain = app_state.blockchains.get(&blockchain_id).ok_or("blockchain not supported")?;
+    let db_connection = app_state.db_pool.get()?;
+
+    let pairs = match args.get(1) {
+        Some(pair_address) => vec![db::fetch_pair_by_params(&db_connection, &blockchain_id, pair_address)?],
+        None => db::fetch_pairs_by_blockchain(&db_connection, &blockchain_id)?
+    };
+
+    for pair in pairs {
+        let base = db::fetch_token_by_id(&db_connection, pair.base_token_id)?;
+        let quote = db::fetch_token_by_id(&db_connection, pair.quote_token_id)?;
+        let detector = blockchain.detector_params.build(&pair.factory_address);
+
+        println!(
+            "\nPair {} ({}/{}), using the {} detector:",
+            pair.pair_address, base.token_symbol, quote.token_symbol, detector.name());
+
+        for range in db::fetch_complete_ranges_by_pair_id(&db_connection, pair.pair_id)? {
+            if !range.swaps_stored {
+                println!(" -- Range [{}, {}]: skipped, no swaps stored", range.lower_bound, range.upper_bound);
+                continue;
+            }
+
+            let (stored, detected) = detect_range(
+                &db_connection,
+                &pair,
+                &range,
+                blockchain.native_token.decimals,
+                (&base, &quote),
+                detector.as_ref())?;
+
+            let added = detected.difference(&stored).collect::<Vec<&SandwichKey>>();
+            let removed = stored.difference(&detected).collect::<Vec<&SandwichKey>>();
+
+            println!(
+                " -- Range [{}, {}]: {} stored, {} detected ({} new, {} missing)",
+                range.lower_bound, range.upper_bound, stored.len(), detected.len(), added.len(), removed.len());
+
+            for (block, frontrun, backrun) in added {
+                println!("    + Block {block}: frontrun {frontrun}, backrun {backrun}");
+            }
+
+            for (block, frontrun, backrun) in removed {
+                println!("    - Block {block}: frontrun {frontrun}, backrun {backrun}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Re-run the given detector over the swaps stored for the given range, and
+// return the range's (stored, detected) sandwiches, to compare them.
+fn detect_range(
+    db_connection: &db::DbConnection,
+    pair: &models::Pair,
+    range: &models::Range,
+    native_decimals: u8,
+    (base, quote): (&models::Token, &models::Token),
+    detector: &dyn SandwichDetector
+) -> Result<(BTreeSet<SandwichKey>, BTreeSet<SandwichKey>), Box<dyn Error>> {
+    let swaps = evm::scanner::load_stored_swaps(
+        db_connection,
+        pair,
+        (range.lower_bound as u64, range.upper_bound as u64),
+        native_decimals,
+        base,
+        quote)?;
+
+    // Group the swaps by block, in transaction order, as the scanner does.
+    let mut swaps_by_block: BTreeMap<i64, Vec<evm::swap::Swap>> = BTreeMap::new();
+
+    for swap in swaps.into_iter() {
+        swaps_by_block.entry(swap.swap.block_number() as i64).or_default().push(swap);
+    }
+
+    let mut detected = BTreeSet::new();
+
+    for (block, bundle) in swaps_by_block.iter_mut() {
+        bundle.sort_by_key(|s| s.swap.tx_index());
+
+        for (i, j) in detector.find_matches(bundle) {
+            detected.insert((
+                *block,
+                format!("{:?}", bundle[i].swap.tx_hash),
+                format!("{:?}", bundle[j].swap.tx_hash)));
+        }
+    }
+
+    Ok((fetch_stored_sandwiches(db_connection, pair, range)?, detected))
+}
+
+// Fetch the current sandwiches stored for the given pair over the given range.
+fn fetch_stored_sandwiches(
+    db_connection: &db::DbConnection,
+    pair: &models::Pair,
+    range: &models::Range
+) -> Result<BTreeSet<SandwichKey>, Box<dyn Error>> {
+    let mut sandwiches = BTreeSet::new();
+
+    for sandwich in db::fetch_all_sandwiches_by_params(
+        db_connection, pair.pair_id, Some(range.lower_bound), Some(range.upper_bound), None)? {
+
+        let frontrun = db::fetch_frontrun_transaction_by_sandwich_id(db_connection, sandwich.sandwich_id)?;
+        let backrun = db::fetch_backrun_transaction_by_sandwich_id(db_connection, sandwich.sandwich_id)?;
+        sandwiches.insert((sandwich.block_number, frontrun.tx_hash, backrun.tx_hash));
+    }
+
+    Ok(sandwiches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::evm::detector::{ DetectorParams, DetectorKind, DEFAULT_TOLERANCE };
+    use crate::api::test_db::test_db_connection;
+    use diesel::r2d2::ConnectionManager;
+    use ethers::types::{ Address, TxHash };
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    const FACTORY_ADDRESS: &str = "0x5c69bee701ef814a2b6a3edd4b1652cb9cc5aa6f";
+
+    fn tx_hash(tx_index: i32) -> String {
+        format!("{:?}", TxHash::from_low_u64_be(tx_index as u64 + 1))
+    }
+
+    // A swap in block 1 of (base in, quote in, base out, quote out).
+    fn new_swap(tx_index: i32, (in0, in1, out0, out1): (u64, u64, u64, u64)) -> db::NewSwap {
+        db::NewSwap {
+            block: 1,
+            tx: (tx_hash(tx_index), tx_index),
+            log_index: tx_index,
+            addresses: (format!("{:?}", Address::zero()), format!("{:?}", Address::zero())),
+            amounts: (in0.to_string(), in1.to_string(), out0.to_string(), out1.to_string()),
+            price: (None, None, None)
+        }
+    }
+
+    #[test]
+    fn rescans_need_a_supported_blockchain() {
+        // The pool never connects, since neither rescan gets that far.
+        let app_state = AppState {
+            app_name: Mutex::new("test".to_string()),
+            db_pool: db::Pool::builder().build_unchecked(ConnectionManager::new("postgres://localhost/unused")),
+            blockchains: HashMap::new()
+        };
+
+        assert!(rescan(&app_state, &[]).is_err());
+        assert_eq!(
+            rescan(&app_state, &["Ethereum".to_string()]).unwrap_err().to_string(),
+            "blockchain not supported");
+    }
+
+    #[test]
+    #[ignore = "requires TEST_DATABASE_URL"]
+    fn rescans_find_sandwiches_that_are_not_stored() {
+        let db_connection = test_db_connection();
+
+        let address = |byte: u8| format!("{:?}", Address::repeat_byte(byte));
+        let base_id = db::insert_token(&db_connection, "Base", "BASE", 0, "mock", &address(1)).unwrap();
+        let quote_id = db::insert_token(&db_connection, "Quote", "QUOTE", 0, "mock", &address(2)).unwrap();
+        let pair_id = db::insert_pair(&db_connection, "mock", FACTORY_ADDRESS, &address(3), base_id, quote_id, None).unwrap();
+        let pair = db::fetch_pair_by_id(&db_connection, pair_id).unwrap();
+        let base = db::fetch_token_by_id(&db_connection, base_id).unwrap();
+        let quote = db::fetch_token_by_id(&db_connection, quote_id).unwrap();
+        let range = db::insert_range(&db_connection, pair_id, 0, 2, true, false, "amount-ratio").unwrap();
+
+        // A frontrun, a victim buying the same way, and a backrun selling
+        // the frontrun's base back, none of which has been saved as a sandwich.
+        db::insert_swaps(&db_connection, pair_id, &[
+            new_swap(0, (100, 0, 0, 200)),
+            new_swap(1, (50, 0, 0, 90)),
+            new_swap(2, (0, 200, 100, 0))
+        ]).unwrap();
+
+        let detector = DetectorParams {
+            kind: DetectorKind::AmountRatio,
+            tolerance: DEFAULT_TOLERANCE,
+            exchange_tolerances: HashMap::new(),
+            compare: false
+        }.build(FACTORY_ADDRESS);
+
+        let (stored, detected) = detect_range(
+            &db_connection, &pair, &range, 18, (&base, &quote), detector.as_ref()).unwrap();
+
+        assert!(stored.is_empty());
+        assert_eq!(detected, BTreeSet::from([(1, tx_hash(0), tx_hash(2))]));
+    }
+}
@@ -0,0 +1,368 @@
+use actix_web::rt;
+use actix_web::web::{ self, Data };
+use std::time::Duration;
+use crate::api::{ evm, db };
+use crate::state::{ AppState, ProviderStatus };
+
+// How often each blockchain's provider is re-checked
+// against the blockchain's expected chain id.
+const CHAIN_ID_CHECK_INTERVAL: Duration = Duration::from_secs(300);
+
+// How often the recorded blocks are checked against each blockchain's
+// provider for reorgs, and how many of the latest blocks are checked.
+const REORG_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const REORG_CHECK_DEPTH: u64 = 1_000;
+
+// How often completed ranges are checked for results
+// from a detector other than the current one.
+const REDETECTION_INTERVAL: Duration = Duration::from_secs(600);
+
+// How often each exchange's factory is checked for newly created pairs,
+// the most blocks of its events fetched per request (fewer if the provider
+// refuses that many), how many requests are made per factory each time (so
+// indexing a factory's history from its deployment is spread over several
+// runs), and how many times a pair's metadata is fetched before the pair is
+// given up on.
+const DISCOVERY_INTERVAL: Duration = Duration::from_secs(300);
+const DISCOVERY_BLOCKS_PER_REQUEST: u64 = 10_000;
+const DISCOVERY_REQUESTS_PER_RUN: u64 = 100;
+const DISCOVERY_MAX_ATTEMPTS: i32 = 5;
+
+// Ask every blockchain's provider for its chain id and record
+// whether it matches the chain id the blockchain expects.
+// Provider errors leave the previous status untouched.
+pub async fn verify_chain_ids(app_state: &AppState) {
+    for (str_id, blockchain) in app_state.blockchains.iter() {
+        match evm::fetch_chain_id(&blockchain.provider_url).await {
+            Ok(reported_chain_id) => {
+                if let ProviderStatus::Mismatch { reported_chain_id } =
+                    blockchain.update_provider_status(reported_chain_id) {
+
+                    println!(
+                        "Provider for {str_id} reports chain id {reported_chain_id} \
+                        but {} is expected; refusing to serve or scan {str_id}.",
+                        blockchain.chain_id);
+                }
+            },
+            Err(e) => println!("Could not verify the chain id for {str_id}: {e}")
+        }
+    }
+}
+
+// Periodically re-verify every provider's chain id in the background,
+// so that a misconfigured or swapped provider is caught while running.
+pub fn start_chain_id_monitor(app_state: Data<AppState>) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(CHAIN_ID_CHECK_INTERVAL);
+        interval.tick().await; // the first tick completes immediately
+
+        loop {
+            interval.tick().await;
+            verify_chain_ids(&app_state).await;
+        }
+    });
+}
+
+// Compare the hash of every recently recorded block against the provider's,
+// and rescan any block whose hash changed, since its sandwiches may no longer
+// exist (or may have been replaced). Errors skip the block until next time.
+// Block scans aren't checked: they record no hash, and blocks are only
+// scanned once they're `confirmations` behind the latest block.
+// Blocks whose rescan (from an earlier check) is still running are skipped.
+pub async fn reconcile_blocks(app_state: &AppState) {
+    for (str_id, blockchain) in app_state.blockchains.iter() {
+        if !blockchain.is_provider_verified() {
+            continue;
+        }
+
+        let latest = match evm::fetch_latest_block_number(&blockchain.provider_url).await {
+            Ok(block_number) => block_number,
+            Err(e) => {
+                println!("Could not check {str_id} for reorgs: {e}");
+                continue;
+            }
+        };
+
+        let db_connection = match app_state.db_pool.get() {
+            Ok(db_connection) => db_connection,
+            Err(e) => return println!("Could not check for reorgs: {e}")
+        };
+
+        let blockchain_id = str_id.clone();
+        let since = i64::try_from(latest.saturating_sub(REORG_CHECK_DEPTH)).unwrap_or(i64::MAX);
+        let blocks = match web::block(move || {
+            db::fetch_blocks_since(&db_connection, &blockchain_id, since)
+        }).await {
+            Ok(Ok(blocks)) => blocks,
+            _ => {
+                println!("Could not check {str_id} for reorgs: database error");
+                continue;
+            }
+        };
+
+        for block in blocks {
+            let block_number = block.block_number as u64;
+
+            // Don't start another rescan of a block before the last one is over.
+            if blockchain.rescanning_blocks.lock().unwrap().contains(&block_number) {
+                continue;
+            }
+
+            match evm::fetch_block_hash(&blockchain.provider_url, block_number).await {
+                Ok(hash) if hash == block.block_hash => {},
+                Ok(_) => {
+                    println!("Block {block_number} on {str_id} was reorged; rescanning it.");
+
+                    let db_connection = match app_state.db_pool.get() {
+                        Ok(db_connection) => db_connection,
+                        Err(e) => return println!("Could not rescan block {block_number}: {e}")
+                    };
+
+                    blockchain.rescanning_blocks.lock().unwrap().insert(block_number);
+
+                    if !evm::scanner::start_rescan_job(
+                        db_connection,
+                        blockchain.chain_params(),
+                        blockchain.supported_exchanges(),
+                        block,
+                        blockchain.detector_params.clone(),
+                        blockchain.rescanning_blocks.clone()) {
+
+                        blockchain.rescanning_blocks.lock().unwrap().remove(&block_number);
+                    }
+                },
+                Err(e) => println!("Could not check block {block_number} on {str_id} for a reorg: {e}")
+            }
+        }
+    }
+}
+
+// Periodically reconcile the recorded blocks with each provider in the background.
+pub fn start_reorg_monitor(app_state: Data<AppState>) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(REORG_CHECK_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            reconcile_blocks(&app_state).await;
+        }
+    });
+}
+
+// Re-run the current detector over every completed range (with stored swaps)
+// that was last detected by a different detector version, one range at a time.
+// Errors leave the range's version untouched, so it's retried next time.
+pub async fn redetect_ranges(app_state: &AppState) {
+    for (str_id, blockchain) in app_state.blockchains.iter() {
+        if !blockchain.is_provider_verified() {
+            continue;
+        }
+
+        let db_connection = match app_state.db_pool.get() {
+            Ok(db_connection) => db_connection,
+            Err(e) => return println!("Could not re-detect ranges: {e}")
+        };
+
+        let blockchain_id = str_id.clone();
+        let ranges = match web::block(move || {
+            db::fetch_redetectable_ranges(&db_connection, &blockchain_id)
+        }).await {
+            Ok(Ok(ranges)) => ranges,
+            _ => {
+                println!("Could not re-detect ranges on {str_id}: database error");
+                continue;
+            }
+        };
+
+        for (pair, range) in ranges {
+            let version = blockchain.detector_params.build(&pair.factory_address).version();
+
+            if range.detector_version == version {
+                continue;
+            }
+
+            let exchange = match blockchain.exchange(&pair.factory_address) {
+                Some(exchange) => exchange,
+                None => continue
+            };
+
+            let db_connection = match app_state.db_pool.get() {
+                Ok(db_connection) => db_connection,
+                Err(e) => return println!("Could not re-detect ranges: {e}")
+            };
+
+            println!(
+                "Re-detecting blocks {} to {} of {} on {str_id} with {version} (was {}).",
+                range.lower_bound, range.upper_bound, pair.pair_address, range.detector_version);
+
+            let chain = blockchain.chain_params();
+            let params = blockchain.scanner_params.clone();
+            let detector_params = blockchain.detector_params.clone();
+
+            match web::block(move || {
+                evm::scanner::redetect_range(
+                    &db_connection,
+                    chain,
+                    pair,
+                    exchange,
+                    range,
+                    params,
+                    detector_params)
+            }).await {
+                Ok(Ok(())) => {},
+                Ok(Err(e)) => println!("Could not re-detect a range on {str_id}: {e}"),
+                Err(e) => println!("Could not re-detect a range on {str_id}: {e}")
+            }
+        }
+    }
+}
+
+// Index the pairs created by every exchange's factory (from its PairCreated or
+// PoolCreated events) into the pairs and tokens tables, picking up after the
+// last indexed block (or from the factory's deployment block), up to the latest
+// confirmed block. Pairs whose metadata couldn't be fetched are recorded and
+// retried first. Errors stop indexing a factory until next time, so no block
+// range is ever skipped.
+pub async fn discover_pairs(app_state: &AppState) {
+    for (str_id, blockchain) in app_state.blockchains.iter() {
+        if !blockchain.is_provider_verified() {
+            continue;
+        }
+
+        let confirmed = match evm::fetch_latest_block_number(&blockchain.provider_url).await {
+            Ok(block_number) => block_number.saturating_sub(blockchain.scanner_params.confirmations),
+            Err(e) => {
+                println!("Could not discover pairs on {str_id}: {e}");
+                continue;
+            }
+        };
+
+        for (factory_address, exchange) in blockchain.supported_exchanges() {
+            let db_connection = match app_state.db_pool.get() {
+                Ok(db_connection) => db_connection,
+                Err(e) => return println!("Could not discover pairs: {e}")
+            };
+
+            let (blockchain_id, factory) = (str_id.clone(), factory_address.clone());
+            let (last_block, failed_pairs) = match web::block(move || {
+                Ok::<_, diesel::result::Error>((
+                    db::fetch_factory_scan_last_block(&db_connection, &blockchain_id, &factory)?,
+                    db::fetch_failed_pairs(&db_connection, &blockchain_id, &factory, DISCOVERY_MAX_ATTEMPTS)?
+                ))
+            }).await {
+                Ok(Ok(scan)) => scan,
+                _ => {
+                    println!("Could not discover pairs on {str_id}: database error");
+                    continue;
+                }
+            };
+
+            if !failed_pairs.is_empty() {
+                let created_pairs = match evm::discovery::fetch_created_pair_metadata(
+                    &blockchain.provider_url,
+                    blockchain.data_aggregator_address.as_deref(),
+                    failed_pairs.iter().filter_map(evm::discovery::created_pair_from_failed).collect()
+                ).await {
+                    Ok(created_pairs) => created_pairs,
+                    Err(e) => {
+                        println!("Could not discover {} pairs on {str_id}: {e}", exchange.name());
+                        continue;
+                    }
+                };
+
+                let db_connection = match app_state.db_pool.get() {
+                    Ok(db_connection) => db_connection,
+                    Err(e) => return println!("Could not discover pairs: {e}")
+                };
+
+                let (blockchain_id, factory) = (str_id.clone(), factory_address.clone());
+                match web::block(move || {
+                    evm::discovery::save_created_pairs(&db_connection, &blockchain_id, &factory, &created_pairs, None)
+                }).await {
+                    Ok(Ok(0)) => {},
+                    Ok(Ok(saved)) => println!(
+                        "Discovered {saved} previously failed {} pairs on {str_id}.",
+                        exchange.name()),
+                    _ => {
+                        println!("Could not save {} pairs on {str_id}: database error", exchange.name());
+                        continue;
+                    }
+                }
+            }
+
+            let mut lower = last_block.map_or(
+                blockchain.factory_deployment_block(&factory_address),
+                |block| block as u64 + 1);
+
+            let mut blocks_per_request = DISCOVERY_BLOCKS_PER_REQUEST;
+
+            for _ in 0..DISCOVERY_REQUESTS_PER_RUN {
+                if lower > confirmed {
+                    break;
+                }
+
+                let (upper, created_pairs) = match evm::discovery::fetch_created_pairs(
+                    &blockchain.provider_url,
+                    blockchain.data_aggregator_address.as_deref(),
+                    &factory_address,
+                    (lower, confirmed),
+                    blocks_per_request
+                ).await {
+                    Ok(created_pairs) => created_pairs,
+                    Err(e) => {
+                        println!("Could not discover {} pairs on {str_id}: {e}", exchange.name());
+                        break;
+                    }
+                };
+
+                let db_connection = match app_state.db_pool.get() {
+                    Ok(db_connection) => db_connection,
+                    Err(e) => return println!("Could not discover pairs: {e}")
+                };
+
+                let (blockchain_id, factory) = (str_id.clone(), factory_address.clone());
+                match web::block(move || {
+                    evm::discovery::save_created_pairs(
+                        &db_connection, &blockchain_id, &factory, &created_pairs, Some(upper as i64))
+                }).await {
+                    Ok(Ok(0)) => {},
+                    Ok(Ok(saved)) => println!(
+                        "Discovered {saved} {} pairs on {str_id} in blocks {lower} to {upper}.",
+                        exchange.name()),
+                    _ => {
+                        println!("Could not save {} pairs on {str_id}: database error", exchange.name());
+                        break;
+                    }
+                }
+
+                // Start from the window that worked, growing it back after a dense range.
+                blocks_per_request = DISCOVERY_BLOCKS_PER_REQUEST.min((upper - lower + 1) * 2);
+                lower = upper + 1;
+            }
+        }
+    }
+}
+
+// Periodically discover new pairs from every exchange's factory in the background.
+pub fn start_discovery_job(app_state: Data<AppState>) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(DISCOVERY_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            discover_pairs(&app_state).await;
+        }
+    });
+}
+
+// Periodically re-detect outdated ranges in the background.
+pub fn start_redetection_job(app_state: Data<AppState>) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(REDETECTION_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            redetect_ranges(&app_state).await;
+        }
+    });
+}
@@ -9,6 +9,8 @@ pub use state::{ AppState, init_app_state };
 
 mod api;
 mod app;
+mod commands;
+mod jobs;
 mod state;
 mod templates;
 
@@ -21,6 +23,33 @@ async fn main() -> std::io::Result<()> {
     // Initialize the global AppState instance.
     let app_state = init_app_state();
 
+    // Instead of serving, re-run detection over the stored swaps
+    // if asked to, i.e. `rescan <blockchain> [pair address]`.
+    let args = env::args().collect::<Vec<String>>();
+
+    if args.get(1).map(|command| command.as_str()) == Some("rescan") {
+        if let Err(e) = commands::rescan(&app_state, &args[2..]) {
+            println!("Rescan failed: {e}");
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
+    // Make sure every provider is on the chain it's configured for,
+    // and keep checking in the background while the server runs.
+    jobs::verify_chain_ids(&app_state).await;
+    jobs::start_chain_id_monitor(app_state.clone());
+
+    // Rescan any recorded blocks that get reorged while the server runs.
+    jobs::start_reorg_monitor(app_state.clone());
+
+    // Re-detect completed ranges whenever the detector changes.
+    jobs::start_redetection_job(app_state.clone());
+
+    // Index the pairs created by each exchange's factory, so they can be searched.
+    jobs::start_discovery_job(app_state.clone());
+
     // Register routes and start running the server.
     HttpServer::new(move || {
         App::new()
@@ -1,9 +1,11 @@
 use actix_web::web::Data;
-use std::collections::HashMap;
-use std::sync::Mutex;
+use std::collections::{ HashMap, HashSet, BTreeMap };
+use std::sync::{ Arc, Mutex };
 use std::env;
-use crate::api::db;
-use crate::api::evm::scanner::Params;
+use crate::api::{ db, models };
+use crate::api::evm::scanner::{ Params, ChainParams };
+use crate::api::evm::timestamps::BlockTimestamps;
+use crate::api::evm::detector::{ DetectorParams, DetectorKind, DEFAULT_TOLERANCE };
 
 // A thread-safe instance of this AppState is used
 // to hold global variables and database connections.
@@ -17,11 +19,107 @@ pub struct AppState {
 // will have helper data stored in a Blockchain instance.
 pub struct Blockchain {
     pub name: String,
+    pub chain_id: u64, // the chain id the provider must report via `eth_chainId`
+    pub chain_kind: ChainKind,
     pub provider_url: String,
-    pub data_aggregator_address: String,
+    pub data_aggregator_address: Option<String>, // if unset, metadata is fetched without the contract
     pub exchanges: HashMap<String, Exchange>, // factory address (key) -> exchange enum (value)
+    pub factory_deployment_blocks: HashMap<String, u64>, // factory address -> first block to index its pairs from
+    pub allow_unverified_exchanges: bool, // if set, serve factories whose pairs were only probed
+    pub detected_exchanges: Mutex<HashMap<String, models::DetectedExchange>>, // factory address -> registry entry
     pub scanner_params: Params,
-    pub native_token: NativeToken
+    pub detector_params: DetectorParams,
+    pub native_token: NativeToken,
+    pub provider_status: Mutex<ProviderStatus>,
+    pub rescanning_blocks: Arc<Mutex<HashSet<u64>>>, // blocks with a reorg rescan in progress
+    pub block_timestamps: BlockTimestamps // a cache for resolving dates to blocks
+}
+
+impl Blockchain {
+    // Only serve or scan a blockchain once its provider
+    // has been confirmed to be on the expected chain.
+    pub fn is_provider_verified(&self) -> bool {
+        matches!(*self.provider_status.lock().unwrap(), ProviderStatus::Verified)
+    }
+
+    // Record the chain id most recently reported by the provider.
+    pub fn update_provider_status(&self, reported_chain_id: u64) -> ProviderStatus {
+        let status = if reported_chain_id == self.chain_id {
+            ProviderStatus::Verified
+        } else {
+            ProviderStatus::Mismatch { reported_chain_id }
+        };
+
+        *self.provider_status.lock().unwrap() = status.clone();
+        status
+    }
+
+    // The block the given factory was deployed in, where indexing the pairs
+    // it created starts. Factories without a configured block start from 0.
+    pub fn factory_deployment_block(&self, factory_address: &str) -> u64 {
+        self.factory_deployment_blocks.get(factory_address).copied().unwrap_or(0)
+    }
+
+    // Find the exchange with the given factory address: either a configured
+    // exchange or, if allowed, one whose interface was detected by probing.
+    pub fn exchange(&self, factory_address: &str) -> Option<Exchange> {
+        if let Some(exchange) = self.exchanges.get(factory_address) {
+            return Some(exchange.clone());
+        }
+
+        match self.detected_exchanges.lock().unwrap().get(factory_address) {
+            Some(detected) if detected.verified || self.allow_unverified_exchanges => {
+                Exchange::from_registry(detected)
+            },
+            _ => None
+        }
+    }
+
+    // Whether the exchange with the given factory address is configured,
+    // or has been marked as verified in the exchange registry.
+    pub fn is_exchange_verified(&self, factory_address: &str) -> bool {
+        self.exchanges.contains_key(factory_address) || matches!(
+            self.detected_exchanges.lock().unwrap().get(factory_address),
+            Some(detected) if detected.verified)
+    }
+
+    // Every exchange that `exchange` would find, by factory address.
+    pub fn supported_exchanges(&self) -> HashMap<String, Exchange> {
+        let mut exchanges = self.exchanges.clone();
+
+        // Copy the registry out, so it isn't locked while the exchanges are built.
+        let detected_exchanges = self.detected_exchanges.lock().unwrap()
+            .values()
+            .cloned()
+            .collect::<Vec<models::DetectedExchange>>();
+
+        for detected in detected_exchanges.iter() {
+            if !(detected.verified || self.allow_unverified_exchanges) {
+                continue;
+            }
+
+            if let Some(exchange) = Exchange::from_registry(detected) {
+                exchanges.entry(detected.factory_address.clone()).or_insert(exchange);
+            }
+        }
+
+        exchanges
+    }
+
+    // The blockchain, as scan jobs need it.
+    pub fn chain_params(&self) -> ChainParams {
+        ChainParams {
+            provider_url: self.provider_url.clone(),
+            chain_id: self.chain_id,
+            chain_kind: self.chain_kind,
+            native_decimals: self.native_token.decimals
+        }
+    }
+
+    // Remember an exchange from the exchange registry.
+    pub fn register_exchange(&self, detected: models::DetectedExchange) {
+        self.detected_exchanges.lock().unwrap().insert(detected.factory_address.clone(), detected);
+    }
 }
 
 pub struct NativeToken {
@@ -30,6 +128,31 @@ pub struct NativeToken {
     pub decimals: u8
 }
 
+// The result of the most recent `eth_chainId` check
+// against a blockchain's provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProviderStatus {
+    Unverified,
+    Verified,
+    Mismatch { reported_chain_id: u64 }
+}
+
+// How a blockchain charges for transactions. Rollups also charge
+// for posting transaction data to L1, which isn't in `gas_price * gas_used`
+// on every rollup, so their receipts have to be read differently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainKind {
+    L1,
+    Optimism, // OP Stack: the L1 data fee is charged on top, as the receipt's `l1Fee`
+    Arbitrum // Nitro: the L1 data fee is already in `gasUsed`, as `gasUsedForL1`
+}
+
+impl ChainKind {
+    pub fn is_rollup(&self) -> bool {
+        *self != ChainKind::L1
+    }
+}
+
 // Each exchange that this application interacts with
 // will have helper data stored in an Exchange instance.
 #[derive(Debug, Clone)]
@@ -45,16 +168,46 @@ impl Exchange {
             Exchange::V3 { name } => name
         }
     }
+
+    // Build an exchange from its entry in the exchange registry,
+    // or return `None` if it has an unknown kind.
+    pub fn from_registry(detected: &models::DetectedExchange) -> Option<Self> {
+        let name = detected.exchange_name.clone();
+
+        match detected.exchange_kind.as_str() {
+            "v2" => Some(Exchange::V2 { name }),
+            "v3" => Some(Exchange::V3 { name }),
+            _ => None
+        }
+    }
 }
 
 // This function should be called on server startup
 // to initialize the application's global, shared state.
 pub fn init_app_state() -> Data<AppState> {
-    Data::new(AppState {
+    let app_state = AppState {
         app_name: Mutex::new(String::from("Sandwich Lab")),
         db_pool: db::init_db_pool(),
         blockchains: init_blockchains() // this function is defined below
-    })
+    };
+
+    // Load the exchanges that were detected (by probing their pairs) in earlier runs.
+    let db_connection = app_state.db_pool.get().expect("error getting database connection");
+
+    for (str_id, blockchain) in app_state.blockchains.iter() {
+        for detected in db::fetch_detected_exchanges(&db_connection, str_id)
+            .expect("error loading detected exchanges") {
+
+            blockchain.register_exchange(detected);
+        }
+    }
+
+    Data::new(app_state)
+}
+
+// Read a yes/no setting from the environment, e.g. `ETHEREUM_ALLOW_UNVERIFIED_EXCHANGES=true`.
+fn env_flag(name: &str) -> bool {
+    env::var(name).is_ok_and(|value| matches!(value.to_lowercase().as_str(), "1" | "true" | "yes"))
 }
 
 // For now, hardcode which blockchains and exchanges are supported.
@@ -65,10 +218,12 @@ fn init_blockchains() -> HashMap<String, Blockchain> {
             "arbitrum".to_string(),
             Blockchain {
                 name: "Arbitrum".to_string(),
+                chain_id: 42161,
+                chain_kind: ChainKind::Arbitrum,
                 provider_url: env::var("ARBITRUM_URL")
                     .expect("error reading provider url"),
-                data_aggregator_address: env::var("ARBITRUM_DATA_AGGREGATOR")
-                    .expect("error reading data aggregator address"),
+                data_aggregator_address: env::var("ARBITRUM_DATA_AGGREGATOR").ok(),
+                allow_unverified_exchanges: env_flag("ARBITRUM_ALLOW_UNVERIFIED_EXCHANGES"),
                 exchanges: HashMap::from([
                     (
                         "0x1F98431c8aD98523631AE4a59f267346ea31F984".to_lowercase(),
@@ -77,27 +232,43 @@ fn init_blockchains() -> HashMap<String, Blockchain> {
                         }
                     )
                 ]),
+                factory_deployment_blocks: HashMap::from([
+                    ("0x1F98431c8aD98523631AE4a59f267346ea31F984".to_lowercase(), 165)
+                ]),
                 scanner_params: Params {
                     blocks_per_chunk: 1_000,
                     max_blocks_per_chunk: 10_000,
                     target_swaps_per_chunk: 300,
-                    max_blocks_per_request: 100_000
+                    max_blocks_per_request: 100_000,
+                    confirmations: 20
+                },
+                detector_params: DetectorParams {
+                    kind: DetectorKind::AmountRatio,
+                    tolerance: DEFAULT_TOLERANCE,
+                    exchange_tolerances: HashMap::new(),
+                    compare: false
                 },
                 native_token: NativeToken {
                     name: "Ethereum".to_string(),
                     symbol: "ETH".to_string(),
                     decimals: 18
-                }
+                },
+                provider_status: Mutex::new(ProviderStatus::Unverified),
+                detected_exchanges: Mutex::new(HashMap::new()),
+                rescanning_blocks: Arc::new(Mutex::new(HashSet::new())),
+                block_timestamps: Mutex::new(BTreeMap::new())
             }
         ),
         (
             "avalanche".to_string(),
             Blockchain {
                 name: "Avalanche".to_string(),
+                chain_id: 43114,
+                chain_kind: ChainKind::L1,
                 provider_url: env::var("AVALANCHE_URL")
                     .expect("error reading provider url"),
-                data_aggregator_address: env::var("AVALANCHE_DATA_AGGREGATOR")
-                    .expect("error reading data aggregator address"),
+                data_aggregator_address: env::var("AVALANCHE_DATA_AGGREGATOR").ok(),
+                allow_unverified_exchanges: env_flag("AVALANCHE_ALLOW_UNVERIFIED_EXCHANGES"),
                 exchanges: HashMap::from([
                     (
                         "0x9Ad6C38BE94206cA50bb0d90783181662f0Cfa10".to_lowercase(),
@@ -118,27 +289,41 @@ fn init_blockchains() -> HashMap<String, Blockchain> {
                         }
                     )
                 ]),
+                factory_deployment_blocks: HashMap::new(),
                 scanner_params: Params {
                     blocks_per_chunk: 1_000,
                     max_blocks_per_chunk: 10_000,
                     target_swaps_per_chunk: 300,
-                    max_blocks_per_request: 100_000
+                    max_blocks_per_request: 100_000,
+                    confirmations: 1
+                },
+                detector_params: DetectorParams {
+                    kind: DetectorKind::AmountRatio,
+                    tolerance: DEFAULT_TOLERANCE,
+                    exchange_tolerances: HashMap::new(),
+                    compare: false
                 },
                 native_token: NativeToken {
                     name: "Avalanche".to_string(),
                     symbol: "AVAX".to_string(),
                     decimals: 18
-                }
+                },
+                provider_status: Mutex::new(ProviderStatus::Unverified),
+                detected_exchanges: Mutex::new(HashMap::new()),
+                rescanning_blocks: Arc::new(Mutex::new(HashSet::new())),
+                block_timestamps: Mutex::new(BTreeMap::new())
             }
         ),*/
         (
             "ethereum".to_string(), 
             Blockchain { 
-                name: "Ethereum".to_string(), 
+                name: "Ethereum".to_string(),
+                chain_id: 1,
+                chain_kind: ChainKind::L1,
                 provider_url: env::var("ETHEREUM_URL")
                     .expect("error reading provider url"),
-                data_aggregator_address: env::var("ETHEREUM_DATA_AGGREGATOR")
-                    .expect("error reading data aggregator address"),
+                data_aggregator_address: env::var("ETHEREUM_DATA_AGGREGATOR").ok(),
+                allow_unverified_exchanges: env_flag("ETHEREUM_ALLOW_UNVERIFIED_EXCHANGES"),
                 exchanges: HashMap::from([
                     (
                         "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f".to_lowercase(),
@@ -159,27 +344,45 @@ fn init_blockchains() -> HashMap<String, Blockchain> {
                         }
                     )
                 ]),
+                factory_deployment_blocks: HashMap::from([
+                    ("0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f".to_lowercase(), 10_000_835),
+                    ("0x1F98431c8aD98523631AE4a59f267346ea31F984".to_lowercase(), 12_369_621),
+                    ("0xC0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac".to_lowercase(), 10_794_229)
+                ]),
                 scanner_params: Params {
                     blocks_per_chunk: 1_000,
                     max_blocks_per_chunk: 10_000,
                     target_swaps_per_chunk: 300,
-                    max_blocks_per_request: 100_000
+                    max_blocks_per_request: 100_000,
+                    confirmations: 12
+                },
+                detector_params: DetectorParams {
+                    kind: DetectorKind::AmountRatio,
+                    tolerance: DEFAULT_TOLERANCE,
+                    exchange_tolerances: HashMap::new(),
+                    compare: false
                 },
                 native_token: NativeToken {
                     name: "Ethereum".to_string(),
                     symbol: "ETH".to_string(),
                     decimals: 18
-                }
+                },
+                provider_status: Mutex::new(ProviderStatus::Unverified),
+                detected_exchanges: Mutex::new(HashMap::new()),
+                rescanning_blocks: Arc::new(Mutex::new(HashSet::new())),
+                block_timestamps: Mutex::new(BTreeMap::new())
             }
         ),
         (
             "goerli".to_string(),
             Blockchain {
                 name: "Goerli".to_string(),
+                chain_id: 5,
+                chain_kind: ChainKind::L1,
                 provider_url: env::var("GOERLI_URL")
                     .expect("error reading provider url"),
-                data_aggregator_address: env::var("GOERLI_DATA_AGGREGATOR")
-                    .expect("error reading data aggregator address"),
+                data_aggregator_address: env::var("GOERLI_DATA_AGGREGATOR").ok(),
+                allow_unverified_exchanges: env_flag("GOERLI_ALLOW_UNVERIFIED_EXCHANGES"),
                 exchanges: HashMap::from([
                     (
                         "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f".to_lowercase(),
@@ -188,27 +391,41 @@ fn init_blockchains() -> HashMap<String, Blockchain> {
                         }
                     )
                 ]),
+                factory_deployment_blocks: HashMap::new(),
                 scanner_params: Params {
                     blocks_per_chunk: 1_000,
                     max_blocks_per_chunk: 10_000,
                     target_swaps_per_chunk: 300,
-                    max_blocks_per_request: 100_000
+                    max_blocks_per_request: 100_000,
+                    confirmations: 12
+                },
+                detector_params: DetectorParams {
+                    kind: DetectorKind::AmountRatio,
+                    tolerance: DEFAULT_TOLERANCE,
+                    exchange_tolerances: HashMap::new(),
+                    compare: false
                 },
                 native_token: NativeToken {
                     name: "Ethereum".to_string(),
                     symbol: "ETH".to_string(),
                     decimals: 18
-                }
+                },
+                provider_status: Mutex::new(ProviderStatus::Unverified),
+                detected_exchanges: Mutex::new(HashMap::new()),
+                rescanning_blocks: Arc::new(Mutex::new(HashSet::new())),
+                block_timestamps: Mutex::new(BTreeMap::new())
             }
         )/*,
         (
             "moonbeam".to_string(),
             Blockchain {
                 name: "Moonbeam".to_string(),
+                chain_id: 1284,
+                chain_kind: ChainKind::L1,
                 provider_url: env::var("MOONBEAM_URL")
                     .expect("error reading provider url"),
-                data_aggregator_address: env::var("MOONBEAM_DATA_AGGREGATOR")
-                    .expect("error reading data aggregator address"),
+                data_aggregator_address: env::var("MOONBEAM_DATA_AGGREGATOR").ok(),
+                allow_unverified_exchanges: env_flag("MOONBEAM_ALLOW_UNVERIFIED_EXCHANGES"),
                 exchanges: HashMap::from([
                     (
                         "0x19B85ae92947E0725d5265fFB3389e7E4F191FDa".to_lowercase(),
@@ -235,27 +452,41 @@ fn init_blockchains() -> HashMap<String, Blockchain> {
                         }
                     )
                 ]),
+                factory_deployment_blocks: HashMap::new(),
                 scanner_params: Params {
                     blocks_per_chunk: 100,
                     max_blocks_per_chunk: 1_000,
                     target_swaps_per_chunk: 100,
-                    max_blocks_per_request: 10_000
+                    max_blocks_per_request: 10_000,
+                    confirmations: 2
+                },
+                detector_params: DetectorParams {
+                    kind: DetectorKind::AmountRatio,
+                    tolerance: DEFAULT_TOLERANCE,
+                    exchange_tolerances: HashMap::new(),
+                    compare: false
                 },
                 native_token: NativeToken {
                     name: "Glimmer".to_string(),
                     symbol: "GLMR".to_string(),
                     decimals: 18
-                }
+                },
+                provider_status: Mutex::new(ProviderStatus::Unverified),
+                detected_exchanges: Mutex::new(HashMap::new()),
+                rescanning_blocks: Arc::new(Mutex::new(HashSet::new())),
+                block_timestamps: Mutex::new(BTreeMap::new())
             }
         ),
         (
             "moonriver".to_string(),
             Blockchain {
                 name: "Moonriver".to_string(),
+                chain_id: 1285,
+                chain_kind: ChainKind::L1,
                 provider_url: env::var("MOONRIVER_URL")
                     .expect("error reading provider url"),
-                data_aggregator_address: env::var("MOONRIVER_DATA_AGGREGATOR")
-                    .expect("error reading data aggregator address"),
+                data_aggregator_address: env::var("MOONRIVER_DATA_AGGREGATOR").ok(),
+                allow_unverified_exchanges: env_flag("MOONRIVER_ALLOW_UNVERIFIED_EXCHANGES"),
                 exchanges: HashMap::from([
                     (
                         "0x049581aEB6Fe262727f290165C29BDAB065a1B68".to_lowercase(),
@@ -276,27 +507,42 @@ fn init_blockchains() -> HashMap<String, Blockchain> {
                         }
                     )
                 ]),
+                factory_deployment_blocks: HashMap::new(),
                 scanner_params: Params {
                     blocks_per_chunk: 100,
                     max_blocks_per_chunk: 1_000,
                     target_swaps_per_chunk: 100,
-                    max_blocks_per_request: 10_000
+                    max_blocks_per_request: 10_000,
+                    confirmations: 2
+                },
+                detector_params: DetectorParams {
+                    kind: DetectorKind::AmountRatio,
+                    tolerance: DEFAULT_TOLERANCE,
+                    exchange_tolerances: HashMap::new(),
+                    compare: false
                 },
                 native_token: NativeToken {
                     name: "Moonriver".to_string(),
                     symbol: "MOVR".to_string(),
                     decimals: 18
-                }
+                },
+                provider_status: Mutex::new(ProviderStatus::Unverified),
+                detected_exchanges: Mutex::new(HashMap::new()),
+                rescanning_blocks: Arc::new(Mutex::new(HashSet::new())),
+                block_timestamps: Mutex::new(BTreeMap::new())
             }
         ),
         (
             "optimism".to_string(),
             Blockchain {
                 name: "Optimism".to_string(),
+                chain_id: 10,
+                chain_kind: ChainKind::Optimism,
                 provider_url: env::var("OPTIMISM_URL")
                     .expect("error reading provider url"),
                 data_aggregator_address: env::var("OPTIMISM_DATA_AGGREGATOR")
                     .expect("error readign data aggregator address"),
+                allow_unverified_exchanges: env_flag("OPTIMISM_ALLOW_UNVERIFIED_EXCHANGES"),
                 exchanges: HashMap::from([
                     (
                         "0x1F98431c8aD98523631AE4a59f267346ea31F984".to_lowercase(),
@@ -305,27 +551,41 @@ fn init_blockchains() -> HashMap<String, Blockchain> {
                         }
                     )
                 ]),
+                factory_deployment_blocks: HashMap::new(),
                 scanner_params: Params {
                     blocks_per_chunk: 1_000,
                     max_blocks_per_chunk: 10_000,
                     target_swaps_per_chunk: 300,
-                    max_blocks_per_request: 100_000
+                    max_blocks_per_request: 100_000,
+                    confirmations: 20
+                },
+                detector_params: DetectorParams {
+                    kind: DetectorKind::AmountRatio,
+                    tolerance: DEFAULT_TOLERANCE,
+                    exchange_tolerances: HashMap::new(),
+                    compare: false
                 },
                 native_token: NativeToken {
                     name: "Ethereum".to_string(),
                     symbol: "ETH".to_string(),
                     decimals: 18
-                }
+                },
+                provider_status: Mutex::new(ProviderStatus::Unverified),
+                detected_exchanges: Mutex::new(HashMap::new()),
+                rescanning_blocks: Arc::new(Mutex::new(HashSet::new())),
+                block_timestamps: Mutex::new(BTreeMap::new())
             }
         ),
         (
             "polygon".to_string(),
             Blockchain {
                 name: "Polygon".to_string(),
+                chain_id: 137,
+                chain_kind: ChainKind::L1,
                 provider_url: env::var("POLYGON_URL")
                     .expect("error reading provider url"),
-                data_aggregator_address: env::var("POLYGON_DATA_AGGREGATOR")
-                    .expect("error reading data aggregator address"),
+                data_aggregator_address: env::var("POLYGON_DATA_AGGREGATOR").ok(),
+                allow_unverified_exchanges: env_flag("POLYGON_ALLOW_UNVERIFIED_EXCHANGES"),
                 exchanges: HashMap::from([
                     (
                         "0x5757371414417b8C6CAad45bAeF941aBc7d3Ab32".to_lowercase(),
@@ -346,18 +606,110 @@ fn init_blockchains() -> HashMap<String, Blockchain> {
                         }
                     )
                 ]),
+                factory_deployment_blocks: HashMap::from([
+                    ("0x1F98431c8aD98523631AE4a59f267346ea31F984".to_lowercase(), 22_757_547)
+                ]),
                 scanner_params: Params {
                     blocks_per_chunk: 1_000,
                     max_blocks_per_chunk: 10_000,
                     target_swaps_per_chunk: 1_000,
-                    max_blocks_per_request: 100_000
+                    max_blocks_per_request: 100_000,
+                    confirmations: 64
+                },
+                detector_params: DetectorParams {
+                    kind: DetectorKind::AmountRatio,
+                    tolerance: DEFAULT_TOLERANCE,
+                    exchange_tolerances: HashMap::new(),
+                    compare: false
                 },
                 native_token: NativeToken {
                     name: "Matic".to_string(),
                     symbol: "MATIC".to_string(),
                     decimals: 18
-                }
+                },
+                provider_status: Mutex::new(ProviderStatus::Unverified),
+                detected_exchanges: Mutex::new(HashMap::new()),
+                rescanning_blocks: Arc::new(Mutex::new(HashSet::new())),
+                block_timestamps: Mutex::new(BTreeMap::new())
             }
         )*/
     ])
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blockchain(allow_unverified_exchanges: bool) -> Blockchain {
+        Blockchain {
+            name: "Ethereum".to_string(),
+            chain_id: 1,
+            chain_kind: ChainKind::L1,
+            provider_url: "http://127.0.0.1:8545".to_string(),
+            data_aggregator_address: None,
+            allow_unverified_exchanges,
+            exchanges: HashMap::from([
+                ("0xconfigured".to_string(), Exchange::V2 { name: "Uniswap V2".to_string() })
+            ]),
+            factory_deployment_blocks: HashMap::new(),
+            scanner_params: Params {
+                blocks_per_chunk: 1_000,
+                max_blocks_per_chunk: 10_000,
+                target_swaps_per_chunk: 300,
+                max_blocks_per_request: 100_000,
+                confirmations: 12
+            },
+            detector_params: DetectorParams {
+                kind: DetectorKind::AmountRatio,
+                tolerance: DEFAULT_TOLERANCE,
+                exchange_tolerances: HashMap::new(),
+                compare: false
+            },
+            native_token: NativeToken {
+                name: "Ethereum".to_string(),
+                symbol: "ETH".to_string(),
+                decimals: 18
+            },
+            provider_status: Mutex::new(ProviderStatus::Verified),
+            detected_exchanges: Mutex::new(HashMap::new()),
+            rescanning_blocks: Arc::new(Mutex::new(HashSet::new())),
+            block_timestamps: Mutex::new(BTreeMap::new())
+        }
+    }
+
+    fn detected(factory_address: &str, exchange_kind: &str, verified: bool) -> models::DetectedExchange {
+        models::DetectedExchange {
+            exchange_id: 1,
+            blockchain_str_id: "ethereum".to_string(),
+            factory_address: factory_address.to_string(),
+            exchange_name: "Detected".to_string(),
+            exchange_kind: exchange_kind.to_string(),
+            verified
+        }
+    }
+
+    fn supported(blockchain: &Blockchain) -> Vec<String> {
+        let mut factory_addresses = blockchain.supported_exchanges().into_keys().collect::<Vec<String>>();
+        factory_addresses.sort();
+        factory_addresses
+    }
+
+    #[test]
+    fn supported_exchanges_include_verified_detected_exchanges() {
+        let blockchain = blockchain(false);
+        blockchain.register_exchange(detected("0xverified", "v3", true));
+        blockchain.register_exchange(detected("0xunverified", "v2", false));
+        blockchain.register_exchange(detected("0xunknown", "v4", true));
+
+        assert_eq!(supported(&blockchain), vec!["0xconfigured", "0xverified"]);
+        assert!(matches!(blockchain.supported_exchanges()["0xverified"], Exchange::V3 { .. }));
+    }
+
+    #[test]
+    fn supported_exchanges_include_unverified_detected_exchanges_if_allowed() {
+        let blockchain = blockchain(true);
+        blockchain.register_exchange(detected("0xunverified", "v2", false));
+
+        assert_eq!(supported(&blockchain), vec!["0xconfigured", "0xunverified"]);
+    }
+}
@@ -5,6 +5,7 @@ use super::base::wrap_in_html;
 struct IndexPage<'a> {
     title: &'a str,
     inspect_url: &'a str,
+    api_search_url: &'a str,
     blockchains: Vec<Blockchain<'a>>
 }
 
@@ -17,6 +18,7 @@ pub struct Blockchain<'a> {
 pub fn render(
     title: &str, 
     inspect_url: &str,
+    api_search_url: &str,
     blockchains: Vec<Blockchain>
 ) -> String {
     let head_content = r##"
@@ -56,10 +58,66 @@ pub fn render(
                 id="pair-button" 
                 class="options__button button">Search for Sandwiches</button>
         </form>
+        <div class="search">
+            <p class="options__instructions">Or Find a Pair by Token</p>
+            <input 
+                type="search" 
+                id="pair-search" 
+                placeholder="e.g. WETH, Wrapped Ether or 0xC02aaA39" 
+                class="options__pair__address" />
+            <ul id="search-results" class="search__results"></ul>
+        </div>
     "##;
 
     let script_content = r##"
+        // Search the pairs as the user types, on the chosen blockchain (if any),
+        // and list each match as a link to its inspect page.
+        const searchInput = document.getElementById("pair-search");
+        const searchResults = document.getElementById("search-results");
+        let searchTimeout;
+
+        searchInput.addEventListener("input", () => {
+            clearTimeout(searchTimeout);
+            searchTimeout = setTimeout(async () => {
+                const query = searchInput.value.trim();
+                empty(searchResults);
+
+                if (!query) return;
+
+                const params = new URLSearchParams({ q: query });
+                const blockchain = document.querySelector("input[name=blockchain]:checked");
+
+                if (blockchain) params.set("blockchain", blockchain.value);
+
+                const response = await fetch(`{{api_search_url}}?${params.toString()}`);
+                const data = await response.json();
+
+                // Ignore responses to outdated queries.
+                if (searchInput.value.trim() != query) return;
+
+                if (!data.pairs) {
+                    const message = data.error_message || "No pairs found.";
+                    searchResults.appendChild(create("li", ["search__result"], message));
+                    return;
+                }
+
+                for (const pair of data.pairs) {
+                    const fee = pair.fee_tier == null ? "" : `, ${pair.fee_tier / 10000}%`;
+                    const link = create(
+                        "a",
+                        ["search__result__link"],
+                        `${pair.base.symbol}-${pair.quote.symbol} (${pair.exchange_name}${fee}) on ${pair.blockchain}`);
+                    link.href = `{{inspect_url}}?${new URLSearchParams({
+                        blockchain: pair.blockchain,
+                        pair: pair.address
+                    }).toString()}`;
 
+                    const item = create("li", ["search__result"]);
+                    item.appendChild(link);
+                    searchResults.appendChild(item);
+                }
+            }, 250);
+        });
     "##;
 
     let source = wrap_in_html(head_content, inner_content, script_content);
@@ -68,6 +126,7 @@ pub fn render(
     template.render(&IndexPage {
         title,
         inspect_url,
+        api_search_url,
         blockchains
     })
 }
\ No newline at end of file
@@ -57,9 +57,10 @@ pub fn render(
         const response = await fetch(apiEndpoint);
         const data = await response.json();
 
-        // Set the exchange name.
+        // Set the exchange name, flagging exchanges that were only detected.
         setElement("card-label__exchange", (element) => {
-            element.textContent = `(${data.pair.exchange_name})`;
+            const unverified = data.pair.exchange_verified ? "" : ", detected, unverified";
+            element.textContent = `(${data.pair.exchange_name}${unverified})`;
         });
         
         // Update the page title.
@@ -201,7 +201,7 @@ class Paginator {
     renderSandwich(index, sandwich) {
         const sandwichElement = create("div", ["sandwiches__element", "card", "col"]);
         const sandwichElementLabel = create(
-            "div", ["sandwiches__element__label", "card-label"], `Block ${sandwich.blockNumber.toLocaleString()}`);
+            "div", ["sandwiches__element__label", "card-label"], sandwich.label());
         const sandwichElementBody = create("div", ["sandwiches__element__body", "card-body"]);
         const sandwichElementFooter = create("div", ["sandwiches__element__footer", "card-footer", "row"]);
         const sandwichElementFooterLeft = create("div", ["sandwiches__element__footer__left"]);
@@ -214,14 +214,17 @@ class Paginator {
         sandwichElementBody.appendChild(this.renderSwap("Frontrun", sandwich.frontrun));
 
         for (let i = 0; i < sandwich.lunchmeat.length; i++) {
-            sandwichElementBody.appendChild(this.renderSwap("Lunchmeat", sandwich.lunchmeat[i]));
+            const label = sandwich.lunchmeat[i].victim ? "Victim" : "Bystander";
+            sandwichElementBody.appendChild(this.renderSwap(label, sandwich.lunchmeat[i]));
         }
 
         sandwichElementBody.appendChild(this.renderSwap("Backrun", sandwich.backrun));
 
         const baseProfit = sandwich.attackerBaseProfit();
         const quoteProfit = sandwich.attackerQuoteProfit();
-        const gas = sandwich.frontrun.gas + sandwich.backrun.gas;
+        const gas = sandwich.frontrun.gas + sandwich.backrun.gas + sandwich.bribe;
+        const validatorProfit = sandwich.validatorProfit();
+        const burned = gas - validatorProfit;
 
         const baseProfitElement = create("span", ["revenue__base-profit"], 
             `${baseProfit < 0 ? "" : "+"}${baseProfit.toLocaleString()} ${this.tokenMetadata.baseSymbol}`);
@@ -230,9 +233,10 @@ class Paginator {
             `${quoteProfit < 0 ? "" : "+"}${quoteProfit.toLocaleString()} ${this.tokenMetadata.quoteSymbol}`);
         const attackerProfitElement = create("p", ["revenue__attacker-profit", "revenue__text"]);
         const attackerGasElement = create("p", ["revenue__gas", "revenue__text", "negative"],
-            `-${gas.toLocaleString()} ${this.tokenMetadata.nativeSymbol}`);
+            `-${gas.toLocaleString()} ${this.tokenMetadata.nativeSymbol}` +
+            (burned > 0 ? ` (${burned.toLocaleString()} burned)` : ""));
         const minerGasElement = create("p", ["revenue__gas", "revenue__text", "positive"],
-            `+${gas.toLocaleString()} ${this.tokenMetadata.nativeSymbol}`);
+            `+${validatorProfit.toLocaleString()} ${this.tokenMetadata.nativeSymbol}`);
 
         if (baseProfit > 0) baseProfitElement.classList.add("positive");
         if (quoteProfit > 0) quoteProfitElement.classList.add("positive");
@@ -248,11 +252,11 @@ class Paginator {
         sandwichElementFooterLeft.appendChild(attackerProfitElement);
 
         sandwichElementFooterCenter.appendChild(create(
-            "h3", ["revenue__title", "revenue__text"], `Attacker Gas Fees`));
+            "h3", ["revenue__title", "revenue__text"], `Attacker Gas Fees & Bribes`));
         sandwichElementFooterCenter.appendChild(attackerGasElement);
 
         sandwichElementFooterRight.appendChild(create(
-            "h3", ["revenue__title", "revenue__text"], `Miner Profit`));
+            "h3", ["revenue__title", "revenue__text"], `Validator Profit`));
         sandwichElementFooterRight.appendChild(minerGasElement);
 
         sandwichElement.appendChild(sandwichElementLabel);
@@ -291,7 +295,11 @@ class Paginator {
             apiSandwich.block_number,
             this.parseSwap(apiSandwich.frontrun),
             apiSandwich.lunchmeat.map(this.parseSwap),
-            this.parseSwap(apiSandwich.backrun));
+            this.parseSwap(apiSandwich.backrun),
+            apiSandwich.base_profit,
+            apiSandwich.quote_profit,
+            apiSandwich.bribe,
+            apiSandwich.timestamp);
     }
 
     parseSwap(apiSwap) {
@@ -302,7 +310,10 @@ class Paginator {
             apiSwap.quote_in,
             apiSwap.base_out,
             apiSwap.quote_out,
-            apiSwap.gas);
+            apiSwap.gas,
+            apiSwap.victim,
+            apiSwap.gas_burned,
+            apiSwap.gas_tip);
     }
 
     async padSleep(start) {
@@ -357,20 +368,45 @@ class TokenMetadata {
 }
 
 class Sandwich {
-    constructor(blockNumber, frontrun, lunchmeat, backrun) {
+    constructor(blockNumber, frontrun, lunchmeat, backrun, baseProfit=null, quoteProfit=null, bribe=null, timestamp=null) {
         this.blockNumber = blockNumber;
+        this.timestamp = timestamp; // in seconds, if the block was recorded
         this.frontrun = frontrun;
         this.lunchmeat = lunchmeat;
         this.backrun = backrun;
+        this.baseProfit = baseProfit;
+        this.quoteProfit = quoteProfit;
+        this.bribe = bribe ?? 0; // paid directly to the block's fee recipient
     }
 
+    label() {
+        const block = `Block ${this.blockNumber.toLocaleString()}`;
+        if (this.timestamp == null) return block;
+
+        return `${block} (${new Date(this.timestamp * 1000).toLocaleString()})`;
+    }
+
+    // Prefer the token-flow profit from the API, falling back
+    // on the pair amounts for sandwiches scanned without it.
     attackerBaseProfit() {
+        if (this.baseProfit != null) return this.baseProfit;
+
         const longProfit = this.backrun.baseOut - this.frontrun.baseIn;
         const shortProfit = this.frontrun.baseOut - this.backrun.baseIn;
         return longProfit + shortProfit;
     }
 
+    // What the block's fee recipient earned from the attacker: priority tips
+    // and bribes, but not burned base fees. Sandwiches scanned before the
+    // split was recorded have no tip, so count their whole gas fee.
+    validatorProfit() {
+        const tip = swap => swap.gasTip ?? swap.gas;
+        return tip(this.frontrun) + tip(this.backrun) + this.bribe;
+    }
+
     attackerQuoteProfit() {
+        if (this.quoteProfit != null) return this.quoteProfit;
+
         const longProfit = this.backrun.quoteOut - this.frontrun.quoteIn;
         const shortProfit = this.frontrun.quoteOut - this.backrun.quoteIn;
         return longProfit + shortProfit;
@@ -378,7 +414,7 @@ class Sandwich {
 }
 
 class Swap {
-    constructor(hash, index, baseIn, quoteIn, baseOut, quoteOut, gas) {
+    constructor(hash, index, baseIn, quoteIn, baseOut, quoteOut, gas, victim=false, gasBurned=null, gasTip=null) {
         this.hash = hash;
         this.index = index;
         this.baseIn = baseIn;
@@ -386,6 +422,9 @@ class Swap {
         this.baseOut = baseOut;
         this.quoteOut = quoteOut;
         this.gas = gas;
+        this.victim = victim;
+        this.gasBurned = gasBurned;
+        this.gasTip = gasTip;
     }
 
     inputSpan(tokenMetadata) {
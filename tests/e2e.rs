@@ -0,0 +1,236 @@
+// End-to-end tests: start a local Hardhat node, deploy a Uniswap V2 pair and
+// the DataAggregator with `data_aggregator/scripts/e2e.js` (which also mines
+// blocks with scripted sandwiches and non-sandwiches), start the app against
+// it, and check that the API reports exactly the scripted sandwiches.
+//
+// These need Node.js, `npm install` in `data_aggregator`, free ports 8545 and
+// 8731, and a Postgres database in `TEST_DATABASE_URL`, which is emptied first
+// (so its name must contain "test").
+// Run them with `cargo test --test e2e -- --ignored`.
+
+#[macro_use] extern crate diesel;
+#[macro_use] extern crate diesel_migrations;
+
+use diesel::prelude::*;
+use diesel::sql_query;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::env;
+use std::io::{ Read, Write };
+use std::net::TcpStream;
+use std::path::{ Path, PathBuf };
+use std::process::{ Child, Command, Stdio };
+use std::thread;
+use std::time::{ Duration, Instant };
+
+const NODE_URL: &str = "http://127.0.0.1:8545";
+const NODE_SOCKET: &str = "127.0.0.1:8545";
+const APP_SOCKET: &str = "127.0.0.1:8731";
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+embed_migrations!();
+
+// A sandwich, identified by its block and its frontrun, lunchmeat and backrun hashes.
+type SandwichKey = (i64, String, Vec<String>, String);
+
+// A row of `pg_tables`, when listing the test database's tables.
+#[derive(QueryableByName)]
+struct Table {
+    #[sql_type = "diesel::sql_types::Text"]
+    tablename: String
+}
+
+// Kill the child process (a node or the app) when the test ends, even on failure.
+struct KillOnDrop(Child);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        self.0.kill().ok();
+        self.0.wait().ok();
+    }
+}
+
+fn data_aggregator_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("data_aggregator")
+}
+
+// Run the Hardhat CLI in `data_aggregator`, with placeholders for the
+// network settings its config requires but these tests don't use.
+fn hardhat(args: &[&str]) -> Command {
+    let mut command = Command::new(data_aggregator_dir().join("node_modules").join(".bin").join("hardhat"));
+    command.args(args).current_dir(data_aggregator_dir());
+
+    for network in ["ARBITRUM", "AVALANCHE", "ETHEREUM", "GOERLI", "MOONBEAM", "MOONRIVER", "OPTIMISM", "POLYGON"] {
+        let url = format!("{network}_URL");
+
+        if env::var(&url).is_err() {
+            command.env(url, NODE_URL);
+        }
+    }
+
+    if env::var("PRIVATE_KEY").is_err() {
+        command.env("PRIVATE_KEY", format!("0x{}", "11".repeat(32)));
+    }
+
+    command
+}
+
+// Wait until something is listening on the given socket.
+fn wait_for(socket: &str, child: &mut Child) {
+    let start = Instant::now();
+
+    while TcpStream::connect(socket).is_err() {
+        if let Ok(Some(status)) = child.try_wait() {
+            panic!("process exited with {status} before listening on {socket}");
+        }
+
+        assert!(start.elapsed() < TIMEOUT, "nothing started listening on {socket}");
+        thread::sleep(Duration::from_millis(250));
+    }
+}
+
+// The database in `TEST_DATABASE_URL`, which is refused unless its
+// name contains "test", since every table in it will be emptied.
+fn test_database_url() -> String {
+    let db_url = env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must be set");
+    let db_name = db_url.split('?').next().unwrap_or_default().rsplit('/').next().unwrap_or_default();
+
+    assert!(
+        db_name.to_lowercase().contains("test"),
+        "refusing to empty the database {db_name:?}: TEST_DATABASE_URL must name a database with \"test\" in its name");
+
+    db_url
+}
+
+// Empty every table in the test database, after running any pending migrations.
+fn reset_database(db_url: &str) {
+    let connection = PgConnection::establish(db_url).expect("error connecting to TEST_DATABASE_URL");
+    embedded_migrations::run(&connection).expect("error running pending migrations");
+
+    let tables = sql_query(
+        "SELECT tablename FROM pg_tables \
+        WHERE schemaname = 'public' AND tablename <> '__diesel_schema_migrations'")
+        .load::<Table>(&connection)
+        .unwrap()
+        .into_iter()
+        .map(|table| format!("\"{}\"", table.tablename))
+        .collect::<Vec<String>>();
+
+    sql_query(format!("TRUNCATE {} RESTART IDENTITY CASCADE", tables.join(", ")))
+        .execute(&connection)
+        .unwrap();
+}
+
+// Send a GET request to the app, and parse the JSON response.
+fn get(path: &str) -> Value {
+    let mut stream = TcpStream::connect(APP_SOCKET).expect("error connecting to the app");
+    write!(stream, "GET {path} HTTP/1.1\r\nHost: {APP_SOCKET}\r\nConnection: close\r\n\r\n").unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let (head, body) = response.split_once("\r\n\r\n").expect("malformed response");
+    assert!(head.starts_with("HTTP/1.1 200"), "GET {path} returned {head}");
+
+    serde_json::from_str(body).unwrap_or_else(|e| panic!("GET {path} returned invalid JSON ({e}): {body}"))
+}
+
+fn as_str(value: &Value) -> String {
+    value.as_str().unwrap_or_default().to_lowercase()
+}
+
+#[test]
+#[ignore = "requires Node.js, ports 8545/8731 and TEST_DATABASE_URL"]
+fn api_reports_exactly_the_scripted_sandwiches() {
+    let db_url = test_database_url();
+    let output = env::temp_dir().join(format!("sandwich-lab-e2e-{}.json", std::process::id()));
+
+    // Start the node, then deploy the contracts and script the blocks.
+    let mut node = KillOnDrop(hardhat(&["node", "--hostname", "127.0.0.1", "--port", "8545"])
+        .stdout(Stdio::null())
+        .spawn()
+        .expect("error starting the Hardhat node (did you run `npm install` in data_aggregator?)"));
+    wait_for(NODE_SOCKET, &mut node.0);
+
+    let status = hardhat(&["run", "scripts/e2e.js", "--network", "localhost"])
+        .env("E2E_OUTPUT", &output)
+        .status()
+        .expect("error running scripts/e2e.js");
+    assert!(status.success(), "scripts/e2e.js failed");
+
+    let scripted: Value = serde_json::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+    std::fs::remove_file(&output).ok();
+
+    // Start the app against the node, on a fresh database.
+    reset_database(&db_url);
+
+    let mut app = KillOnDrop(Command::new(env!("CARGO_BIN_EXE_sandwich-lab-v2"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("DATABASE_URL", &db_url)
+        .env("ETHEREUM_URL", NODE_URL)
+        .env("ETHEREUM_DATA_AGGREGATOR", as_str(&scripted["data_aggregator"]))
+        .env("GOERLI_URL", NODE_URL) // on the wrong chain, so Goerli is never served
+        .env("GOERLI_DATA_AGGREGATOR", as_str(&scripted["data_aggregator"]))
+        .env("SOCKET", APP_SOCKET)
+        .stdout(Stdio::null())
+        .spawn()
+        .expect("error starting the app"));
+    wait_for(APP_SOCKET, &mut app.0);
+
+    let pair_address = as_str(&scripted["pair"]);
+
+    // The pair's metadata comes from the DataAggregator, and its exchange from the factory.
+    let pair = get(&format!("/api/pair?blockchain=ethereum&pair={pair_address}"));
+    assert_eq!(pair["error_message"], "", "{pair}");
+    assert_eq!(as_str(&pair["pair"]["address"]), pair_address);
+    assert_eq!(pair["pair"]["exchange_name"], "Uniswap V2");
+    assert_eq!(pair["base"]["symbol"], scripted["base"]);
+    assert_eq!(pair["quote"]["symbol"], scripted["quote"]);
+
+    // Once saved, the pair can be found by either token's symbol.
+    let search = get(&format!("/api/search?blockchain=ethereum&q={}", as_str(&scripted["quote"])));
+    assert_eq!(search["error_message"], "", "{search}");
+    assert_eq!(as_str(&search["pairs"][0]["address"]), pair_address);
+
+    // And its factory resolves it from its two tokens.
+    let resolved = get(&format!(
+        "/api/pair/resolve?blockchain=ethereum&token_a={}&token_b={}",
+        as_str(&pair["base"]["address"]),
+        as_str(&pair["quote"]["address"])));
+    assert_eq!(resolved["error_message"], "", "{resolved}");
+    assert_eq!(as_str(&resolved["pairs"][0]["address"]), pair_address);
+    assert_eq!(resolved["pairs"][0]["exchange_name"], "Uniswap V2");
+
+    // The first request starts a scan; poll until it's complete.
+    let path = format!("/api/sandwiches?blockchain=ethereum&pair={pair_address}");
+    let start = Instant::now();
+    let sandwiches = loop {
+        let response = get(&path);
+        assert_eq!(response["error_message"], "", "{response}");
+
+        if response["scan_metadata"]["complete"] == true {
+            break response["sandwiches"].clone();
+        }
+
+        assert!(start.elapsed() < TIMEOUT, "the scan did not complete: {response}");
+        thread::sleep(Duration::from_millis(500));
+    };
+
+    let expected = scripted["blocks"].as_array().unwrap().iter()
+        .flat_map(|block| block["sandwiches"].as_array().unwrap().iter().map(move |sandwich| (
+            block["block_number"].as_i64().unwrap(),
+            as_str(&sandwich["frontrun"]),
+            sandwich["lunchmeat"].as_array().unwrap().iter().map(as_str).collect(),
+            as_str(&sandwich["backrun"]))))
+        .collect::<BTreeSet<SandwichKey>>();
+
+    let found = sandwiches.as_array().cloned().unwrap_or_default().iter()
+        .map(|sandwich| (
+            sandwich["block_number"].as_i64().unwrap(),
+            as_str(&sandwich["frontrun"]["hash"]),
+            sandwich["lunchmeat"].as_array().unwrap().iter().map(|tx| as_str(&tx["hash"])).collect(),
+            as_str(&sandwich["backrun"]["hash"])))
+        .collect::<BTreeSet<SandwichKey>>();
+
+    assert_eq!(found, expected);
+}